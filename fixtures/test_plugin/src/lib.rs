@@ -0,0 +1,25 @@
+//! A plugin loaded by winbar's tests, rendering its `label` option.
+use winbar::plugin::{Plugin, RenderCommand};
+
+struct TestPlugin {
+    label: String,
+}
+
+impl Plugin for TestPlugin {
+    fn create(options: serde_json::Value) -> Result<Self, String> {
+        let label = options["label"].as_str().ok_or("missing label")?;
+        Ok(Self {
+            label: label.to_string(),
+        })
+    }
+
+    fn render(&self) -> Vec<RenderCommand> {
+        vec![RenderCommand::text(self.label.clone())]
+    }
+
+    fn tooltip(&self) -> Option<String> {
+        Some(format!("{} tooltip", self.label))
+    }
+}
+
+winbar::export_plugin!(TestPlugin);
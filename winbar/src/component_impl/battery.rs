@@ -0,0 +1,154 @@
+use std::{sync::Arc, time::Duration};
+
+use winbar::template::{Fields, Template, Value};
+
+use crate::metrics::{BatteryState, BatteryStatus, SystemMetricsProvider};
+
+use super::metric::{MetricComponent, Sample, Sampler};
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+pub const DEFAULT_FORMAT: &str = "BAT {percent:.0}%{?charging}+{/}";
+
+const TOOLTIP_FORMAT: &str = "{percent:.0}% ({state})\
+    {?time}{?charging}\n{time|duration} until full{/}\
+    {!charging}\n{time|duration} remaining{/}{/}";
+
+/// The status of the battery, or `None` if the system has none.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Battery {
+    pub status: Option<BatteryStatus>,
+}
+
+/// Displays the charge of the battery. Nothing is displayed without a battery.
+pub type BatteryComponent = MetricComponent<Battery>;
+
+pub fn sampler(provider: Arc<dyn SystemMetricsProvider>) -> Sampler<Battery> {
+    Arc::new(move || {
+        Ok(Battery {
+            status: provider.battery()?,
+        })
+    })
+}
+
+/// The fields available to the format. The percentage is from 0 to 100, and the time in seconds.
+impl Fields for Battery {
+    fn field(&self, name: &str) -> Option<Value> {
+        let status = self.status.as_ref()?;
+        match name {
+            "percent" => Some(status.percent.into()),
+            "state" => Some(status.state.name().into()),
+            "charging" => Some((status.state == BatteryState::Charging).into()),
+            "discharging" => Some((status.state == BatteryState::Discharging).into()),
+            "full" => Some((status.state == BatteryState::Full).into()),
+            "time" => Some(status.time_remaining?.as_secs_f64().into()),
+            _ => None,
+        }
+    }
+}
+
+impl Sample for Battery {
+    const NAME: &'static str = "battery status";
+    const DESCENDING: bool = true;
+
+    fn render(&self, format: &Template) -> String {
+        if self.status.is_none() {
+            return String::new();
+        }
+
+        format.render(self)
+    }
+
+    /// The charge, state and estimated time until the battery is empty or full.
+    fn tooltip(&self) -> Option<String> {
+        self.status.as_ref()?;
+        let template = Template::parse(TOOLTIP_FORMAT).ok()?;
+        Some(template.render(self))
+    }
+
+    /// The charge while discharging, so that a low battery isn't a warning while it charges.
+    fn level(&self) -> Option<f64> {
+        let status = self.status.as_ref()?;
+        (status.state == BatteryState::Discharging).then_some(status.percent)
+    }
+
+    /// `charging` or `full` when plugged in.
+    fn state(&self) -> Option<&'static str> {
+        let status = self.status.as_ref()?;
+        (status.state != BatteryState::Discharging).then(|| status.state.name())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use winbar::styles::StyleOptions;
+
+    use crate::{component_impl::metric::Thresholds, metrics::mock::MockMetricsProvider};
+
+    use super::*;
+
+    fn battery(percent: f64, state: BatteryState, minutes: Option<u64>) -> Battery {
+        Battery {
+            status: Some(BatteryStatus {
+                percent,
+                state,
+                time_remaining: minutes.map(|minutes| Duration::from_secs(minutes * 60)),
+            }),
+        }
+    }
+
+    #[test]
+    fn format_exposes_charge_and_state() {
+        let render =
+            |battery: &Battery, format: &str| battery.render(&Template::parse(format).unwrap());
+        let discharging = battery(42.4, BatteryState::Discharging, Some(65));
+        let charging = battery(80.0, BatteryState::Charging, None);
+
+        assert_eq!(render(&discharging, DEFAULT_FORMAT), "BAT 42%");
+        assert_eq!(render(&charging, DEFAULT_FORMAT), "BAT 80%+");
+        assert_eq!(
+            render(
+                &discharging,
+                "{state|icon(charging=C,full=F,_=B)}{percent|icon(0,1,2,3,4)} {time|duration}"
+            ),
+            "B2 1h 5m"
+        );
+        assert_eq!(render(&Battery::default(), DEFAULT_FORMAT), "");
+
+        assert_eq!(
+            discharging.tooltip().as_deref(),
+            Some("42% (discharging)\n1h 5m remaining")
+        );
+        assert_eq!(charging.tooltip().as_deref(), Some("80% (charging)"));
+        assert_eq!(Battery::default().tooltip(), None);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn low_battery_is_styled_only_while_discharging() {
+        let provider = Arc::new(MockMetricsProvider::default());
+        let component = BatteryComponent::new(
+            sampler(provider.clone()),
+            Template::parse(DEFAULT_FORMAT).unwrap(),
+            DEFAULT_INTERVAL,
+            Thresholds {
+                warning: Some(20.0),
+                critical: Some(10.0),
+            },
+            StyleOptions::default().into(),
+        );
+
+        let set = |battery: Battery| *provider.battery.lock().unwrap() = battery.status;
+
+        set(battery(15.0, BatteryState::Discharging, None));
+        assert!(component.sample().await);
+        assert_eq!(component.state(), Some("warning"));
+        set(battery(5.0, BatteryState::Charging, None));
+        assert!(component.sample().await);
+        assert_eq!(component.state(), Some("charging"));
+        set(battery(5.0, BatteryState::Discharging, None));
+        assert!(component.sample().await);
+        assert_eq!(component.state(), Some("critical"));
+        set(battery(100.0, BatteryState::Full, None));
+        assert!(component.sample().await);
+        assert_eq!(component.state(), Some("full"));
+    }
+}
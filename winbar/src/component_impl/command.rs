@@ -0,0 +1,81 @@
+//! Running programs on behalf of components, such that a program that hangs can't hang the
+//! component with it.
+use std::{
+    io::Read,
+    process::{Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+
+/// How long programs run by components may take before they're killed.
+pub const TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a running program is checked for having exited.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Runs the command without a console window, returning its standard output. The program is killed
+/// if it hasn't exited within the timeout.
+pub fn output(cmd: &mut Command, timeout: Duration) -> Result<Vec<u8>> {
+    cmd.stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        use windows::Win32::System::Threading::CREATE_NO_WINDOW;
+
+        cmd.creation_flags(CREATE_NO_WINDOW.0);
+    }
+
+    let program = cmd.get_program().to_string_lossy().into_owned();
+    let mut child = cmd
+        .spawn()
+        .with_context(|| format!("Could not run {}", program))?;
+
+    // the output is read on its own thread, since a program filling the pipe blocks until it's read
+    let mut stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| anyhow!("Could not read the output of {}", program))?;
+    let reader = thread::spawn(move || {
+        let mut output = Vec::new();
+        stdout.read_to_end(&mut output).map(|_| output)
+    });
+
+    let deadline = Instant::now() + timeout;
+    while child.try_wait()?.is_none() {
+        if Instant::now() >= deadline {
+            let _ = child.kill();
+            let _ = child.wait();
+            bail!("{} did not exit within {:?}", program, timeout);
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+
+    reader
+        .join()
+        .map_err(|_| anyhow!("Could not read the output of {}", program))?
+        .with_context(|| format!("Could not read the output of {}", program))
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use super::*;
+
+    #[test]
+    fn output_is_returned() {
+        let output = output(Command::new("echo").arg("hello"), TIMEOUT).unwrap();
+        assert_eq!(output, b"hello\n");
+    }
+
+    #[test]
+    fn program_running_too_long_is_killed() {
+        let started = Instant::now();
+        let result = output(Command::new("sleep").arg("10"), Duration::from_millis(100));
+
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
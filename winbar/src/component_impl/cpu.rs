@@ -0,0 +1,136 @@
+use std::{sync::Arc, time::Duration};
+
+use winbar::template::{Fields, Template, Value};
+
+use crate::metrics::{CpuUsage, SystemMetricsProvider};
+
+use super::metric::{MetricComponent, Sample, Sampler};
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(2);
+pub const DEFAULT_FORMAT: &str = "CPU {usage:.0}%";
+
+/// Displays the CPU usage.
+pub type CpuComponent = MetricComponent<CpuUsage>;
+
+pub fn sampler(provider: Arc<dyn SystemMetricsProvider>) -> Sampler<CpuUsage> {
+    Arc::new(move || provider.cpu_usage())
+}
+
+/// The fields available to the format. Usages are percentages from 0 to 100.
+///
+/// - `usage`: the total usage
+/// - `core0`, `core1`, ...: the usage of each core
+/// - `cores`: the usage of each core, rounded and separated by spaces
+/// - `core_count`: the number of cores
+impl Fields for CpuUsage {
+    fn field(&self, name: &str) -> Option<Value> {
+        match name {
+            "usage" => Some(self.total.into()),
+            "cores" => Some(
+                self.cores
+                    .iter()
+                    .map(|core| format!("{:.0}", core))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+                    .into(),
+            ),
+            "core_count" => Some((self.cores.len() as f64).into()),
+            _ => {
+                let index = name.strip_prefix("core")?.parse::<usize>().ok()?;
+                self.cores.get(index).map(|core| (*core).into())
+            }
+        }
+    }
+}
+
+impl Sample for CpuUsage {
+    const NAME: &'static str = "CPU usage";
+
+    fn render(&self, format: &Template) -> String {
+        format.render(self)
+    }
+
+    /// The usage of each core.
+    fn tooltip(&self) -> Option<String> {
+        let lines = self
+            .cores
+            .iter()
+            .enumerate()
+            .map(|(index, core)| format!("Core {}: {:.0}%", index, core))
+            .collect::<Vec<String>>();
+
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
+    fn level(&self) -> Option<f64> {
+        Some(self.total)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+
+    use winbar::{styles::StyleOptions, util::rect::Rect, Component, WinbarAction, WinbarContext};
+    use windows::Win32::Foundation::HWND;
+
+    use crate::{component_impl::metric::Thresholds, metrics::mock::MockMetricsProvider};
+
+    use super::*;
+
+    fn usage() -> CpuUsage {
+        CpuUsage {
+            total: 42.4,
+            cores: vec![80.6, 4.2],
+        }
+    }
+
+    #[test]
+    fn format_exposes_total_and_core_usage() {
+        let usage = usage();
+        let render = |format: &str| Template::parse(format).unwrap().render(&usage);
+
+        assert_eq!(render(DEFAULT_FORMAT), "CPU 42%");
+        assert_eq!(render("{core0:.0} {core1:.1} {core2}"), "81 4.2 ");
+        assert_eq!(render("{cores} ({core_count})"), "81 4 (2)");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn samples_and_redraws_on_change() {
+        let provider = Arc::new(MockMetricsProvider::default());
+        let component = Arc::new(CpuComponent::new(
+            sampler(provider.clone()),
+            Template::parse(DEFAULT_FORMAT).unwrap(),
+            Duration::from_millis(10),
+            Thresholds::default(),
+            StyleOptions::default().into(),
+        ));
+        assert_eq!(component.text(), "");
+
+        // failed samples are skipped
+        assert!(!component.sample().await);
+
+        *provider.cpu.lock().unwrap() = Some(usage());
+        let (send, recv) = mpsc::channel();
+        let ctx = WinbarContext::new(send);
+        let handle = tokio::spawn({
+            let component = component.clone();
+            let ctx = ctx.clone();
+            async move { component.start(ctx, HWND(0), Rect::default()).await }
+        });
+        assert!(matches!(
+            recv.recv_timeout(Duration::from_secs(5)),
+            Ok(WinbarAction::UpdateWindow)
+        ));
+        assert_eq!(component.text(), "CPU 42%");
+        assert_eq!(
+            component.tooltip().as_deref(),
+            Some("Core 0: 81%\nCore 1: 4%")
+        );
+
+        // unchanged samples don't redraw
+        assert!(recv.recv_timeout(Duration::from_millis(100)).is_err());
+
+        handle.abort();
+    }
+}
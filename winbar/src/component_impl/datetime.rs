@@ -1,80 +1,366 @@
-use std::{sync::Arc, time::Duration};
+use std::{
+    fmt::Display,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
 
+use anyhow::Result;
 use async_trait::async_trait;
-use chrono::Local;
-use tokio::time::{self};
+use chrono::{
+    format::{Fixed, Item, Numeric, StrftimeItems},
+    DateTime, Datelike, Local, NaiveDate, TimeZone, Timelike,
+};
 use winbar::{
-    styles::{StyleOptions, Styles},
+    popup::{PopupContent, PopupSpan},
+    styles::{ComponentStyles, StyleOptions, Styles},
+    template::{Fields, Template, Value},
     util::rect::Rect,
-    Component, WinbarAction, WinbarContext,
-};
-use windows::Win32::{
-    Foundation::{HWND, SIZE},
-    Graphics::Gdi::{DrawTextW, GetTextExtentPoint32W, DT_CENTER, DT_SINGLELINE, DT_VCENTER, HDC},
+    Component, WinbarContext,
 };
+use windows::Win32::{Foundation::HWND, Graphics::Gdi::HDC};
+
+use crate::scheduler::Ticker;
+
+/// The format of the tooltip, i.e., the full date.
+const TOOLTIP_FORMAT: &str = "%A, %B %-d, %Y";
+
+/// The format of the component: a strftime format, or a [`Template`] of the [`TimeFields`] if it
+/// contains braces.
+enum TimeFormat {
+    Strftime(String),
+    Template(Template),
+}
+
+impl TimeFormat {
+    fn parse(format: String) -> Result<Self> {
+        if format.contains('{') {
+            Ok(Self::Template(Template::parse(&format)?))
+        } else {
+            Ok(Self::Strftime(format))
+        }
+    }
+
+    fn format(&self, time: DateTime<Local>) -> String {
+        match self {
+            Self::Strftime(format) => time.format(format).to_string(),
+            Self::Template(template) => template.render(&TimeFields(time)),
+        }
+    }
+
+    fn granularity(&self) -> Granularity {
+        let displays_seconds = match self {
+            Self::Strftime(format) => StrftimeItems::new(format).any(|item| {
+                matches!(
+                    item,
+                    Item::Numeric(
+                        Numeric::Second | Numeric::Nanosecond | Numeric::Timestamp,
+                        _
+                    ) | Item::Fixed(
+                        Fixed::Nanosecond
+                                | Fixed::Nanosecond3
+                                | Fixed::Nanosecond6
+                                | Fixed::Nanosecond9
+                                | Fixed::RFC2822
+                                | Fixed::RFC3339
+                                // internal items are sub-second precise
+                                | Fixed::Internal(_)
+                    )
+                )
+            }),
+            Self::Template(template) => return Granularity::of_template(template),
+        };
+
+        if displays_seconds {
+            Granularity::Second
+        } else {
+            Granularity::Minute
+        }
+    }
+}
+
+/// The finest unit of time displayed by a format, which updates are aligned to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Granularity {
+    Second,
+    Minute,
+}
+
+impl Granularity {
+    /// The granularity of a template of the [`TimeFields`].
+    pub fn of_template(template: &Template) -> Self {
+        let displays_seconds = template
+            .fields()
+            .iter()
+            .any(|field| matches!(*field, "second" | "timestamp"));
+
+        if displays_seconds {
+            Self::Second
+        } else {
+            Self::Minute
+        }
+    }
 
-use crate::windows_api::WindowsApi;
+    pub fn period(self) -> Duration {
+        match self {
+            Self::Second => Duration::from_secs(1),
+            Self::Minute => Duration::from_secs(60),
+        }
+    }
+}
+
+/// The fields of a time exposed to templates.
+pub struct TimeFields<Tz: TimeZone>(pub DateTime<Tz>);
+
+impl<Tz: TimeZone> Fields for TimeFields<Tz>
+where
+    Tz::Offset: Display,
+{
+    fn field(&self, name: &str) -> Option<Value> {
+        let time = &self.0;
+        Some(match name {
+            "year" => time.year().into(),
+            "month" => time.month().into(),
+            "day" => time.day().into(),
+            "hour" => time.hour().into(),
+            "hour12" => time.hour12().1.into(),
+            "minute" => time.minute().into(),
+            "second" => time.second().into(),
+            "am_pm" => time.format("%p").to_string().into(),
+            "weekday" => time.format("%A").to_string().into(),
+            "weekday_short" => time.format("%a").to_string().into(),
+            "month_name" => time.format("%B").to_string().into(),
+            "month_short" => time.format("%b").to_string().into(),
+            "timestamp" => time.timestamp().into(),
+            _ => return None,
+        })
+    }
+}
 
 pub struct DateTimeComponent {
-    pub format: String,
-    pub styles: Arc<StyleOptions>,
+    format: TimeFormat,
+    alt_format: Option<TimeFormat>,
+    /// How often the time is updated, aligned to the wall clock.
+    interval: Duration,
+    styles: ComponentStyles,
+    /// Whether the alternative format is used.
+    toggled: AtomicBool,
 }
 
 impl DateTimeComponent {
-    pub fn new(format: String, styles: StyleOptions) -> Self {
-        Self {
+    /// Creates the component, updating the time at the interval if given, or else whenever the
+    /// finest unit of time displayed by either format changes.
+    pub fn new(
+        format: String,
+        alt_format: Option<String>,
+        interval: Option<Duration>,
+        styles: ComponentStyles,
+    ) -> Result<Self> {
+        let format = TimeFormat::parse(format)?;
+        let alt_format = alt_format.map(TimeFormat::parse).transpose()?;
+        let granularity = alt_format
+            .iter()
+            .map(TimeFormat::granularity)
+            .fold(format.granularity(), Granularity::min);
+
+        Ok(Self {
             format,
-            styles: Arc::new(styles),
+            alt_format,
+            interval: interval.unwrap_or(granularity.period()),
+            styles,
+            toggled: AtomicBool::new(false),
+        })
+    }
+
+    fn format(&self) -> &TimeFormat {
+        match &self.alt_format {
+            Some(alt_format) if self.toggled.load(Ordering::SeqCst) => alt_format,
+            _ => &self.format,
         }
     }
+
+    fn formatted_time(&self) -> String {
+        self.format().format(Local::now())
+    }
 }
 
 #[async_trait]
 impl Component for DateTimeComponent {
     fn styles(&self) -> Arc<StyleOptions> {
-        self.styles.clone()
+        self.styles.resolve(&self.formatted_time())
     }
 
-    fn width(&self, _hwnd: HWND, hdc: HDC) -> i32 {
-        let time = Local::now();
-        let formatted_time = time.format(&self.format).to_string();
+    fn set_state(&self, state: Option<String>) {
+        self.styles.set_state(state);
+    }
 
-        unsafe {
-            let mut length: SIZE = SIZE::default();
+    fn tooltip(&self) -> Option<String> {
+        Some(Local::now().format(TOOLTIP_FORMAT).to_string())
+    }
 
-            GetTextExtentPoint32W(
-                hdc,
-                &WindowsApi::str_to_u16_slice(&formatted_time),
-                &mut length,
-            );
+    /// A calendar of the current month, with today emphasized.
+    fn popup(&self) -> Option<PopupContent> {
+        Some(calendar(Local::now().date_naive()))
+    }
 
-            length.cx + self.styles.padding_x * 2
-        }
+    fn toggle_format(&self) {
+        self.toggled.fetch_xor(true, Ordering::SeqCst);
+    }
+
+    fn width(&self, _hwnd: HWND, hdc: HDC) -> i32 {
+        let formatted_time = self.formatted_time();
+        let styles = self.styles.resolve(&formatted_time);
+
+        Styles::text_component_width(hdc, &formatted_time, &styles)
     }
 
     fn draw(&self, _hwnd: HWND, rect: Rect, hdc: HDC) {
-        let time = Local::now();
-        let formatted = time.format(&self.format).to_string();
-
-        Styles::draw_rect(hdc, &rect, &self.styles.border_style);
-        unsafe {
-            DrawTextW(
-                hdc,
-                &mut WindowsApi::str_to_u16_slice(&formatted),
-                &mut rect.into(),
-                DT_SINGLELINE | DT_VCENTER | DT_CENTER,
-            );
-        }
+        let formatted = self.formatted_time();
+        let styles = self.styles.resolve(&formatted);
+
+        Styles::draw_rect(hdc, &rect, &styles);
+        Styles::draw_text(hdc, &rect, &formatted, &styles);
     }
 
     async fn start(&self, ctx: WinbarContext, _hwnd: HWND, _rect: Rect) {
-        let mut interval = time::interval(Duration::from_millis(500));
+        let mut ticker = Ticker::aligned(self.interval);
+        let mut last = None;
         loop {
             // first tick completes immediately
-            interval.tick().await;
-            if let Err(e) = ctx.sender().send(WinbarAction::UpdateWindow) {
+            ticker.tick().await;
+
+            // only redraw when what's displayed changed
+            let formatted = Some(self.formatted_time());
+            if formatted == last {
+                continue;
+            }
+            last = formatted;
+
+            if let Err(e) = ctx.request_redraw() {
                 tracing::error!("Could not send update window action over channel: {}", e);
             }
         }
     }
 }
+
+/// The width of a calendar, i.e., seven two character wide days separated by spaces.
+const CALENDAR_WIDTH: usize = 20;
+
+/// Creates a calendar of the month of the given day, starting weeks on Monday, with the day
+/// emphasized.
+fn calendar(today: NaiveDate) -> PopupContent {
+    let title = today.format("%B %Y").to_string();
+    let mut lines = vec![
+        vec![PopupSpan::plain(format!(
+            "{:^width$}",
+            title,
+            width = CALENDAR_WIDTH
+        ))],
+        vec![PopupSpan::plain("Mo Tu We Th Fr Sa Su")],
+    ];
+
+    let first = today.with_day(1).unwrap_or(today);
+    let offset = first.weekday().num_days_from_monday() as usize;
+    let days = first
+        .iter_days()
+        .take_while(|day| day.month() == today.month())
+        .collect::<Vec<NaiveDate>>();
+
+    let mut cells = vec![None; offset];
+    cells.extend(days.into_iter().map(Some));
+    for week in cells.chunks(7) {
+        let mut spans = Vec::new();
+        let mut plain = String::new();
+        for (index, day) in week.iter().enumerate() {
+            if index > 0 {
+                plain.push(' ');
+            }
+
+            match day {
+                Some(day) if *day == today => {
+                    spans.push(PopupSpan::plain(std::mem::take(&mut plain)));
+                    spans.push(PopupSpan::emphasized(format!("{:>2}", day.day())));
+                }
+                Some(day) => plain.push_str(&format!("{:>2}", day.day())),
+                None => plain.push_str("  "),
+            }
+        }
+        spans.push(PopupSpan::plain(plain));
+        spans.retain(|span| !span.text.is_empty());
+
+        lines.push(spans);
+    }
+
+    PopupContent { lines }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn formats_support_strftime_and_templates() {
+        let time = Local.with_ymd_and_hms(2026, 10, 18, 9, 5, 0).unwrap();
+
+        let format = TimeFormat::parse("%F %H:%M".to_string()).unwrap();
+        assert_eq!(format.format(time), "2026-10-18 09:05");
+
+        let format = TimeFormat::parse(
+            "{weekday_short} {day} {month_short} {hour12}:{minute:0>2} {am_pm}".to_string(),
+        )
+        .unwrap();
+        assert_eq!(format.format(time), "Sun 18 Oct 9:05 AM");
+
+        assert!(TimeFormat::parse("{hour|unknown}".to_string()).is_err());
+    }
+
+    #[test]
+    fn granularity_is_finest_unit_displayed() {
+        let granularity =
+            |format: &str| TimeFormat::parse(format.to_string()).unwrap().granularity();
+
+        assert_eq!(granularity("%F %r"), Granularity::Second);
+        assert_eq!(granularity("%H:%M:%S"), Granularity::Second);
+        assert_eq!(granularity("%s"), Granularity::Second);
+        assert_eq!(granularity("%F %H:%M"), Granularity::Minute);
+        assert_eq!(granularity("%A"), Granularity::Minute);
+        assert_eq!(granularity("{hour}:{minute:0>2}"), Granularity::Minute);
+        assert_eq!(granularity("{?second}{second}{/}"), Granularity::Second);
+
+        let component = DateTimeComponent::new(
+            "%H:%M".to_string(),
+            Some("%T".to_string()),
+            None,
+            StyleOptions::default().into(),
+        )
+        .unwrap();
+        assert_eq!(component.interval, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn calendar_works() {
+        let content = calendar(NaiveDate::from_ymd_opt(2026, 10, 18).unwrap());
+
+        assert_eq!(
+            content.text_lines(),
+            vec![
+                "    October 2026    ",
+                "Mo Tu We Th Fr Sa Su",
+                "          1  2  3  4",
+                " 5  6  7  8  9 10 11",
+                "12 13 14 15 16 17 18",
+                "19 20 21 22 23 24 25",
+                "26 27 28 29 30 31",
+            ]
+        );
+        assert_eq!(
+            content.lines[4],
+            vec![
+                PopupSpan::plain("12 13 14 15 16 17 "),
+                PopupSpan::emphasized("18"),
+            ]
+        );
+    }
+}
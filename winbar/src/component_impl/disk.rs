@@ -0,0 +1,215 @@
+use std::{path::PathBuf, sync::Arc, time::Duration};
+
+use winbar::template::{Fields, Template, Value};
+
+use crate::metrics::{DiskUsage, SystemMetricsProvider};
+
+use super::metric::{MetricComponent, Sample, Sampler};
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+pub const DEFAULT_FORMAT: &str = "{label} {?missing}missing{/}{!missing}{percent:.0}%{/}";
+pub const DEFAULT_SEPARATOR: &str = "  ";
+
+const TOOLTIP_FORMAT: &str = "{label}: {?missing}missing{/}\
+    {!missing}{free|bytes} free of {total|bytes} ({percent:.0}% used){/}";
+
+/// A drive or path whose file system is displayed.
+pub struct Drive {
+    pub path: PathBuf,
+    pub label: String,
+    /// The format of the drive, instead of the component's format.
+    pub format: Option<Template>,
+}
+
+/// The drives displayed by a disk component.
+pub struct Drives {
+    pub drives: Vec<Drive>,
+    /// The text between drives.
+    pub separator: String,
+    /// Whether drives that can't be read (e.g., unplugged drives) are hidden.
+    pub hide_missing: bool,
+}
+
+/// The usage of each drive, or `None` for drives that are missing.
+#[derive(Clone)]
+pub struct DisksUsage {
+    drives: Arc<Drives>,
+    usages: Vec<Option<DiskUsage>>,
+}
+
+impl DisksUsage {
+    fn fields(&self) -> impl Iterator<Item = DriveFields<'_>> {
+        self.drives
+            .drives
+            .iter()
+            .zip(&self.usages)
+            .map(|(drive, usage)| DriveFields {
+                drive,
+                usage: usage.as_ref(),
+            })
+    }
+}
+
+/// Displays the usage of the file systems of drives or paths.
+pub type DiskComponent = MetricComponent<DisksUsage>;
+
+pub fn sampler(
+    provider: Arc<dyn SystemMetricsProvider>,
+    drives: Arc<Drives>,
+) -> Sampler<DisksUsage> {
+    Arc::new(move || {
+        let usages = drives
+            .drives
+            .iter()
+            .map(|drive| match provider.disk_usage(&drive.path) {
+                Ok(usage) => Some(usage),
+                Err(e) => {
+                    tracing::debug!("Drive {} is missing: {:#}", drive.path.display(), e);
+                    None
+                }
+            })
+            .collect();
+
+        Ok(DisksUsage {
+            drives: drives.clone(),
+            usages,
+        })
+    })
+}
+
+/// The fields of a drive available to the format. Sizes are in bytes, and the percentage is from 0
+/// to 100. Missing drives only have their label, path and `missing`.
+struct DriveFields<'a> {
+    drive: &'a Drive,
+    usage: Option<&'a DiskUsage>,
+}
+
+impl Fields for DriveFields<'_> {
+    fn field(&self, name: &str) -> Option<Value> {
+        match name {
+            "label" => return Some(self.drive.label.as_str().into()),
+            "path" => return Some(self.drive.path.display().to_string().into()),
+            "missing" => return Some(self.usage.is_none().into()),
+            _ => {}
+        }
+
+        let usage = self.usage?;
+        let value = match name {
+            "used" => usage.used() as f64,
+            "free" => usage.free as f64,
+            "total" => usage.total as f64,
+            "percent" => usage.percent(),
+            _ => return None,
+        };
+        Some(value.into())
+    }
+}
+
+impl Sample for DisksUsage {
+    const NAME: &'static str = "disk usage";
+
+    /// The drives rendered with their own format or the component's, separated by the separator.
+    fn render(&self, format: &Template) -> String {
+        self.fields()
+            .filter(|fields| !self.drives.hide_missing || fields.usage.is_some())
+            .map(|fields| {
+                fields
+                    .drive
+                    .format
+                    .as_ref()
+                    .unwrap_or(format)
+                    .render(&fields)
+            })
+            .collect::<Vec<String>>()
+            .join(&self.drives.separator)
+    }
+
+    /// The free and total space of every drive, including hidden ones.
+    fn tooltip(&self) -> Option<String> {
+        let template = Template::parse(TOOLTIP_FORMAT).ok()?;
+        let lines = self
+            .fields()
+            .map(|fields| template.render(&fields))
+            .collect::<Vec<String>>();
+
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+
+    /// The usage of the fullest drive.
+    fn level(&self) -> Option<f64> {
+        self.usages
+            .iter()
+            .flatten()
+            .map(DiskUsage::percent)
+            .reduce(f64::max)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::metrics::mock::MockMetricsProvider;
+
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn drives(hide_missing: bool) -> Arc<Drives> {
+        let drive = |path: &str, format: Option<&str>| Drive {
+            path: PathBuf::from(path),
+            label: path.to_string(),
+            format: format.map(|format| Template::parse(format).unwrap()),
+        };
+
+        Arc::new(Drives {
+            drives: vec![
+                drive("C:\\", None),
+                drive("D:\\", Some("{label} {free|bytes:.0}")),
+                drive("E:\\", None),
+            ],
+            separator: DEFAULT_SEPARATOR.to_string(),
+            hide_missing,
+        })
+    }
+
+    fn sample(hide_missing: bool) -> DisksUsage {
+        let provider = Arc::new(MockMetricsProvider::default());
+        {
+            let mut disks = provider.disks.lock().unwrap();
+            let usage = |total, free| DiskUsage {
+                total: total * GIB,
+                free: free * GIB,
+            };
+            disks.insert(PathBuf::from("C:\\"), usage(100, 25));
+            disks.insert(PathBuf::from("D:\\"), usage(1000, 900));
+        }
+
+        sampler(provider, drives(hide_missing))().unwrap()
+    }
+
+    #[test]
+    fn drives_use_their_own_format() {
+        let format = Template::parse(DEFAULT_FORMAT).unwrap();
+        assert_eq!(
+            sample(false).render(&format),
+            "C:\\ 75%  D:\\ 900 GiB  E:\\ missing"
+        );
+        assert_eq!(sample(true).render(&format), "C:\\ 75%  D:\\ 900 GiB");
+    }
+
+    #[test]
+    fn level_is_usage_of_fullest_drive() {
+        assert_eq!(sample(true).level(), Some(75.0));
+    }
+
+    #[test]
+    fn tooltip_lists_all_drives() {
+        assert_eq!(
+            sample(true).tooltip().as_deref(),
+            Some(
+                "C:\\: 25.0 GiB free of 100.0 GiB (75% used)\n\
+                 D:\\: 900.0 GiB free of 1000.0 GiB (10% used)\n\
+                 E:\\: missing"
+            )
+        );
+    }
+}
@@ -0,0 +1,551 @@
+//! Workspaces of the [komorebi](https://github.com/LGUG2Z/komorebi) tiling window manager.
+//!
+//! komorebi sends its state to subscribers through a named pipe created by the subscriber. The
+//! notifications are JSON objects written one after another, without delimiters.
+use std::{
+    process::Command,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::io::{AsyncRead, AsyncReadExt};
+use winbar::{
+    input::{MouseButton, Point},
+    styles::{ComponentStyles, StyleOptions, Styles},
+    util::rect::Rect,
+    Component, WinbarContext,
+};
+use windows::Win32::{Foundation::HWND, Graphics::Gdi::HDC};
+
+use crate::container::SelectedStyles;
+
+pub const DEFAULT_PIPE: &str = "winbar";
+
+/// How long komorebi has to connect to the pipe after subscribing.
+#[cfg(windows)]
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+/// The delay before subscribing again after the connection failed or was closed.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+const READ_BUFFER_SIZE: usize = 64 * 1024;
+
+/// A workspace of the displayed monitor.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Workspace {
+    /// The name of the workspace, or its number if it has none.
+    pub name: String,
+    pub focused: bool,
+    /// Whether the workspace has any windows.
+    pub occupied: bool,
+}
+
+impl Workspace {
+    /// The state the workspace's cell is styled with.
+    pub fn state(&self) -> &'static str {
+        if self.focused {
+            "focused"
+        } else if self.occupied {
+            "occupied"
+        } else {
+            "empty"
+        }
+    }
+}
+
+/// A notification sent by komorebi after every event, with its state after the event.
+#[derive(Deserialize)]
+pub struct Notification {
+    state: State,
+}
+
+/// The parts of komorebi's state needed for the workspaces. Everything else is ignored.
+#[derive(Deserialize)]
+struct State {
+    monitors: Ring<Monitor>,
+}
+
+/// komorebi's list of elements with a focused element.
+#[derive(Deserialize)]
+struct Ring<T> {
+    elements: Vec<T>,
+    #[serde(default)]
+    focused: usize,
+}
+
+#[derive(Deserialize)]
+struct Monitor {
+    workspaces: Ring<KomorebiWorkspace>,
+}
+
+#[derive(Deserialize)]
+struct KomorebiWorkspace {
+    #[serde(default)]
+    name: Option<String>,
+    containers: Ring<Value>,
+    #[serde(default)]
+    monocle_container: Option<Value>,
+    #[serde(default)]
+    maximized_window: Option<Value>,
+    /// A list of windows, or a ring of them in newer versions.
+    #[serde(default)]
+    floating_windows: Option<Value>,
+}
+
+impl KomorebiWorkspace {
+    fn occupied(&self) -> bool {
+        let floating = match &self.floating_windows {
+            Some(Value::Array(windows)) => !windows.is_empty(),
+            Some(Value::Object(ring)) => ring
+                .get("elements")
+                .and_then(Value::as_array)
+                .is_some_and(|windows| !windows.is_empty()),
+            _ => false,
+        };
+
+        !self.containers.elements.is_empty()
+            || self.monocle_container.is_some()
+            || self.maximized_window.is_some()
+            || floating
+    }
+}
+
+impl Notification {
+    /// The workspaces of the monitor at the index, or none if there is no such monitor.
+    pub fn workspaces(&self, monitor: usize) -> Vec<Workspace> {
+        let Some(monitor) = self.state.monitors.elements.get(monitor) else {
+            return Vec::new();
+        };
+
+        let workspaces = &monitor.workspaces;
+        workspaces
+            .elements
+            .iter()
+            .enumerate()
+            .map(|(index, workspace)| Workspace {
+                name: workspace
+                    .name
+                    .clone()
+                    .unwrap_or_else(|| (index + 1).to_string()),
+                focused: index == workspaces.focused,
+                occupied: workspace.occupied(),
+            })
+            .collect()
+    }
+}
+
+/// Splits the bytes received from komorebi into notifications.
+#[derive(Default)]
+pub struct NotificationBuffer {
+    buffer: Vec<u8>,
+}
+
+impl NotificationBuffer {
+    /// Adds the received bytes, returning the notifications completed by them. Invalid
+    /// notifications are returned as errors, and the bytes received so far are discarded.
+    pub fn push(&mut self, bytes: &[u8]) -> Vec<Result<Notification>> {
+        self.buffer.extend_from_slice(bytes);
+
+        let mut notifications = Vec::new();
+        let mut stream = serde_json::Deserializer::from_slice(&self.buffer).into_iter();
+        loop {
+            match stream.next() {
+                Some(Ok(notification)) => notifications.push(Ok(notification)),
+                // the rest of the notification hasn't been received yet
+                Some(Err(e)) if e.is_eof() => break,
+                Some(Err(e)) => {
+                    notifications.push(Err(e).context("Invalid komorebi notification"));
+                    self.buffer.clear();
+                    return notifications;
+                }
+                None => break,
+            }
+        }
+
+        let consumed = stream.byte_offset();
+        self.buffer.drain(..consumed);
+        notifications
+    }
+}
+
+/// Reads notifications until the stream is closed, calling `on_workspaces` with the workspaces of
+/// the monitor after each one.
+pub async fn read_notifications<R, F>(
+    mut reader: R,
+    monitor: usize,
+    mut on_workspaces: F,
+) -> Result<()>
+where
+    R: AsyncRead + Unpin,
+    F: FnMut(Vec<Workspace>),
+{
+    let mut buffer = NotificationBuffer::default();
+    let mut chunk = vec![0; READ_BUFFER_SIZE];
+    loop {
+        let read = reader.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(());
+        }
+
+        for notification in buffer.push(&chunk[..read]) {
+            match notification {
+                Ok(notification) => on_workspaces(notification.workspaces(monitor)),
+                Err(e) => tracing::warn!("{:#}", e),
+            }
+        }
+    }
+}
+
+/// Creates the pipe, subscribes komorebi to it and reads its notifications until it disconnects.
+#[cfg(windows)]
+async fn subscribe<F>(pipe: &str, monitor: usize, on_workspaces: F) -> Result<()>
+where
+    F: FnMut(Vec<Workspace>),
+{
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let path = format!(r"\\.\pipe\{}", pipe);
+    let server = ServerOptions::new()
+        .create(&path)
+        .with_context(|| format!("Could not create pipe {}", path))?;
+
+    komorebic(&["subscribe-pipe", pipe])?;
+    tokio::time::timeout(CONNECT_TIMEOUT, server.connect())
+        .await
+        .context("komorebi did not connect to the pipe")??;
+
+    read_notifications(server, monitor, on_workspaces).await
+}
+
+#[cfg(not(windows))]
+async fn subscribe<F>(_pipe: &str, _monitor: usize, _on_workspaces: F) -> Result<()>
+where
+    F: FnMut(Vec<Workspace>),
+{
+    anyhow::bail!("komorebi is only available on Windows")
+}
+
+/// Runs a komorebic command without waiting for it.
+fn komorebic(args: &[&str]) -> Result<()> {
+    let mut cmd = Command::new("komorebic");
+    cmd.args(args);
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::CommandExt;
+        use windows::Win32::System::Threading::CREATE_NO_WINDOW;
+
+        cmd.creation_flags(CREATE_NO_WINDOW.0);
+    }
+    cmd.spawn()
+        .with_context(|| format!("Could not run komorebic {}", args.join(" ")))?;
+
+    Ok(())
+}
+
+/// Displays a cell for each workspace of a monitor, styled by its state. Clicking a cell focuses
+/// its workspace.
+pub struct KomorebiComponent {
+    /// The name of the pipe komorebi sends notifications to.
+    pipe: String,
+    /// The index of the monitor whose workspaces are displayed.
+    monitor: usize,
+    styles: ComponentStyles,
+    workspaces: RwLock<Vec<Workspace>>,
+    /// The rects of the cells when last drawn, relative to the component.
+    cells: RwLock<Vec<Rect>>,
+}
+
+impl KomorebiComponent {
+    pub fn new(pipe: String, monitor: usize, styles: ComponentStyles) -> Self {
+        Self {
+            pipe,
+            monitor,
+            styles,
+            workspaces: RwLock::new(Vec::new()),
+            cells: RwLock::new(Vec::new()),
+        }
+    }
+
+    /// Sets the workspaces, returning whether they changed.
+    pub fn set_workspaces(&self, workspaces: Vec<Workspace>) -> bool {
+        let mut current = self.workspaces.write().unwrap();
+        if *current == workspaces {
+            return false;
+        }
+
+        *current = workspaces;
+        true
+    }
+
+    /// The workspaces with the styles of their state.
+    fn styled_workspaces(&self) -> Vec<(Workspace, Arc<StyleOptions>)> {
+        self.workspaces
+            .read()
+            .unwrap()
+            .iter()
+            .map(|workspace| {
+                let styles = self
+                    .styles
+                    .resolve_with_state(&workspace.name, Some(workspace.state()));
+                (workspace.clone(), styles)
+            })
+            .collect()
+    }
+
+    /// The widths of the cells, measured with their styles selected.
+    fn cell_widths(hdc: HDC, workspaces: &[(Workspace, Arc<StyleOptions>)]) -> Vec<i32> {
+        workspaces
+            .iter()
+            .map(|(workspace, styles)| {
+                let _selected = SelectedStyles::select(hdc, styles);
+                Styles::text_component_width(hdc, &workspace.name, styles)
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl Component for KomorebiComponent {
+    fn styles(&self) -> Arc<StyleOptions> {
+        self.styles.resolve("")
+    }
+
+    fn set_state(&self, state: Option<String>) {
+        self.styles.set_state(state);
+    }
+
+    fn width(&self, _hwnd: HWND, hdc: HDC) -> i32 {
+        Self::cell_widths(hdc, &self.styled_workspaces())
+            .iter()
+            .sum()
+    }
+
+    fn draw(&self, _hwnd: HWND, rect: Rect, hdc: HDC) {
+        let workspaces = self.styled_workspaces();
+        let widths = Self::cell_widths(hdc, &workspaces);
+
+        let mut cells = Vec::with_capacity(workspaces.len());
+        let mut x = 0;
+        for ((workspace, styles), width) in workspaces.iter().zip(widths) {
+            let cell = Rect {
+                x: rect.x + x,
+                y: rect.y,
+                width,
+                height: rect.height,
+            };
+            let _selected = SelectedStyles::select(hdc, styles);
+            Styles::draw_rect(hdc, &cell, styles);
+            Styles::draw_text(hdc, &cell, &workspace.name, styles);
+
+            cells.push(Rect {
+                x,
+                y: 0,
+                width,
+                height: rect.height,
+            });
+            x += width;
+        }
+
+        *self.cells.write().unwrap() = cells;
+    }
+
+    fn on_click(&self, button: MouseButton, point: Point) {
+        if button != MouseButton::Left {
+            return;
+        }
+
+        let Some(index) = self
+            .cells
+            .read()
+            .unwrap()
+            .iter()
+            .position(|cell| cell.contains(point))
+        else {
+            return;
+        };
+
+        let (monitor, workspace) = (self.monitor.to_string(), index.to_string());
+        if let Err(e) = komorebic(&["focus-monitor-workspace", &monitor, &workspace]) {
+            tracing::error!("Could not focus workspace: {:#}", e);
+        }
+    }
+
+    async fn start(&self, ctx: WinbarContext, _hwnd: HWND, _rect: Rect) {
+        loop {
+            let result = subscribe(&self.pipe, self.monitor, |workspaces| {
+                if self.set_workspaces(workspaces) {
+                    if let Err(e) = ctx.request_redraw() {
+                        tracing::error!("Could not send update window action over channel: {}", e);
+                    }
+                }
+            })
+            .await;
+            match result {
+                Ok(()) => tracing::info!("komorebi disconnected"),
+                Err(e) => tracing::warn!("Could not subscribe to komorebi: {:#}", e),
+            }
+
+            // the workspaces are unknown until komorebi connects again
+            if self.set_workspaces(Vec::new()) {
+                if let Err(e) = ctx.request_redraw() {
+                    tracing::error!("Could not send update window action over channel: {}", e);
+                }
+            }
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use tokio::{
+        io::AsyncWriteExt,
+        net::{TcpListener, TcpStream},
+    };
+
+    use super::*;
+
+    /// A notification recorded from komorebi with two monitors, trimmed to a few fields per
+    /// element.
+    const NOTIFICATION: &str = r#"{
+        "event": { "type": "FocusWorkspaceNumber", "content": 1 },
+        "state": {
+            "monitors": {
+                "elements": [
+                    {
+                        "id": 65537,
+                        "name": "DISPLAY1",
+                        "workspaces": {
+                            "elements": [
+                                {
+                                    "name": "code",
+                                    "containers": {
+                                        "elements": [{ "windows": { "elements": [{ "hwnd": 1 }], "focused": 0 } }],
+                                        "focused": 0
+                                    },
+                                    "monocle_container": null,
+                                    "maximized_window": null,
+                                    "floating_windows": [],
+                                    "layout": { "Default": "BSP" }
+                                },
+                                {
+                                    "name": null,
+                                    "containers": { "elements": [], "focused": 0 },
+                                    "monocle_container": null,
+                                    "maximized_window": null,
+                                    "floating_windows": []
+                                },
+                                {
+                                    "name": "chat",
+                                    "containers": { "elements": [], "focused": 0 },
+                                    "monocle_container": null,
+                                    "maximized_window": null,
+                                    "floating_windows": { "elements": [{ "hwnd": 2 }], "focused": 0 }
+                                }
+                            ],
+                            "focused": 1
+                        }
+                    },
+                    {
+                        "id": 65539,
+                        "name": "DISPLAY2",
+                        "workspaces": {
+                            "elements": [
+                                {
+                                    "name": "web",
+                                    "containers": { "elements": [], "focused": 0 },
+                                    "monocle_container": { "windows": { "elements": [{ "hwnd": 3 }], "focused": 0 } },
+                                    "maximized_window": null,
+                                    "floating_windows": []
+                                }
+                            ],
+                            "focused": 0
+                        }
+                    }
+                ],
+                "focused": 0
+            },
+            "is_paused": false
+        }
+    }"#;
+
+    fn workspace(name: &str, focused: bool, occupied: bool) -> Workspace {
+        Workspace {
+            name: name.to_string(),
+            focused,
+            occupied,
+        }
+    }
+
+    #[test]
+    fn parses_workspaces_of_monitor() {
+        let notification: Notification = serde_json::from_str(NOTIFICATION).unwrap();
+
+        let workspaces = notification.workspaces(0);
+        assert_eq!(
+            workspaces,
+            vec![
+                workspace("code", false, true),
+                workspace("2", true, false),
+                workspace("chat", false, true),
+            ]
+        );
+        assert_eq!(
+            workspaces.iter().map(Workspace::state).collect::<Vec<_>>(),
+            vec!["occupied", "focused", "occupied"]
+        );
+        assert_eq!(
+            notification.workspaces(1),
+            vec![workspace("web", true, true)]
+        );
+        assert_eq!(notification.workspaces(2), vec![]);
+    }
+
+    #[test]
+    fn buffer_splits_notifications() {
+        let mut buffer = NotificationBuffer::default();
+        let bytes = format!("{}{}", NOTIFICATION, NOTIFICATION);
+        let (first, rest) = bytes.as_bytes().split_at(NOTIFICATION.len() / 2);
+
+        assert!(buffer.push(first).is_empty());
+        let notifications = buffer.push(rest);
+        assert_eq!(notifications.len(), 2);
+        assert!(notifications.iter().all(Result::is_ok));
+
+        // invalid notifications are dropped
+        let notifications = buffer.push(b"{\"state\": 42}\n");
+        assert!(matches!(notifications.as_slice(), [Err(_)]));
+        assert_eq!(buffer.push(NOTIFICATION.as_bytes()).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn reads_notifications_from_socket() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+
+        // komorebi writes each notification at once, but reads may still split them
+        let writer = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(address).await.unwrap();
+            let focused_third = NOTIFICATION.replacen(r#""focused": 1"#, r#""focused": 2"#, 1);
+            for notification in [NOTIFICATION, &focused_third] {
+                let (first, rest) = notification.as_bytes().split_at(100);
+                stream.write_all(first).await.unwrap();
+                stream.flush().await.unwrap();
+                stream.write_all(rest).await.unwrap();
+            }
+        });
+
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut focused = Vec::new();
+        read_notifications(stream, 0, |workspaces| {
+            focused.push(workspaces.iter().position(|workspace| workspace.focused));
+        })
+        .await
+        .unwrap();
+        writer.await.unwrap();
+
+        assert_eq!(focused, vec![Some(1), Some(2)]);
+    }
+}
@@ -1,13 +1,30 @@
-use std::sync::{atomic::Ordering, Arc};
+use std::{
+    panic::{self, AssertUnwindSafe},
+    sync::{atomic::Ordering, Arc},
+    time::Duration,
+};
 
+use anyhow::{anyhow, Result};
 use getset::Getters;
 use serde::{Deserialize, Serialize};
-use tokio::task::LocalSet;
+use tokio::{runtime::Handle, task::JoinHandle};
+use tokio_util::sync::CancellationToken;
 use tracing::instrument;
-use winbar::{util::rect::Rect, Component, WinbarContext};
+use winbar::{
+    input::Hover,
+    protocol::ComponentStatusReport,
+    styles::{StyleOptions, Styles},
+    util::rect::Rect,
+    Component, WinbarContext,
+};
 use windows::Win32::{Foundation::HWND, Graphics::Gdi::HDC};
 
-use crate::{COMPONENT_GAP, HEIGHT, WIDTH};
+use crate::{container::SelectedStyles, popup_window::PopupRequest, COMPONENT_GAP, HEIGHT, WIDTH};
+
+use super::{
+    mouse::{self, Action, ComponentActions, MouseDispatch, MouseEvent},
+    supervisor::{self, ComponentHealth, SupervisedTask, FAILED_PLACEHOLDER},
+};
 
 #[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub enum ComponentLocation {
@@ -18,18 +35,49 @@ pub enum ComponentLocation {
 
 #[derive(Getters)]
 pub struct ComponentState {
+    #[getset(get = "pub")]
+    id: Option<String>,
     #[getset(get = "pub")]
     location_intention: ComponentLocation,
     #[getset(get = "pub")]
     location: Rect,
     #[getset(get = "pub")]
     component: Arc<dyn Component + Send + Sync>,
+    #[getset(get = "pub")]
+    health: ComponentHealth,
+    #[getset(get = "pub")]
+    actions: ComponentActions,
+    /// A static tooltip, used instead of the component's own tooltip.
+    #[getset(get = "pub")]
+    tooltip: Option<String>,
+    task: Option<ComponentTask>,
+}
+
+/// A running component task.
+struct ComponentTask {
+    token: CancellationToken,
+    handle: JoinHandle<()>,
+}
+
+/// What is needed to spawn component tasks, available once the manager has been started.
+struct Runner {
+    ctx: WinbarContext,
+    hwnd: HWND,
+    runtime: Handle,
 }
 
 pub struct ComponentManager {
     components: Vec<ComponentState>,
+    runner: Option<Runner>,
+    /// The index of the component the mouse is over.
+    hovered: Option<usize>,
+    /// Whether the component locations need to be recomputed before the next paint.
+    layout_stale: bool,
 }
 
+/// The time given to components to finish cleaning up when winbar shuts down.
+pub const COMPONENT_STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
 impl Default for ComponentManager {
     fn default() -> Self {
         Self::new()
@@ -40,6 +88,9 @@ impl ComponentManager {
     pub fn new() -> Self {
         Self {
             components: Vec::new(),
+            runner: None,
+            hovered: None,
+            layout_stale: true,
         }
     }
 
@@ -58,108 +109,840 @@ impl ComponentManager {
         self.components.iter().for_each(f);
     }
 
-    pub fn start(&mut self, ctx: WinbarContext, hwnd: HWND) -> LocalSet {
-        let set = LocalSet::new();
+    pub fn for_each_indexed<F>(&self, f: F)
+    where
+        F: FnMut(usize, &ComponentState),
+    {
+        let mut f = f;
+        self.components
+            .iter()
+            .enumerate()
+            .for_each(|(index, state)| f(index, state));
+    }
 
-        for winbar_comp in self.components.iter_mut() {
-            let component = winbar_comp.component.clone();
-            let location = winbar_comp.location;
-            let cloned_ctx = ctx.clone();
+    /// Finds the component with the given id.
+    pub fn find(&self, id: &str) -> Option<&ComponentState> {
+        self.components
+            .iter()
+            .find(|state| state.id.as_deref() == Some(id))
+    }
+
+    /// Finds the index of the component with the given id.
+    fn position(&self, id: &str) -> Result<usize> {
+        self.components
+            .iter()
+            .position(|state| state.id.as_deref() == Some(id))
+            .ok_or_else(|| anyhow!("No component with id: {}", id))
+    }
 
-            set.spawn_local(async move {
-                component.start(cloned_ctx, hwnd, location).await;
-            });
+    /// Sets the state of the component with the given id, returning its index to redraw it.
+    pub fn set_state(&self, id: &str, state: Option<String>) -> Result<usize> {
+        let index = self.position(id)?;
+        self.components[index].component.set_state(state);
+        Ok(index)
+    }
+
+    /// Starts all components on the given runtime.
+    pub fn start(&mut self, ctx: WinbarContext, hwnd: HWND, runtime: Handle) {
+        let runner = Runner { ctx, hwnd, runtime };
+
+        for (index, state) in self.components.iter_mut().enumerate() {
+            state.task = Some(Self::spawn(&runner, index, state, None, Duration::ZERO));
         }
 
-        set
+        self.runner = Some(runner);
+    }
+
+    /// Restarts the component with the given id. The component is started again once the previous
+    /// task has stopped.
+    pub fn restart(&mut self, id: &str) -> Result<()> {
+        let index = self.position(id)?;
+
+        tracing::info!("Restarting component {}", id);
+        self.restart_index(index, Duration::ZERO)
+    }
+
+    /// Marks the component at the given index as failed and restarts it after a backoff. This is
+    /// used when the component panics outside of its task (e.g., while drawing).
+    pub fn fail(&mut self, index: usize, error: String) -> Result<()> {
+        let state = self
+            .components
+            .get(index)
+            .ok_or_else(|| anyhow!("No component at index: {}", index))?;
+
+        // the failed placeholder likely has another width
+        self.layout_stale = true;
+        let failures = state.health.fail(error.clone());
+        let delay = supervisor::backoff(failures);
+        tracing::error!(
+            "Component {} panicked ({} failures), restarting in {:?}: {}",
+            state.id.as_deref().unwrap_or("<no id>"),
+            failures,
+            delay,
+            error
+        );
+
+        self.restart_index(index, delay)
+    }
+
+    fn restart_index(&mut self, index: usize, delay: Duration) -> Result<()> {
+        let runner = self
+            .runner
+            .as_ref()
+            .ok_or_else(|| anyhow!("Components have not been started"))?;
+        let state = self
+            .components
+            .get_mut(index)
+            .ok_or_else(|| anyhow!("No component at index: {}", index))?;
+
+        let previous = state.task.take().map(|task| {
+            task.token.cancel();
+            task.handle
+        });
+        state.task = Some(Self::spawn(runner, index, state, previous, delay));
+
+        Ok(())
+    }
+
+    /// Returns a report of the status of every component.
+    pub fn status_reports(&self) -> Vec<ComponentStatusReport> {
+        self.components
+            .iter()
+            .map(|state| ComponentStatusReport {
+                id: state.id.clone(),
+                status: state.health.status(),
+                failures: state.health.failures(),
+                last_error: state.health.last_error(),
+            })
+            .collect()
+    }
+
+    /// Stops all components, returning the handles of the tasks that may still be cleaning up.
+    pub fn stop_all(&mut self) -> Vec<JoinHandle<()>> {
+        self.runner = None;
+        self.components
+            .iter_mut()
+            .filter_map(|state| state.task.take())
+            .map(|task| {
+                task.token.cancel();
+                task.handle
+            })
+            .collect()
+    }
+
+    /// Spawns the supervised task of a component. If a previous task is given, the component is
+    /// only started once the previous task finishes.
+    fn spawn(
+        runner: &Runner,
+        index: usize,
+        state: &ComponentState,
+        previous: Option<JoinHandle<()>>,
+        delay: Duration,
+    ) -> ComponentTask {
+        let ctx = runner.ctx.for_component(index);
+        let token = ctx.cancellation_token().clone();
+        let task = SupervisedTask {
+            component: state.component.clone(),
+            health: state.health.clone(),
+            ctx,
+            hwnd: runner.hwnd,
+            location: state.location,
+        };
+
+        let handle = runner.runtime.spawn(task.run(previous, delay));
+
+        ComponentTask { token, handle }
     }
 
     pub fn add(
         &mut self,
+        id: Option<String>,
         location: ComponentLocation,
         component: Arc<dyn Component + Send + Sync>,
+        actions: ComponentActions,
+        tooltip: Option<String>,
     ) {
         self.components.push(ComponentState {
+            id,
             location_intention: location,
             location: Rect::default(),
             component,
-        })
+            health: ComponentHealth::default(),
+            actions,
+            tooltip,
+            task: None,
+        });
+        self.layout_stale = true;
     }
 
-    #[instrument(level = "trace", skip(self))]
-    pub fn compute_locations(&mut self, hwnd: HWND, hdc: HDC) {
-        let width = WIDTH.load(Ordering::SeqCst);
-        let height = HEIGHT.load(Ordering::SeqCst);
-        let gap = COMPONENT_GAP.load(Ordering::SeqCst);
+    /// Dispatches a mouse event to the component under the mouse, hit-tested against the locations
+    /// from the last `compute_locations`. Bound actions that don't need the component are returned
+    /// to be run by the caller.
+    pub fn handle_mouse(&mut self, event: MouseEvent) -> MouseDispatch {
+        let mut dispatch = MouseDispatch::default();
 
-        let mut curr_loc_x = 0;
+        let point = match event {
+            MouseEvent::Move(point) => Some(point),
+            MouseEvent::Leave => None,
+            MouseEvent::Click { point, .. } => Some(point),
+            MouseEvent::Scroll { point, .. } => Some(point),
+        };
+        let hit = point.and_then(|point| {
+            mouse::hit_test(self.components.iter().map(|state| &state.location), point)
+        });
 
-        // left
-        self.components
-            .iter_mut()
-            .filter(|state| state.location_intention == ComponentLocation::LEFT)
-            .for_each(|state| {
-                let component_width = state.component.width(hwnd, hdc);
-                state.location = Rect {
-                    x: curr_loc_x,
-                    y: 0,
-                    width: component_width,
-                    height,
-                };
-                curr_loc_x += component_width + gap;
-            });
-
-        // right
-        curr_loc_x = width;
-        self.components
-            .iter_mut()
-            .filter(|state| state.location_intention == ComponentLocation::RIGHT)
-            .for_each(|state| {
-                let component_width = state.component.width(hwnd, hdc);
-                state.location = Rect {
-                    x: curr_loc_x - component_width,
-                    y: 0,
-                    width: component_width,
-                    height,
-                };
-                curr_loc_x -= component_width + gap;
-            });
-
-        // middle
-        // FIXME: a bit inefficient, change in the future...
-        let mut total_components = 0;
-        let total_width = if let Some(width) = self
+        if hit != self.hovered {
+            if let Some(previous) = self.hovered {
+                self.call(previous, |component| component.on_hover(Hover::Leave));
+                dispatch.redraw.insert(previous);
+            }
+            if let Some(index) = hit {
+                self.call(index, |component| component.on_hover(Hover::Enter));
+                dispatch.redraw.insert(index);
+            }
+            self.hovered = hit;
+            dispatch.hover_changed = true;
+        }
+
+        let Some(index) = hit else {
+            return dispatch;
+        };
+        let action = match event {
+            MouseEvent::Click { button, point } => {
+                let point = self.components[index].location.relative(point);
+                self.call(index, |component| component.on_click(button, point));
+                self.components[index].actions.click(button).cloned()
+            }
+            MouseEvent::Scroll { delta, .. } => {
+                self.call(index, |component| component.on_scroll(delta));
+                self.components[index].actions.scroll(delta).cloned()
+            }
+            _ => return dispatch,
+        };
+        dispatch.redraw.insert(index);
+
+        match action {
+            Some(Action::ToggleFormat) => {
+                self.call(index, |component| component.toggle_format());
+            }
+            Some(Action::TogglePopup) => {
+                let content = self.call(index, |component| component.popup()).flatten();
+                dispatch.popup = content.map(|content| PopupRequest {
+                    index,
+                    anchor: self.components[index].location,
+                    content,
+                });
+            }
+            Some(action) => dispatch.actions.push(action),
+            None => {}
+        }
+
+        dispatch
+    }
+
+    /// Calls into a component outside of its task, failing the component if it panics. Failed
+    /// components are not called.
+    fn call<T, F>(&mut self, index: usize, f: F) -> Option<T>
+    where
+        F: FnOnce(&(dyn Component + Send + Sync)) -> T,
+    {
+        let state = &self.components[index];
+        if state.health.is_failed() {
+            return None;
+        }
+
+        match panic::catch_unwind(AssertUnwindSafe(|| f(state.component.as_ref()))) {
+            Ok(value) => Some(value),
+            Err(e) => {
+                if let Err(e) = self.fail(index, supervisor::panic_message(e.as_ref())) {
+                    tracing::error!("Could not restart failed component: {}", e);
+                }
+                None
+            }
+        }
+    }
+
+    /// The tooltip of the component at the given index. A tooltip from the config takes precedence
+    /// over the component's own tooltip.
+    fn tooltip(&mut self, index: usize) -> Option<String> {
+        if let Some(tooltip) = &self.components[index].tooltip {
+            return Some(tooltip.clone());
+        }
+
+        self.call(index, |component| component.tooltip()).flatten()
+    }
+
+    /// The index of the component the mouse is over, if any.
+    pub fn hovered(&self) -> Option<usize> {
+        self.hovered
+    }
+
+    /// The tooltip of the component the mouse is over, if any.
+    pub fn hovered_tooltip(&mut self) -> Option<String> {
+        self.hovered.and_then(|index| self.tooltip(index))
+    }
+
+    /// The tooltip of the component with the given id.
+    pub fn find_tooltip(&mut self, id: &str) -> Result<Option<String>> {
+        let index = self.position(id)?;
+        Ok(self.tooltip(index))
+    }
+
+    /// Measures the width of a component with its styles selected into the device context,
+    /// returning the panic message if measuring panics.
+    ///
+    /// Failed components are measured as the failed placeholder.
+    fn measure(state: &ComponentState, hwnd: HWND, hdc: HDC) -> Result<i32, String> {
+        if state.health.is_failed() {
+            return Ok(Self::measure_placeholder(hdc));
+        }
+
+        panic::catch_unwind(AssertUnwindSafe(|| {
+            let _selected = SelectedStyles::select(hdc, &state.component.styles());
+            state.component.width(hwnd, hdc)
+        }))
+        .map_err(|e| supervisor::panic_message(e.as_ref()))
+    }
+
+    fn measure_placeholder(hdc: HDC) -> i32 {
+        let styles = StyleOptions::default();
+        let _selected = SelectedStyles::select(hdc, &styles);
+        Styles::text_component_width(hdc, FAILED_PLACEHOLDER, &styles)
+    }
+
+    /// Marks the component locations to be recomputed before the next paint.
+    pub fn invalidate_layout(&mut self) {
+        self.layout_stale = true;
+    }
+
+    /// Recomputes the component locations if the layout is stale.
+    pub fn ensure_layout(&mut self, hwnd: HWND, hdc: HDC) {
+        if self.layout_stale {
+            self.compute_locations(hwnd, hdc);
+        }
+    }
+
+    /// The regions to redraw for the components at the given indices, i.e., their current
+    /// locations. If the width of any of them changed, the layout is marked stale and `None` is
+    /// returned, since the whole window has to be redrawn.
+    pub fn redraw_regions(
+        &mut self,
+        hwnd: HWND,
+        hdc: HDC,
+        indices: impl IntoIterator<Item = usize>,
+    ) -> Option<Vec<Rect>> {
+        let mut regions = Vec::new();
+        for index in indices {
+            let state = self.components.get(index)?;
+            match Self::measure(state, hwnd, hdc) {
+                Ok(width) if !self.layout_stale && width == state.location.width => {
+                    regions.push(state.location)
+                }
+                Ok(_) => self.layout_stale = true,
+                Err(e) => {
+                    if let Err(e) = self.fail(index, e) {
+                        tracing::error!("Could not restart failed component: {}", e);
+                    }
+                }
+            }
+        }
+
+        (!self.layout_stale).then_some(regions)
+    }
+
+    #[instrument(level = "trace", skip(self))]
+    pub fn compute_locations(&mut self, hwnd: HWND, hdc: HDC) {
+        let mut failures = Vec::new();
+        let widths = self
             .components
-            .iter_mut()
-            .filter_map(|state| {
-                if state.location_intention == ComponentLocation::MIDDLE {
-                    total_components += 1;
-                    Some(state.component.width(hwnd, hdc))
-                } else {
-                    None
+            .iter()
+            .enumerate()
+            .map(|(index, state)| match Self::measure(state, hwnd, hdc) {
+                Ok(width) => width,
+                Err(e) => {
+                    failures.push((index, e));
+                    Self::measure_placeholder(hdc)
                 }
             })
-            .reduce(|acc, width| acc + width)
-        {
-            width
-        } else {
-            0
-        };
+            .collect::<Vec<i32>>();
 
-        // we multiply by 1 less gap since it's in between the components
-        curr_loc_x = width / 2 - (total_width + (gap - 1) * total_components) / 2;
+        let intentions = self
+            .components
+            .iter()
+            .map(|state| state.location_intention)
+            .collect::<Vec<ComponentLocation>>();
+        let locations = layout(
+            &intentions,
+            &widths,
+            WIDTH.load(Ordering::SeqCst),
+            HEIGHT.load(Ordering::SeqCst),
+            COMPONENT_GAP.load(Ordering::SeqCst),
+        );
         self.components
             .iter_mut()
-            .filter(|c| c.location_intention == ComponentLocation::MIDDLE)
-            .for_each(|state| {
-                let component_width = state.component.width(hwnd, hdc);
-                state.location = Rect {
-                    x: curr_loc_x,
-                    y: 0,
-                    width: component_width,
-                    height,
-                };
-                curr_loc_x += component_width + gap;
-            });
+            .zip(locations)
+            .for_each(|(state, location)| state.location = location);
+
+        for (index, error) in failures {
+            if let Err(e) = self.fail(index, error) {
+                tracing::error!("Could not restart failed component: {}", e);
+            }
+        }
+        // the failed components were already measured as the placeholder
+        self.layout_stale = false;
+    }
+}
+
+/// Computes the location of each component given its location intention and width. Left components
+/// are laid out from the left edge of the bar, right components from the right edge, and middle
+/// components are centered.
+pub fn layout(
+    intentions: &[ComponentLocation],
+    widths: &[i32],
+    width: i32,
+    height: i32,
+    gap: i32,
+) -> Vec<Rect> {
+    let mut locations = vec![Rect::default(); widths.len()];
+    let components = || intentions.iter().zip(widths).enumerate();
+
+    // left
+    let mut curr_loc_x = 0;
+    components()
+        .filter(|(_, (intention, _))| **intention == ComponentLocation::LEFT)
+        .for_each(|(index, (_, component_width))| {
+            locations[index] = Rect {
+                x: curr_loc_x,
+                y: 0,
+                width: *component_width,
+                height,
+            };
+            curr_loc_x += component_width + gap;
+        });
+
+    // right
+    curr_loc_x = width;
+    components()
+        .filter(|(_, (intention, _))| **intention == ComponentLocation::RIGHT)
+        .for_each(|(index, (_, component_width))| {
+            locations[index] = Rect {
+                x: curr_loc_x - component_width,
+                y: 0,
+                width: *component_width,
+                height,
+            };
+            curr_loc_x -= component_width + gap;
+        });
+
+    // middle
+    let (total_components, total_width) = components()
+        .filter(|(_, (intention, _))| **intention == ComponentLocation::MIDDLE)
+        .fold((0, 0), |(count, total), (_, (_, component_width))| {
+            (count + 1, total + component_width)
+        });
+
+    // there's 1 less gap than components since gaps are in between them
+    let total_gap = gap * (total_components - 1).max(0);
+    curr_loc_x = width / 2 - (total_width + total_gap) / 2;
+    components()
+        .filter(|(_, (intention, _))| **intention == ComponentLocation::MIDDLE)
+        .for_each(|(index, (_, component_width))| {
+            locations[index] = Rect {
+                x: curr_loc_x,
+                y: 0,
+                width: *component_width,
+                height,
+            };
+            curr_loc_x += component_width + gap;
+        });
+
+    locations
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        collections::BTreeSet,
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            mpsc, Mutex,
+        },
+    };
+
+    use async_trait::async_trait;
+    use tokio::runtime::Runtime;
+    use winbar::{
+        input::{MouseButton, Point},
+        popup::PopupContent,
+        styles::StyleOptions,
+    };
+
+    use super::*;
+
+    #[derive(Default)]
+    struct CountingComponent {
+        starts: AtomicUsize,
+        stops: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Component for CountingComponent {
+        fn styles(&self) -> Arc<StyleOptions> {
+            Arc::new(StyleOptions::default())
+        }
+
+        fn width(&self, _hwnd: HWND, _hdc: HDC) -> i32 {
+            0
+        }
+
+        fn draw(&self, _hwnd: HWND, _rect: Rect, _hdc: HDC) {}
+
+        async fn start(&self, _ctx: WinbarContext, _hwnd: HWND, _rect: Rect) {
+            self.starts.fetch_add(1, Ordering::SeqCst);
+            std::future::pending::<()>().await;
+        }
+
+        async fn stop(&self) {
+            self.stops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    fn started_manager(rt: &Runtime) -> (ComponentManager, Arc<CountingComponent>) {
+        let component = Arc::new(CountingComponent::default());
+        let mut manager = ComponentManager::new();
+        manager.add(
+            Some("counter".to_string()),
+            ComponentLocation::LEFT,
+            component.clone(),
+            ComponentActions::default(),
+            None,
+        );
+
+        let (send, _recv) = mpsc::channel();
+        manager.start(WinbarContext::new(send), HWND(0), rt.handle().clone());
+
+        (manager, component)
+    }
+
+    fn wait_for_starts(component: &CountingComponent, starts: usize, rt: &Runtime) {
+        rt.block_on(async {
+            while component.starts.load(Ordering::SeqCst) < starts {
+                tokio::task::yield_now().await;
+            }
+        });
+    }
+
+    fn wait_for(handles: Vec<JoinHandle<()>>, rt: &Runtime) {
+        rt.block_on(async {
+            for handle in handles {
+                handle.await.unwrap();
+            }
+        });
+    }
+
+    #[test]
+    fn layout_works() {
+        let locations = layout(
+            &[
+                ComponentLocation::LEFT,
+                ComponentLocation::RIGHT,
+                ComponentLocation::LEFT,
+                ComponentLocation::RIGHT,
+            ],
+            &[10, 20, 30, 40],
+            200,
+            25,
+            5,
+        );
+
+        let xs = locations.iter().map(|rect| rect.x).collect::<Vec<i32>>();
+        assert_eq!(xs, vec![0, 180, 15, 135]);
+        assert!(locations.iter().all(|rect| rect.height == 25));
+    }
+
+    #[test]
+    fn layout_centers_middle_components_with_gaps() {
+        let locations = layout(
+            &[
+                ComponentLocation::MIDDLE,
+                ComponentLocation::MIDDLE,
+                ComponentLocation::MIDDLE,
+            ],
+            &[20, 30, 40],
+            200,
+            25,
+            10,
+        );
+
+        // 90 wide components and 2 gaps of 10 take 110 of 200, leaving 45 on either side
+        let xs = locations.iter().map(|rect| rect.x).collect::<Vec<i32>>();
+        assert_eq!(xs, vec![45, 75, 115]);
+        let last = locations.last().unwrap();
+        assert_eq!(200 - last.x2(), 45);
+    }
+
+    #[test]
+    fn stop_all_stops_components() {
+        let rt = Runtime::new().unwrap();
+        let (mut manager, component) = started_manager(&rt);
+        wait_for_starts(&component, 1, &rt);
+
+        let handles = manager.stop_all();
+        wait_for(handles, &rt);
+
+        assert_eq!(component.starts.load(Ordering::SeqCst), 1);
+        assert_eq!(component.stops.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn restart_stops_then_starts_component() {
+        let rt = Runtime::new().unwrap();
+        let (mut manager, component) = started_manager(&rt);
+        wait_for_starts(&component, 1, &rt);
+
+        manager.restart("counter").unwrap();
+        wait_for_starts(&component, 2, &rt);
+        assert_eq!(component.stops.load(Ordering::SeqCst), 1);
+
+        let handles = manager.stop_all();
+        wait_for(handles, &rt);
+        assert_eq!(component.stops.load(Ordering::SeqCst), 2);
+    }
+
+    /// A component recording the mouse events it receives.
+    #[derive(Default)]
+    struct MouseComponent {
+        events: Mutex<Vec<String>>,
+    }
+
+    impl MouseComponent {
+        fn record(&self, event: String) {
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn take_events(&self) -> Vec<String> {
+            std::mem::take(&mut self.events.lock().unwrap())
+        }
+    }
+
+    #[async_trait]
+    impl Component for MouseComponent {
+        fn styles(&self) -> Arc<StyleOptions> {
+            Arc::new(StyleOptions::default())
+        }
+
+        fn width(&self, _hwnd: HWND, _hdc: HDC) -> i32 {
+            0
+        }
+
+        fn draw(&self, _hwnd: HWND, _rect: Rect, _hdc: HDC) {}
+
+        fn on_click(&self, button: MouseButton, point: Point) {
+            self.record(format!("click {:?} {} {}", button, point.x, point.y));
+        }
+
+        fn on_scroll(&self, delta: i32) {
+            self.record(format!("scroll {}", delta));
+        }
+
+        fn on_hover(&self, hover: Hover) {
+            self.record(format!("hover {:?}", hover));
+        }
+
+        fn toggle_format(&self) {
+            self.record("toggle".to_string());
+        }
+
+        fn tooltip(&self) -> Option<String> {
+            Some("dynamic tooltip".to_string())
+        }
+
+        fn popup(&self) -> Option<PopupContent> {
+            Some(PopupContent::from_text("popup"))
+        }
+
+        async fn start(&self, _ctx: WinbarContext, _hwnd: HWND, _rect: Rect) {}
+    }
+
+    /// A manager with two mouse components, at x 0..10 and 20..30, where the second has bound
+    /// actions and a static tooltip.
+    fn mouse_manager() -> (ComponentManager, Arc<MouseComponent>, Arc<MouseComponent>) {
+        let first = Arc::new(MouseComponent::default());
+        let second = Arc::new(MouseComponent::default());
+        let mut manager = ComponentManager::new();
+        manager.add(
+            Some("first".to_string()),
+            ComponentLocation::LEFT,
+            first.clone(),
+            ComponentActions::default(),
+            None,
+        );
+        manager.add(
+            None,
+            ComponentLocation::LEFT,
+            second.clone(),
+            ComponentActions {
+                left_click: Some(Action::ToggleFormat),
+                middle_click: Some(Action::TogglePopup),
+                scroll_down: Some(Action::Command("echo down".to_string())),
+                ..Default::default()
+            },
+            Some("static tooltip".to_string()),
+        );
+
+        let locations = layout(
+            &[ComponentLocation::LEFT, ComponentLocation::LEFT],
+            &[10, 10],
+            100,
+            20,
+            10,
+        );
+        manager
+            .components
+            .iter_mut()
+            .zip(locations)
+            .for_each(|(state, location)| state.location = location);
+
+        (manager, first, second)
+    }
+
+    fn point(x: i32, y: i32) -> Point {
+        Point { x, y }
+    }
+
+    #[test]
+    fn handle_mouse_tracks_hover() {
+        let (mut manager, first, second) = mouse_manager();
+
+        let redraw = |dispatch: MouseDispatch| dispatch.redraw.into_iter().collect::<Vec<_>>();
+        assert_eq!(
+            redraw(manager.handle_mouse(MouseEvent::Move(point(5, 5)))),
+            [0]
+        );
+        assert!(redraw(manager.handle_mouse(MouseEvent::Move(point(6, 5)))).is_empty());
+        assert_eq!(first.take_events(), vec!["hover Enter"]);
+
+        manager.handle_mouse(MouseEvent::Move(point(25, 5)));
+        assert_eq!(first.take_events(), vec!["hover Leave"]);
+        assert_eq!(second.take_events(), vec!["hover Enter"]);
+
+        // the gap between components
+        manager.handle_mouse(MouseEvent::Move(point(15, 5)));
+        assert_eq!(second.take_events(), vec!["hover Leave"]);
+
+        manager.handle_mouse(MouseEvent::Move(point(25, 5)));
+        manager.handle_mouse(MouseEvent::Leave);
+        assert_eq!(second.take_events(), vec!["hover Enter", "hover Leave"]);
+    }
+
+    #[test]
+    fn hover_redraws_components_without_relayout() {
+        let (mut manager, _, _) = mouse_manager();
+        manager.layout_stale = false;
+
+        let dispatch = manager.handle_mouse(MouseEvent::Move(point(5, 5)));
+        assert_eq!(dispatch.redraw, BTreeSet::from([0]));
+        let dispatch = manager.handle_mouse(MouseEvent::Move(point(25, 5)));
+        assert_eq!(dispatch.redraw, BTreeSet::from([0, 1]));
+
+        // the redraw path lays out the window again only if a redrawn component's width changed
+        assert!(!manager.layout_stale);
+    }
+
+    #[test]
+    fn handle_mouse_dispatches_clicks_and_scrolls() {
+        let (mut manager, first, second) = mouse_manager();
+
+        let dispatch = manager.handle_mouse(MouseEvent::Click {
+            button: MouseButton::Left,
+            point: point(3, 4),
+        });
+        assert!(dispatch.redraw.contains(&0));
+        assert!(dispatch.actions.is_empty());
+        assert_eq!(first.take_events(), vec!["hover Enter", "click Left 3 4"]);
+
+        // toggling the format is handled by the manager
+        let dispatch = manager.handle_mouse(MouseEvent::Click {
+            button: MouseButton::Left,
+            point: point(22, 4),
+        });
+        assert!(dispatch.actions.is_empty());
+        assert_eq!(first.take_events(), vec!["hover Leave"]);
+        assert_eq!(
+            second.take_events(),
+            vec!["hover Enter", "click Left 2 4", "toggle"]
+        );
+
+        let dispatch = manager.handle_mouse(MouseEvent::Scroll {
+            point: point(22, 4),
+            delta: -120,
+        });
+        assert!(matches!(
+            dispatch.actions.as_slice(),
+            [Action::Command(command)] if command == "echo down"
+        ));
+        assert_eq!(second.take_events(), vec!["scroll -120"]);
+
+        let dispatch = manager.handle_mouse(MouseEvent::Click {
+            button: MouseButton::Right,
+            point: point(50, 4),
+        });
+        assert!(dispatch.actions.is_empty());
+        assert!(first.take_events().is_empty());
+        assert_eq!(second.take_events(), vec!["hover Leave"]);
+    }
+
+    #[test]
+    fn handle_mouse_requests_popup() {
+        let (mut manager, _, _) = mouse_manager();
+
+        let dispatch = manager.handle_mouse(MouseEvent::Click {
+            button: MouseButton::Middle,
+            point: point(5, 4),
+        });
+        assert!(dispatch.popup.is_none());
+
+        let dispatch = manager.handle_mouse(MouseEvent::Click {
+            button: MouseButton::Middle,
+            point: point(25, 4),
+        });
+        let request = dispatch.popup.unwrap();
+        assert_eq!(request.index, 1);
+        assert_eq!(request.anchor, manager.components[1].location);
+        assert_eq!(request.content.text_lines(), vec!["popup"]);
+    }
+
+    #[test]
+    fn tooltips_prefer_static_tooltip() {
+        let (mut manager, _, _) = mouse_manager();
+
+        assert_eq!(manager.hovered_tooltip(), None);
+
+        let dispatch = manager.handle_mouse(MouseEvent::Move(point(5, 5)));
+        assert!(dispatch.hover_changed);
+        assert_eq!(
+            manager.hovered_tooltip().as_deref(),
+            Some("dynamic tooltip")
+        );
+
+        manager.handle_mouse(MouseEvent::Move(point(25, 5)));
+        assert_eq!(manager.hovered_tooltip().as_deref(), Some("static tooltip"));
+
+        manager.handle_mouse(MouseEvent::Leave);
+        assert_eq!(manager.hovered_tooltip(), None);
+
+        assert_eq!(
+            manager.find_tooltip("first").unwrap().as_deref(),
+            Some("dynamic tooltip")
+        );
+        assert!(manager.find_tooltip("unknown").is_err());
+    }
+
+    #[test]
+    fn restart_unknown_component_fails() {
+        let rt = Runtime::new().unwrap();
+        let (mut manager, _) = started_manager(&rt);
+
+        assert!(manager.restart("unknown").is_err());
     }
 }
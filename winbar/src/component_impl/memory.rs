@@ -0,0 +1,104 @@
+use std::{sync::Arc, time::Duration};
+
+use winbar::template::{Fields, Template, Value};
+
+use crate::metrics::{MemoryUsage, SystemMetricsProvider};
+
+use super::metric::{MetricComponent, Sample, Sampler};
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(5);
+pub const DEFAULT_FORMAT: &str = "RAM {percent:.0}%";
+
+const TOOLTIP_FORMAT: &str = "Memory: {used|bytes} / {total|bytes} ({percent:.0}%)\n\
+    {?swap_total}Swap: {swap_used|bytes} / {swap_total|bytes} ({swap_percent:.0}%){/}\
+    {!swap_total}No swap{/}";
+
+/// Displays the memory and swap usage.
+pub type MemoryComponent = MetricComponent<MemoryUsage>;
+
+pub fn sampler(provider: Arc<dyn SystemMetricsProvider>) -> Sampler<MemoryUsage> {
+    Arc::new(move || provider.memory_usage())
+}
+
+/// The fields available to the format. Sizes are in bytes, and percentages from 0 to 100.
+impl Fields for MemoryUsage {
+    fn field(&self, name: &str) -> Option<Value> {
+        let value = match name {
+            "used" => self.used() as f64,
+            "available" => self.available as f64,
+            "total" => self.total as f64,
+            "percent" => self.percent(),
+            "swap_used" => self.swap_used() as f64,
+            "swap_free" => self.swap_free as f64,
+            "swap_total" => self.swap_total as f64,
+            "swap_percent" => self.swap_percent(),
+            _ => return None,
+        };
+        Some(value.into())
+    }
+}
+
+impl Sample for MemoryUsage {
+    const NAME: &'static str = "memory usage";
+
+    fn render(&self, format: &Template) -> String {
+        format.render(self)
+    }
+
+    /// The memory and swap usage in bytes.
+    fn tooltip(&self) -> Option<String> {
+        let template = Template::parse(TOOLTIP_FORMAT).ok()?;
+        Some(template.render(self))
+    }
+
+    fn level(&self) -> Option<f64> {
+        Some(self.percent())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GIB: u64 = 1024 * 1024 * 1024;
+
+    fn usage() -> MemoryUsage {
+        MemoryUsage {
+            total: 16 * GIB,
+            available: 4 * GIB,
+            swap_total: 2 * GIB,
+            swap_free: GIB + GIB / 2,
+        }
+    }
+
+    #[test]
+    fn format_exposes_sizes_and_percentages() {
+        let usage = usage();
+        let render = |format: &str| Template::parse(format).unwrap().render(&usage);
+
+        assert_eq!(render(DEFAULT_FORMAT), "RAM 75%");
+        assert_eq!(
+            render("{used|bytes}/{total|bytes:.0} swap {swap_percent:.0}%"),
+            "12.0 GiB/16 GiB swap 25%"
+        );
+        assert_eq!(render("{available|bytes}"), "4.0 GiB");
+    }
+
+    #[test]
+    fn tooltip_shows_memory_and_swap() {
+        assert_eq!(
+            usage().tooltip().as_deref(),
+            Some("Memory: 12.0 GiB / 16.0 GiB (75%)\nSwap: 512.0 MiB / 2.0 GiB (25%)")
+        );
+
+        let without_swap = MemoryUsage {
+            swap_total: 0,
+            swap_free: 0,
+            ..usage()
+        };
+        assert_eq!(
+            without_swap.tooltip().as_deref(),
+            Some("Memory: 12.0 GiB / 16.0 GiB (75%)\nNo swap")
+        );
+    }
+}
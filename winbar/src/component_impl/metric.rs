@@ -0,0 +1,228 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use winbar::{
+    styles::{ComponentStyles, StyleOptions, Styles},
+    template::Template,
+    util::rect::Rect,
+    Component, WinbarContext,
+};
+use windows::Win32::{Foundation::HWND, Graphics::Gdi::HDC};
+
+use crate::scheduler::Ticker;
+
+/// A sample of a system metric.
+pub trait Sample: Clone + Send + Sync + 'static {
+    /// The name of the metric, used in log messages.
+    const NAME: &'static str;
+
+    /// Whether lower levels are worse (e.g., a battery's charge), in which case the states are
+    /// reached at and below the thresholds.
+    const DESCENDING: bool = false;
+
+    /// The text displayed for the sample given the component's format, usually the format rendered
+    /// with the sample's fields.
+    fn render(&self, format: &Template) -> String;
+
+    /// The tooltip shown for the sample, if any.
+    fn tooltip(&self) -> Option<String> {
+        None
+    }
+
+    /// The level compared against the component's thresholds, usually a percentage, if the metric
+    /// has one.
+    fn level(&self) -> Option<f64> {
+        None
+    }
+
+    /// The state of the sample itself (e.g., `disconnected`), which takes precedence over the
+    /// thresholds.
+    fn state(&self) -> Option<&'static str> {
+        None
+    }
+}
+
+/// Takes a sample of a metric. Called on a blocking thread.
+pub type Sampler<S> = Arc<dyn Fn() -> Result<S> + Send + Sync>;
+
+/// Levels (percentages for most metrics) at and above (or at and below, for descending metrics)
+/// which a metric component is in the `warning` or `critical` state, which can be styled with
+/// `State` rules. A state set through the protocol takes precedence.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Thresholds {
+    #[serde(default)]
+    pub warning: Option<f64>,
+    #[serde(default)]
+    pub critical: Option<f64>,
+}
+
+impl Thresholds {
+    pub fn state(&self, level: f64) -> Option<&'static str> {
+        self.state_when(|threshold| level >= threshold)
+    }
+
+    /// The state of a level that gets worse as it decreases.
+    pub fn state_below(&self, level: f64) -> Option<&'static str> {
+        self.state_when(|threshold| level <= threshold)
+    }
+
+    fn state_when(&self, reached: impl Fn(f64) -> bool) -> Option<&'static str> {
+        let reached = |threshold: Option<f64>| threshold.is_some_and(&reached);
+        if reached(self.critical) {
+            Some("critical")
+        } else if reached(self.warning) {
+            Some("warning")
+        } else {
+            None
+        }
+    }
+}
+
+/// Displays a system metric, sampled at a fixed interval.
+pub struct MetricComponent<S: Sample> {
+    sampler: Sampler<S>,
+    format: Template,
+    interval: Duration,
+    thresholds: Thresholds,
+    styles: ComponentStyles,
+    /// The latest sample, if there was one.
+    sample: RwLock<Option<S>>,
+}
+
+impl<S: Sample> MetricComponent<S> {
+    pub fn new(
+        sampler: Sampler<S>,
+        format: Template,
+        interval: Duration,
+        thresholds: Thresholds,
+        styles: ComponentStyles,
+    ) -> Self {
+        Self {
+            sampler,
+            format,
+            interval,
+            thresholds,
+            styles,
+            sample: RwLock::new(None),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        match self.sample.read().unwrap().as_ref() {
+            Some(sample) => sample.render(&self.format),
+            None => String::new(),
+        }
+    }
+
+    fn resolve_styles(&self, text: &str) -> Arc<StyleOptions> {
+        self.styles.resolve_with_state(text, self.state())
+    }
+
+    /// Takes a sample, returning whether the displayed text or state changed.
+    pub async fn sample(&self) -> bool {
+        let sampler = self.sampler.clone();
+        let sample = match tokio::task::spawn_blocking(move || sampler()).await {
+            Ok(Ok(sample)) => sample,
+            Ok(Err(e)) => {
+                tracing::error!("Could not sample {}: {:#}", S::NAME, e);
+                return false;
+            }
+            Err(e) => {
+                tracing::error!("{} sampling task failed: {}", S::NAME, e);
+                return false;
+            }
+        };
+
+        let previous = (self.text(), self.state());
+        *self.sample.write().unwrap() = Some(sample);
+        (self.text(), self.state()) != previous
+    }
+
+    /// The state of the latest sample, or else its state given the thresholds.
+    pub fn state(&self) -> Option<&'static str> {
+        let sample = self.sample.read().unwrap();
+        let sample = sample.as_ref()?;
+        sample.state().or_else(|| {
+            let level = sample.level()?;
+            if S::DESCENDING {
+                self.thresholds.state_below(level)
+            } else {
+                self.thresholds.state(level)
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl<S: Sample> Component for MetricComponent<S> {
+    fn styles(&self) -> Arc<StyleOptions> {
+        self.resolve_styles(&self.text())
+    }
+
+    fn set_state(&self, state: Option<String>) {
+        self.styles.set_state(state);
+    }
+
+    fn tooltip(&self) -> Option<String> {
+        self.sample.read().unwrap().as_ref()?.tooltip()
+    }
+
+    fn width(&self, _hwnd: HWND, hdc: HDC) -> i32 {
+        let text = self.text();
+        let styles = self.resolve_styles(&text);
+
+        Styles::text_component_width(hdc, &text, &styles)
+    }
+
+    fn draw(&self, _hwnd: HWND, rect: Rect, hdc: HDC) {
+        let text = self.text();
+        let styles = self.resolve_styles(&text);
+
+        Styles::draw_rect(hdc, &rect, &styles);
+        Styles::draw_text(hdc, &rect, &text, &styles);
+    }
+
+    async fn start(&self, ctx: WinbarContext, _hwnd: HWND, _rect: Rect) {
+        let mut ticker = Ticker::new(self.interval);
+        loop {
+            ticker.tick().await;
+
+            if !self.sample().await {
+                continue;
+            }
+            if let Err(e) = ctx.request_redraw() {
+                tracing::error!("Could not send update window action over channel: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn thresholds_select_highest_reached_state() {
+        let thresholds = Thresholds {
+            warning: Some(80.0),
+            critical: Some(95.0),
+        };
+        assert_eq!(thresholds.state(50.0), None);
+        assert_eq!(thresholds.state(80.0), Some("warning"));
+        assert_eq!(thresholds.state(99.0), Some("critical"));
+        assert_eq!(Thresholds::default().state(100.0), None);
+
+        let low = Thresholds {
+            warning: Some(20.0),
+            critical: Some(5.0),
+        };
+        assert_eq!(low.state_below(50.0), None);
+        assert_eq!(low.state_below(20.0), Some("warning"));
+        assert_eq!(low.state_below(3.0), Some("critical"));
+    }
+}
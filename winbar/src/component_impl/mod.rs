@@ -1,3 +1,19 @@
+pub mod battery;
+pub mod command;
+pub mod cpu;
 pub mod datetime;
+pub mod disk;
+pub mod komorebi;
 pub mod manager;
+pub mod memory;
+pub mod metric;
+pub mod mouse;
+pub mod network;
+pub mod plugin;
+pub mod render;
+pub mod script;
 pub mod static_text;
+pub mod supervisor;
+pub mod wasm;
+pub mod window_title;
+pub mod world_clock;
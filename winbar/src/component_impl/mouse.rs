@@ -0,0 +1,179 @@
+//! Mouse events and the actions bound to them.
+use std::{collections::BTreeSet, process::Command};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use winbar::{
+    input::{MouseButton, Point},
+    protocol::ServerMessage,
+    util::rect::Rect,
+    WinbarContext,
+};
+
+use crate::{popup_window::PopupRequest, server::WinbarServer};
+
+/// A mouse event over the status bar window. Points are relative to the window.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MouseEvent {
+    /// The mouse moved to the point.
+    Move(Point),
+    /// The mouse left the window.
+    Leave,
+    /// A mouse button was clicked at the point.
+    Click { button: MouseButton, point: Point },
+    /// The mouse wheel was scrolled at the point. A positive delta is a scroll up.
+    Scroll { point: Point, delta: i32 },
+}
+
+/// An action that can be bound to a mouse event of a component.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Action {
+    /// Runs the command with `cmd /C`.
+    Command(String),
+    /// Handles the protocol message as if it was sent by a client.
+    Message(ServerMessage),
+    /// Toggles the component's alternative format.
+    ToggleFormat,
+    /// Opens the component's popup panel, or closes it if it's open.
+    TogglePopup,
+}
+
+/// The actions bound to the mouse events of a component. These run in addition to the component's
+/// own handlers.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ComponentActions {
+    #[serde(default)]
+    pub left_click: Option<Action>,
+    #[serde(default)]
+    pub right_click: Option<Action>,
+    #[serde(default)]
+    pub middle_click: Option<Action>,
+    #[serde(default)]
+    pub scroll_up: Option<Action>,
+    #[serde(default)]
+    pub scroll_down: Option<Action>,
+}
+
+impl ComponentActions {
+    /// The action bound to clicking the given button.
+    pub fn click(&self, button: MouseButton) -> Option<&Action> {
+        match button {
+            MouseButton::Left => self.left_click.as_ref(),
+            MouseButton::Right => self.right_click.as_ref(),
+            MouseButton::Middle => self.middle_click.as_ref(),
+        }
+    }
+
+    /// The action bound to scrolling with the given delta.
+    pub fn scroll(&self, delta: i32) -> Option<&Action> {
+        match delta.signum() {
+            1 => self.scroll_up.as_ref(),
+            -1 => self.scroll_down.as_ref(),
+            _ => None,
+        }
+    }
+}
+
+/// The result of dispatching a mouse event to the components.
+#[derive(Debug, Default)]
+pub struct MouseDispatch {
+    /// The indices of the components to redraw, e.g., the ones the mouse moved on or off of. The
+    /// window is only laid out again if the width of one of them changed.
+    pub redraw: BTreeSet<usize>,
+    /// Whether the mouse moved onto a different component, or off of all components.
+    pub hover_changed: bool,
+    /// The actions to run. These are run after the component manager is released, since they may
+    /// need it themselves.
+    pub actions: Vec<Action>,
+    /// The popup panel to toggle.
+    pub popup: Option<PopupRequest>,
+}
+
+/// Returns the index of the rect containing the point, if any.
+pub fn hit_test<'a, I>(locations: I, point: Point) -> Option<usize>
+where
+    I: IntoIterator<Item = &'a Rect>,
+{
+    locations
+        .into_iter()
+        .position(|location| location.contains(point))
+}
+
+/// Runs an action that does not need the component it's bound to. Actions that do are handled by
+/// the component manager.
+pub fn run_action(ctx: &WinbarContext, action: &Action) -> Result<()> {
+    match action {
+        Action::Command(command) => {
+            let mut cmd = Command::new("cmd");
+            cmd.args(["/C", command]);
+            #[cfg(windows)]
+            {
+                use std::os::windows::process::CommandExt;
+                use windows::Win32::System::Threading::CREATE_NO_WINDOW;
+
+                cmd.creation_flags(CREATE_NO_WINDOW.0);
+            }
+            cmd.spawn()
+                .with_context(|| format!("Could not run command: {}", command))?;
+        }
+        Action::Message(message) => {
+            WinbarServer::handle_message(ctx, message)?;
+        }
+        Action::ToggleFormat | Action::TogglePopup => {}
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rect(x: i32, width: i32) -> Rect {
+        Rect {
+            x,
+            y: 0,
+            width,
+            height: 20,
+        }
+    }
+
+    #[test]
+    fn hit_test_works() {
+        let locations = [rect(0, 10), rect(20, 10), rect(50, 30)];
+        assert_eq!(hit_test(&locations, Point { x: 5, y: 5 }), Some(0));
+        assert_eq!(hit_test(&locations, Point { x: 25, y: 5 }), Some(1));
+        assert_eq!(hit_test(&locations, Point { x: 79, y: 19 }), Some(2));
+        // gap between components
+        assert_eq!(hit_test(&locations, Point { x: 15, y: 5 }), None);
+        assert_eq!(hit_test(&locations, Point { x: 80, y: 5 }), None);
+    }
+
+    #[test]
+    fn actions_deserialize() {
+        let actions: ComponentActions = serde_json::from_str(
+            r#"{
+                "left_click": { "Command": "start ms-settings:" },
+                "right_click": "ToggleFormat",
+                "scroll_up": { "Message": "HideWindow" }
+            }"#,
+        )
+        .unwrap();
+
+        assert!(matches!(
+            actions.click(MouseButton::Left),
+            Some(Action::Command(command)) if command == "start ms-settings:"
+        ));
+        assert!(matches!(
+            actions.click(MouseButton::Right),
+            Some(Action::ToggleFormat)
+        ));
+        assert!(actions.click(MouseButton::Middle).is_none());
+        assert!(matches!(
+            actions.scroll(120),
+            Some(Action::Message(ServerMessage::HideWindow))
+        ));
+        assert!(actions.scroll(-120).is_none());
+        assert!(actions.scroll(0).is_none());
+    }
+}
@@ -0,0 +1,282 @@
+use std::{
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use winbar::template::{Fields, Template, Value};
+
+use crate::metrics::{NetworkInterface, SystemMetricsProvider};
+
+use super::metric::{MetricComponent, Sample, Sampler};
+
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(2);
+pub const DEFAULT_FORMAT: &str =
+    "{?connected}↓{down|bytes}/s ↑{up|bytes}/s{/}{!connected}Disconnected{/}";
+
+const TOOLTIP_FORMAT: &str = "{?name}{name}{/}{!name}No interface{/}\
+    {?connected}{?ip}\nAddress: {ip}{/}\nReceived: {received|bytes}\nSent: {sent|bytes}{/}\
+    {!connected}\nDisconnected{/}";
+
+/// The status of the displayed interface, and its traffic rates in bytes per second.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NetworkStatus {
+    /// The interface, or `None` if it doesn't exist.
+    pub interface: Option<NetworkInterface>,
+    pub down: f64,
+    pub up: f64,
+}
+
+impl NetworkStatus {
+    fn connected(&self) -> bool {
+        self.interface
+            .as_ref()
+            .is_some_and(|interface| interface.connected)
+    }
+}
+
+/// Displays the traffic and status of a network interface.
+pub type NetworkComponent = MetricComponent<NetworkStatus>;
+
+/// Samples the interface with the name, or the interface of the default route.
+pub fn sampler(
+    provider: Arc<dyn SystemMetricsProvider>,
+    interface: Option<String>,
+) -> Sampler<NetworkStatus> {
+    let meter = Mutex::new(RateMeter::default());
+    Arc::new(move || {
+        let interface =
+            provider
+                .network_interfaces()?
+                .into_iter()
+                .find(|candidate| match &interface {
+                    Some(name) => candidate.name.eq_ignore_ascii_case(name),
+                    None => candidate.default,
+                });
+
+        let (down, up) = match &interface {
+            Some(interface) => meter.lock().unwrap().update(interface, Instant::now()),
+            None => (0.0, 0.0),
+        };
+        Ok(NetworkStatus {
+            interface,
+            down,
+            up,
+        })
+    })
+}
+
+/// The counters of an interface at a sample.
+struct Counters {
+    name: String,
+    at: Instant,
+    received: u64,
+    sent: u64,
+}
+
+/// Computes the traffic rates of an interface from the counters of consecutive samples.
+#[derive(Default)]
+pub struct RateMeter {
+    previous: Option<Counters>,
+}
+
+impl RateMeter {
+    /// The bytes received and sent per second since the previous sample, or 0 for the first sample
+    /// of an interface (e.g., after the default route changed).
+    pub fn update(&mut self, interface: &NetworkInterface, at: Instant) -> (f64, f64) {
+        let previous = self.previous.replace(Counters {
+            name: interface.name.clone(),
+            at,
+            received: interface.received,
+            sent: interface.sent,
+        });
+        let Some(previous) = previous.filter(|previous| previous.name == interface.name) else {
+            return (0.0, 0.0);
+        };
+
+        let elapsed = at.saturating_duration_since(previous.at).as_secs_f64();
+        if elapsed == 0.0 {
+            return (0.0, 0.0);
+        }
+
+        // the counters restart when the interface comes back up
+        let rate = |current: u64, previous: u64| current.saturating_sub(previous) as f64 / elapsed;
+        (
+            rate(interface.received, previous.received),
+            rate(interface.sent, previous.sent),
+        )
+    }
+}
+
+/// The fields available to the format. Rates are in bytes per second, and totals in bytes.
+impl Fields for NetworkStatus {
+    fn field(&self, name: &str) -> Option<Value> {
+        let interface = self.interface.as_ref();
+        match name {
+            "connected" => Some(self.connected().into()),
+            "down" => Some(self.down.into()),
+            "up" => Some(self.up.into()),
+            "name" => Some(interface?.name.as_str().into()),
+            "ip" => Some(interface?.address?.to_string().into()),
+            "received" => Some((interface?.received as f64).into()),
+            "sent" => Some((interface?.sent as f64).into()),
+            _ => None,
+        }
+    }
+}
+
+impl Sample for NetworkStatus {
+    const NAME: &'static str = "network status";
+
+    fn render(&self, format: &Template) -> String {
+        format.render(self)
+    }
+
+    /// The interface's name, address and total traffic.
+    fn tooltip(&self) -> Option<String> {
+        let template = Template::parse(TOOLTIP_FORMAT).ok()?;
+        Some(template.render(self))
+    }
+
+    /// The combined rate, in bytes per second, compared against the thresholds.
+    fn level(&self) -> Option<f64> {
+        self.connected().then_some(self.down + self.up)
+    }
+
+    fn state(&self) -> Option<&'static str> {
+        (!self.connected()).then_some("disconnected")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use crate::{component_impl::metric::Thresholds, metrics::mock::MockMetricsProvider};
+
+    use super::*;
+
+    fn interface(name: &str, received: u64, sent: u64) -> NetworkInterface {
+        NetworkInterface {
+            name: name.to_string(),
+            address: Some(IpAddr::V4(Ipv4Addr::new(192, 168, 1, 20))),
+            connected: true,
+            default: false,
+            received,
+            sent,
+        }
+    }
+
+    #[test]
+    fn rates_are_counter_deltas_over_elapsed_time() {
+        let start = Instant::now();
+        // counters recorded every 2 seconds
+        let samples = [
+            ("Wi-Fi", 0, 1_000_000, 50_000),
+            ("Wi-Fi", 2, 3_048_576, 60_240),
+            ("Wi-Fi", 4, 3_048_576, 60_240),
+            // the counters restarted
+            ("Wi-Fi", 6, 1_000, 100),
+            // the default route moved to another interface
+            ("Ethernet", 8, 9_000_000, 9_000_000),
+            ("Ethernet", 9, 9_001_024, 9_000_512),
+        ];
+
+        let mut meter = RateMeter::default();
+        let rates = samples
+            .iter()
+            .map(|(name, seconds, received, sent)| {
+                let at = start + Duration::from_secs(*seconds);
+                meter.update(&interface(name, *received, *sent), at)
+            })
+            .collect::<Vec<(f64, f64)>>();
+        assert_eq!(
+            rates,
+            vec![
+                (0.0, 0.0),
+                (1_024_288.0, 5_120.0),
+                (0.0, 0.0),
+                (0.0, 0.0),
+                (0.0, 0.0),
+                (1_024.0, 512.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn samples_chosen_or_default_interface() {
+        let provider = Arc::new(MockMetricsProvider::default());
+        *provider.network.lock().unwrap() = vec![
+            interface("Ethernet", 0, 0),
+            NetworkInterface {
+                default: true,
+                ..interface("Wi-Fi", 0, 0)
+            },
+        ];
+
+        let name = |sample: NetworkStatus| sample.interface.map(|interface| interface.name);
+        assert_eq!(
+            name(sampler(provider.clone(), None)().unwrap()).as_deref(),
+            Some("Wi-Fi")
+        );
+        assert_eq!(
+            name(sampler(provider.clone(), Some("ethernet".to_string()))().unwrap()).as_deref(),
+            Some("Ethernet")
+        );
+        assert_eq!(
+            name(sampler(provider, Some("VPN".to_string()))().unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn format_and_state_reflect_connection() {
+        let format = Template::parse(DEFAULT_FORMAT).unwrap();
+        let connected = NetworkStatus {
+            interface: Some(interface("Wi-Fi", 5 * 1024 * 1024, 1024)),
+            down: 1536.0,
+            up: 100.0,
+        };
+        assert_eq!(connected.render(&format), "↓1.5 KiB/s ↑100 B/s");
+        assert_eq!(connected.state(), None);
+        assert_eq!(
+            connected.tooltip().as_deref(),
+            Some("Wi-Fi\nAddress: 192.168.1.20\nReceived: 5.0 MiB\nSent: 1.0 KiB")
+        );
+
+        let disconnected = NetworkStatus {
+            interface: Some(NetworkInterface {
+                connected: false,
+                address: None,
+                ..interface("Wi-Fi", 0, 0)
+            }),
+            ..Default::default()
+        };
+        assert_eq!(disconnected.render(&format), "Disconnected");
+        assert_eq!(disconnected.state(), Some("disconnected"));
+        assert_eq!(
+            disconnected.tooltip().as_deref(),
+            Some("Wi-Fi\nDisconnected")
+        );
+        assert_eq!(
+            NetworkStatus::default().tooltip().as_deref(),
+            Some("No interface\nDisconnected")
+        );
+    }
+
+    #[test]
+    fn thresholds_apply_to_combined_rate() {
+        let thresholds = Thresholds {
+            warning: Some(1024.0),
+            critical: Some(1024.0 * 1024.0),
+        };
+        let status = NetworkStatus {
+            interface: Some(interface("Wi-Fi", 0, 0)),
+            down: 1000.0,
+            up: 100.0,
+        };
+        assert_eq!(status.level(), Some(1100.0));
+        assert_eq!(thresholds.state(status.level().unwrap()), Some("warning"));
+
+        assert_eq!(NetworkStatus::default().level(), None);
+    }
+}
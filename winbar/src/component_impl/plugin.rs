@@ -0,0 +1,505 @@
+use std::{
+    ffi::{c_char, c_void, CStr, CString},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, PoisonError,
+    },
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use libloading::{Library, Symbol};
+use winbar::{
+    input::{Hover, MouseButton, Point},
+    plugin::{PluginEntry, PluginVTable, RenderCommand, ABI_VERSION, ENTRY_SYMBOL},
+    styles::{ComponentStyles, StyleOptions},
+    util::rect::Rect,
+    Component, WinbarContext,
+};
+use windows::Win32::{Foundation::HWND, Graphics::Gdi::HDC};
+
+use super::render;
+
+/// A component implemented by a plugin. See [`winbar::plugin`] for the ABI.
+pub struct PluginComponent {
+    vtable: *const PluginVTable,
+    instance: *mut c_void,
+    styles: ComponentStyles,
+    /// The commands last rendered by the plugin, which are drawn until they're stale.
+    rendered: Mutex<Vec<RenderCommand>>,
+    /// Whether the plugin has to render again, i.e., it requested a redraw or received an event
+    /// since it last rendered.
+    stale: Arc<AtomicBool>,
+    /// Passed to the plugin as the host pointer while it's started, i.e., `Some` between `start`
+    /// and `stop`.
+    host: Mutex<Option<Box<Host>>>,
+    /// The library the vtable and instance belong to, unloaded once the instance is destroyed.
+    _library: Option<Library>,
+}
+
+/// What the host pointer passed to a started plugin points to.
+struct Host {
+    ctx: WinbarContext,
+    stale: Arc<AtomicBool>,
+}
+
+// plugins must allow their instances to be used from any thread (see the ABI docs)
+unsafe impl Send for PluginComponent {}
+unsafe impl Sync for PluginComponent {}
+
+impl PluginComponent {
+    /// Loads the plugin library at the path and creates an instance with the given options.
+    pub fn load(path: &Path, options: &serde_json::Value, styles: ComponentStyles) -> Result<Self> {
+        unsafe {
+            let library = Library::new(path)
+                .with_context(|| format!("Could not load plugin: {}", path.display()))?;
+            let vtable = {
+                let entry: Symbol<PluginEntry> = library
+                    .get(ENTRY_SYMBOL.as_bytes())
+                    .with_context(|| format!("Plugin does not export {}", ENTRY_SYMBOL))?;
+                entry()
+            };
+
+            Self::from_vtable(vtable, Some(library), options, styles)
+        }
+    }
+
+    /// Creates an instance of the plugin with the given vtable.
+    ///
+    /// # Safety
+    ///
+    /// The vtable must be valid for as long as the library is loaded, or forever if no library is
+    /// given.
+    pub unsafe fn from_vtable(
+        vtable: *const PluginVTable,
+        library: Option<Library>,
+        options: &serde_json::Value,
+        styles: ComponentStyles,
+    ) -> Result<Self> {
+        let table = vtable
+            .as_ref()
+            .ok_or_else(|| anyhow!("Plugin returned no vtable"))?;
+        if table.abi_version != ABI_VERSION {
+            bail!(
+                "Plugin ABI version {} is not supported, expected version {}",
+                table.abi_version,
+                ABI_VERSION
+            );
+        }
+
+        let options = CString::new(options.to_string())?;
+        let instance = (table.create)(options.as_ptr());
+        if instance.is_null() {
+            bail!("Plugin could not be created with the given options");
+        }
+
+        Ok(Self {
+            vtable,
+            instance,
+            styles,
+            rendered: Mutex::new(Vec::new()),
+            stale: Arc::new(AtomicBool::new(true)),
+            host: Mutex::new(None),
+            _library: library,
+        })
+    }
+
+    fn vtable(&self) -> &PluginVTable {
+        // the vtable is valid for as long as the library is loaded, which is at least as long as self
+        unsafe { &*self.vtable }
+    }
+
+    /// Takes ownership of a string returned by the plugin.
+    fn take_string(&self, string: *mut c_char) -> Option<String> {
+        if string.is_null() {
+            return None;
+        }
+
+        unsafe {
+            let owned = CStr::from_ptr(string).to_string_lossy().into_owned();
+            (self.vtable().free_string)(string);
+            Some(owned)
+        }
+    }
+
+    fn render(&self) -> Vec<RenderCommand> {
+        let rendered = self.take_string(unsafe { (self.vtable().render)(self.instance) });
+        match rendered.map(|json| serde_json::from_str(&json)) {
+            Some(Ok(commands)) => commands,
+            Some(Err(e)) => {
+                tracing::error!("Plugin returned invalid render commands: {}", e);
+                Vec::new()
+            }
+            None => Vec::new(),
+        }
+    }
+
+    /// Calls the function with the commands rendered by the plugin, which only renders again if
+    /// they're stale, and the styles resolved against them.
+    fn with_rendered<T>(&self, f: impl FnOnce(&[RenderCommand], Arc<StyleOptions>) -> T) -> T {
+        let mut rendered = self.rendered.lock().unwrap();
+        if self.stale.swap(false, Ordering::SeqCst) {
+            *rendered = self.render();
+        }
+
+        let styles = self.styles.resolve(&render::text(&rendered));
+        f(&rendered, styles)
+    }
+
+    /// Delivers an event to the plugin, which is rendered again afterwards since the event may
+    /// change what it displays.
+    fn event(&self, f: impl FnOnce(&PluginVTable)) {
+        f(self.vtable());
+        self.stale.store(true, Ordering::SeqCst);
+    }
+}
+
+impl Drop for PluginComponent {
+    fn drop(&mut self) {
+        // a started instance is stopped before it's destroyed, and the host pointer stays valid
+        // until it's stopped (see the ABI docs)
+        let host = self
+            .host
+            .get_mut()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take();
+        unsafe {
+            if host.is_some() {
+                (self.vtable().stop)(self.instance);
+            }
+            (self.vtable().destroy)(self.instance);
+        }
+    }
+}
+
+extern "C" fn notify(host: *mut c_void) {
+    // the host pointer is the boxed host, which lives until the plugin is stopped
+    let host = unsafe { &*(host as *const Host) };
+    host.stale.store(true, Ordering::SeqCst);
+    if let Err(e) = host.ctx.request_redraw() {
+        tracing::error!("Could not send update window action over channel: {}", e);
+    }
+}
+
+#[async_trait]
+impl Component for PluginComponent {
+    fn styles(&self) -> Arc<StyleOptions> {
+        self.with_rendered(|_, styles| styles)
+    }
+
+    fn set_state(&self, state: Option<String>) {
+        self.styles.set_state(state);
+    }
+
+    fn width(&self, _hwnd: HWND, hdc: HDC) -> i32 {
+        self.with_rendered(|commands, styles| render::width(hdc, commands, &styles))
+    }
+
+    fn draw(&self, _hwnd: HWND, rect: Rect, hdc: HDC) {
+        self.with_rendered(|commands, styles| render::draw(hdc, rect, commands, &styles))
+    }
+
+    fn on_click(&self, button: MouseButton, point: Point) {
+        self.event(|vtable| unsafe {
+            (vtable.on_click)(self.instance, button.to_abi(), point.x, point.y)
+        });
+    }
+
+    fn on_scroll(&self, delta: i32) {
+        self.event(|vtable| unsafe { (vtable.on_scroll)(self.instance, delta) });
+    }
+
+    fn on_hover(&self, hover: Hover) {
+        self.event(|vtable| unsafe { (vtable.on_hover)(self.instance, hover == Hover::Enter) });
+    }
+
+    fn tooltip(&self) -> Option<String> {
+        self.take_string(unsafe { (self.vtable().tooltip)(self.instance) })
+    }
+
+    async fn start(&self, ctx: WinbarContext, _hwnd: HWND, _rect: Rect) {
+        let token = ctx.cancellation_token().clone();
+        {
+            let mut host = self.host.lock().unwrap();
+            let boxed = host.insert(Box::new(Host {
+                ctx,
+                stale: self.stale.clone(),
+            }));
+            let pointer = boxed.as_ref() as *const Host as *mut c_void;
+            unsafe { (self.vtable().start)(self.instance, notify, pointer) }
+        }
+
+        token.cancelled().await;
+    }
+
+    async fn stop(&self) {
+        let mut host = self.host.lock().unwrap();
+        if host.is_some() {
+            unsafe { (self.vtable().stop)(self.instance) }
+            *host = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        path::PathBuf,
+        process::Command,
+        sync::{atomic::AtomicUsize, mpsc},
+    };
+
+    use winbar::{
+        plugin::{self, Notifier, Plugin},
+        WinbarAction,
+    };
+
+    use super::*;
+
+    /// A plugin recording the events it receives, with the given label as its text.
+    struct TestPlugin {
+        label: String,
+        events: Mutex<Vec<String>>,
+        notifier: Mutex<Option<Notifier>>,
+        renders: AtomicUsize,
+    }
+
+    /// The events of destroyed test plugins, by their label.
+    static DESTROYED: Mutex<Vec<(String, Vec<String>)>> = Mutex::new(Vec::new());
+
+    impl Drop for TestPlugin {
+        fn drop(&mut self) {
+            let events = self.events.lock().unwrap().clone();
+            DESTROYED.lock().unwrap().push((self.label.clone(), events));
+        }
+    }
+
+    impl Plugin for TestPlugin {
+        fn create(options: serde_json::Value) -> Result<Self, String> {
+            let label = options["label"]
+                .as_str()
+                .ok_or_else(|| "missing label".to_string())?;
+
+            Ok(Self {
+                label: label.to_string(),
+                events: Mutex::new(Vec::new()),
+                notifier: Mutex::new(None),
+                renders: AtomicUsize::new(0),
+            })
+        }
+
+        fn render(&self) -> Vec<RenderCommand> {
+            self.renders.fetch_add(1, Ordering::SeqCst);
+            vec![
+                RenderCommand::text(self.label.clone()),
+                RenderCommand::Space(4),
+                RenderCommand::Text {
+                    text: "!".to_string(),
+                    fg_color: Some("#ff0000".to_string()),
+                },
+            ]
+        }
+
+        fn tooltip(&self) -> Option<String> {
+            Some(format!("{} tooltip", self.label))
+        }
+
+        fn start(&self, notifier: Notifier) {
+            notifier.notify();
+            *self.notifier.lock().unwrap() = Some(notifier);
+        }
+
+        fn stop(&self) {
+            self.notifier.lock().unwrap().take();
+            self.events.lock().unwrap().push("stop".to_string());
+        }
+
+        fn on_click(&self, button: MouseButton, point: Point) {
+            let event = format!("click {:?} {} {}", button, point.x, point.y);
+            self.events.lock().unwrap().push(event);
+        }
+
+        fn on_scroll(&self, _delta: i32) {
+            panic!("scrolling panics");
+        }
+    }
+
+    fn create(options: serde_json::Value) -> Result<PluginComponent> {
+        unsafe {
+            PluginComponent::from_vtable(
+                plugin::vtable::<TestPlugin>(),
+                None,
+                &options,
+                StyleOptions::default().into(),
+            )
+        }
+    }
+
+    fn plugin(component: &PluginComponent) -> &TestPlugin {
+        unsafe { &*(component.instance as *const TestPlugin) }
+    }
+
+    fn events(component: &PluginComponent) -> Vec<String> {
+        plugin(component).events.lock().unwrap().clone()
+    }
+
+    #[test]
+    fn plugin_renders_and_handles_events() {
+        let component = create(serde_json::json!({ "label": "cpu" })).unwrap();
+
+        let commands = component.render();
+        assert_eq!(commands.len(), 3);
+        assert_eq!(render::text(&commands), "cpu!");
+        assert_eq!(component.tooltip().as_deref(), Some("cpu tooltip"));
+
+        component.on_click(MouseButton::Right, Point { x: 3, y: 4 });
+        // panics are caught at the ABI boundary
+        component.on_scroll(120);
+        assert_eq!(events(&component), vec!["click Right 3 4"]);
+    }
+
+    #[test]
+    fn plugin_renders_again_only_when_stale() {
+        let component = create(serde_json::json!({ "label": "cpu" })).unwrap();
+        let renders = || plugin(&component).renders.load(Ordering::SeqCst);
+
+        for _ in 0..3 {
+            let text = component.with_rendered(|commands, _| render::text(commands));
+            assert_eq!(text, "cpu!");
+        }
+        assert_eq!(renders(), 1);
+
+        component.on_click(MouseButton::Left, Point::default());
+        component.with_rendered(|_, _| ());
+        component.with_rendered(|_, _| ());
+        assert_eq!(renders(), 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn plugin_notifies_until_stopped() {
+        let component = Arc::new(create(serde_json::json!({ "label": "cpu" })).unwrap());
+        let (send, recv) = mpsc::channel();
+        let ctx = WinbarContext::new(send);
+
+        let handle = tokio::spawn({
+            let component = component.clone();
+            let ctx = ctx.clone();
+            async move { component.start(ctx, HWND(0), Rect::default()).await }
+        });
+        assert!(matches!(
+            recv.recv_timeout(std::time::Duration::from_secs(5)),
+            Ok(WinbarAction::UpdateWindow)
+        ));
+        // notifying makes the plugin render again
+        component.with_rendered(|_, _| ());
+        plugin(&component)
+            .notifier
+            .lock()
+            .unwrap()
+            .as_ref()
+            .unwrap()
+            .notify();
+        component.with_rendered(|_, _| ());
+        assert_eq!(plugin(&component).renders.load(Ordering::SeqCst), 2);
+
+        ctx.cancellation_token().cancel();
+        handle.await.unwrap();
+        component.stop().await;
+        assert_eq!(events(&component), vec!["stop"]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn started_plugin_is_stopped_before_destroyed() {
+        let component = Arc::new(create(serde_json::json!({ "label": "dropped" })).unwrap());
+        let (send, _recv) = mpsc::channel();
+        let ctx = WinbarContext::new(send);
+
+        let handle = tokio::spawn({
+            let component = component.clone();
+            let ctx = ctx.clone();
+            async move { component.start(ctx, HWND(0), Rect::default()).await }
+        });
+        while component.host.lock().unwrap().is_none() {
+            tokio::task::yield_now().await;
+        }
+        ctx.cancellation_token().cancel();
+        handle.await.unwrap();
+
+        // dropped without being stopped
+        drop(Arc::into_inner(component).unwrap());
+        let destroyed = DESTROYED.lock().unwrap();
+        let (_, events) = destroyed
+            .iter()
+            .find(|(label, _)| label == "dropped")
+            .unwrap();
+        assert_eq!(events, &vec!["stop".to_string()]);
+    }
+
+    #[test]
+    fn plugin_with_invalid_options_fails() {
+        assert!(create(serde_json::json!({})).is_err());
+    }
+
+    /// Builds the plugin fixture (`fixtures/test_plugin`), returning the path of its library.
+    fn build_fixture() -> PathBuf {
+        // the fixture is built into the same target directory and profile as the tests
+        let exe = std::env::current_exe().unwrap();
+        let profile_dir = exe.parent().and_then(Path::parent).unwrap();
+
+        let mut cargo = Command::new(std::env::var("CARGO").unwrap_or_else(|_| "cargo".into()));
+        cargo.args(["build", "--quiet", "-p", "winbar-test-plugin"]);
+        if profile_dir.ends_with("release") {
+            cargo.arg("--release");
+        }
+        assert!(cargo.status().unwrap().success(), "Could not build fixture");
+
+        profile_dir.join(libloading::library_filename("winbar_test_plugin"))
+    }
+
+    #[test]
+    fn plugin_library_is_loaded() {
+        let path = build_fixture();
+        let component = PluginComponent::load(
+            &path,
+            &serde_json::json!({ "label": "loaded" }),
+            StyleOptions::default().into(),
+        )
+        .unwrap();
+
+        assert_eq!(
+            component.with_rendered(|commands, _| commands.to_vec()),
+            vec![RenderCommand::text("loaded")]
+        );
+        assert_eq!(component.tooltip().as_deref(), Some("loaded tooltip"));
+
+        // the options are validated by the plugin
+        assert!(PluginComponent::load(
+            &path,
+            &serde_json::json!({}),
+            StyleOptions::default().into()
+        )
+        .is_err());
+        assert!(PluginComponent::load(
+            &path.with_file_name("missing"),
+            &serde_json::json!({}),
+            StyleOptions::default().into()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn plugin_with_other_abi_version_fails() {
+        let vtable = plugin::vtable::<TestPlugin>() as *mut PluginVTable;
+        unsafe {
+            (*vtable).abi_version = ABI_VERSION + 1;
+            let result = PluginComponent::from_vtable(
+                vtable,
+                None,
+                &serde_json::json!({ "label": "cpu" }),
+                StyleOptions::default().into(),
+            );
+            assert!(result.is_err());
+        }
+    }
+}
@@ -0,0 +1,145 @@
+//! Measuring and drawing of [`RenderCommand`]s, shared by the components rendered by plugins.
+use winbar::{
+    plugin::RenderCommand,
+    styles::{StyleOptions, Styles, TextAlign},
+    util::{hex_parser, rect::Rect, truncate::Truncation},
+};
+use windows::Win32::{
+    Foundation::COLORREF,
+    Graphics::Gdi::{GetTextColor, SetTextColor, HDC},
+};
+
+/// What was last rendered by a component, which is drawn without rendering it again.
+#[derive(Default)]
+pub struct Rendered {
+    pub commands: Vec<RenderCommand>,
+    pub tooltip: Option<String>,
+}
+
+/// The text of the render commands, used to resolve conditional styles.
+pub fn text(commands: &[RenderCommand]) -> String {
+    commands
+        .iter()
+        .filter_map(|command| match command {
+            RenderCommand::Text { text, .. } => Some(text.as_str()),
+            RenderCommand::Space(_) => None,
+        })
+        .collect()
+}
+
+fn command_width(hdc: HDC, command: &RenderCommand) -> i32 {
+    match command {
+        RenderCommand::Text { text, .. } => Styles::text_width(hdc, text),
+        RenderCommand::Space(width) => *width,
+    }
+}
+
+/// The width of the commands, without padding.
+pub fn content_width(hdc: HDC, commands: &[RenderCommand]) -> i32 {
+    commands
+        .iter()
+        .map(|command| command_width(hdc, command))
+        .sum()
+}
+
+/// The width of a component drawing the commands.
+pub fn width(hdc: HDC, commands: &[RenderCommand], styles: &StyleOptions) -> i32 {
+    styles.constrain_width(content_width(hdc, commands) + styles.padding_x * 2)
+}
+
+/// The widths the commands are drawn with when their total width may not exceed the available
+/// width. Commands are cut from the end, or from the start when truncating at the start: the command
+/// straddling the edge keeps what's left of the width, and is truncated in its own text, and the
+/// commands past it get no width.
+fn fit(widths: &[i32], available: i32, truncation: Truncation) -> Vec<i32> {
+    let mut remaining = available.max(0);
+    let fit = |width: &i32| {
+        let width = (*width).min(remaining);
+        remaining -= width;
+        width
+    };
+
+    match truncation {
+        Truncation::Start => {
+            let mut fitted = widths.iter().rev().map(fit).collect::<Vec<i32>>();
+            fitted.reverse();
+            fitted
+        }
+        Truncation::Middle | Truncation::End => widths.iter().map(fit).collect(),
+    }
+}
+
+/// Draws the commands in the rect, including the background of the component. Commands that don't
+/// fit within the rect are truncated according to the styles.
+pub fn draw(hdc: HDC, rect: Rect, commands: &[RenderCommand], styles: &StyleOptions) {
+    Styles::draw_rect(hdc, &rect, styles);
+
+    let available = rect.width - styles.padding_x * 2;
+    let full_widths = commands
+        .iter()
+        .map(|command| command_width(hdc, command))
+        .collect::<Vec<i32>>();
+    let widths = fit(&full_widths, available, styles.truncation);
+
+    // the commands are laid out as a whole according to the text alignment
+    let free = available - widths.iter().sum::<i32>();
+    let mut x = rect.x
+        + styles.padding_x
+        + match styles.text_align {
+            TextAlign::Left => 0,
+            TextAlign::Center => free / 2,
+            TextAlign::Right => free,
+        };
+    let text_styles = StyleOptions {
+        text_align: TextAlign::Left,
+        padding_x: 0,
+        ..styles.clone()
+    };
+
+    for (command, width) in commands.iter().zip(widths) {
+        if width <= 0 {
+            continue;
+        }
+
+        if let RenderCommand::Text { text, fg_color } = command {
+            let color = fg_color.as_ref().and_then(|hex| {
+                hex_parser::parse_color(hex)
+                    .map_err(|e| tracing::error!("Invalid color in render command: {}", e))
+                    .ok()
+            });
+            unsafe {
+                let old_color = GetTextColor(hdc);
+                if let Some(color) = &color {
+                    SetTextColor(hdc, COLORREF(color.b() << 16 | color.g() << 8 | color.r()));
+                }
+
+                // text drawn in less than its width is truncated with the styles' truncation
+                let text_rect = Rect { x, width, ..rect };
+                Styles::draw_text(hdc, &text_rect, text, &text_styles);
+
+                SetTextColor(hdc, old_color);
+            }
+        }
+
+        x += width;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn commands_fitting_are_unchanged() {
+        assert_eq!(fit(&[10, 5, 20], 35, Truncation::End), vec![10, 5, 20]);
+        assert_eq!(fit(&[10, 5, 20], 100, Truncation::Start), vec![10, 5, 20]);
+    }
+
+    #[test]
+    fn commands_are_cut_where_truncated() {
+        assert_eq!(fit(&[10, 5, 20], 22, Truncation::End), vec![10, 5, 7]);
+        assert_eq!(fit(&[10, 5, 20], 12, Truncation::Middle), vec![10, 2, 0]);
+        assert_eq!(fit(&[10, 5, 20], 22, Truncation::Start), vec![0, 2, 20]);
+        assert_eq!(fit(&[10, 5, 20], -4, Truncation::End), vec![0, 0, 0]);
+    }
+}
@@ -0,0 +1,635 @@
+//! Components implemented by Rhai scripts.
+//!
+//! A script defines an `update` function, which is called at the component's interval and returns
+//! what to display. Functions are called with `this` bound to a map that persists between calls,
+//! which scripts can keep their state in. See `docs/components/script.md`.
+use std::{
+    path::Path,
+    process::Command,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use chrono::{
+    format::{Item, StrftimeItems},
+    Local,
+};
+use rhai::{CallFnOptions, Dynamic, Engine, EvalAltResult, FuncArgs, Map, Scope, AST};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use winbar::{
+    input::{MouseButton, Point},
+    plugin::RenderCommand,
+    styles::{ComponentStyles, StyleOptions},
+    template::{Fields, Template, Value},
+    util::rect::Rect,
+    Component, WinbarContext,
+};
+use windows::Win32::{Foundation::HWND, Graphics::Gdi::HDC};
+
+use crate::scheduler::Ticker;
+
+use super::{
+    command,
+    render::{self, Rendered},
+    supervisor,
+};
+
+/// The interval at which `update` is called, unless the component is configured otherwise.
+pub const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// The number of operations a single call into a script may perform before it's aborted.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// The name of the function called to render the component.
+const UPDATE_FN: &str = "update";
+
+/// What a call to `update` displays, and the state it sets, if any.
+#[derive(Debug, PartialEq)]
+struct Update {
+    commands: Vec<RenderCommand>,
+    tooltip: Option<String>,
+    state: Option<Option<String>>,
+}
+
+impl Update {
+    /// Parses the value returned by `update`: either the text to display, or a map with a `text`
+    /// and the optional `fg_color`, `tooltip` and `state` keys. With a format, the text is instead
+    /// rendered from the fields of the map.
+    fn parse(value: Dynamic, format: Option<&Template>) -> Result<Self> {
+        if value.is_unit() {
+            return Ok(Self {
+                commands: Vec::new(),
+                tooltip: None,
+                state: None,
+            });
+        }
+
+        if value.is_string() {
+            return Ok(Self {
+                commands: vec![RenderCommand::text(value.to_string())],
+                tooltip: None,
+                state: None,
+            });
+        }
+
+        let Some(map) = value.try_cast::<Map>() else {
+            bail!("update must return a string or a map");
+        };
+        let string = |key: &str| -> Result<Option<String>> {
+            match map.get(key) {
+                None => Ok(None),
+                Some(value) if value.is_unit() => Ok(None),
+                Some(value) if value.is_string() => Ok(Some(value.to_string())),
+                Some(value) => bail!("{} must be a string, not {}", key, value.type_name()),
+            }
+        };
+
+        let text = match format {
+            Some(format) => format.render(&ScriptFields(&map)),
+            None => string("text")?.unwrap_or_default(),
+        };
+
+        Ok(Self {
+            commands: vec![RenderCommand::Text {
+                text,
+                fg_color: string("fg_color")?,
+            }],
+            tooltip: string("tooltip")?,
+            state: map
+                .contains_key("state")
+                .then(|| string("state"))
+                .transpose()?,
+        })
+    }
+}
+
+/// The entries of a map returned by `update`, exposed to the format of the component.
+struct ScriptFields<'a>(&'a Map);
+
+impl Fields for ScriptFields<'_> {
+    fn field(&self, name: &str) -> Option<Value> {
+        let value = self.0.get(name)?;
+        Some(if let Ok(int) = value.as_int() {
+            int.into()
+        } else if let Ok(float) = value.as_float() {
+            float.into()
+        } else if let Ok(bool) = value.as_bool() {
+            bool.into()
+        } else if value.is_unit() {
+            return None;
+        } else {
+            value.to_string().into()
+        })
+    }
+}
+
+/// Creates the engine scripts are run with, with the built-in data providers registered.
+fn engine() -> Engine {
+    let mut engine = Engine::new();
+    engine.set_max_operations(MAX_OPERATIONS);
+    engine.on_print(|message| tracing::info!("Script: {}", message));
+    engine.on_debug(|message, _, position| tracing::debug!("Script {}: {}", position, message));
+
+    engine.register_fn(
+        "now",
+        |format: &str| -> Result<String, Box<EvalAltResult>> {
+            let items = StrftimeItems::new(format).collect::<Vec<_>>();
+            if items.contains(&Item::Error) {
+                return Err(format!("Invalid time format: {}", format).into());
+            }
+            Ok(Local::now()
+                .format_with_items(items.into_iter())
+                .to_string())
+        },
+    );
+    engine.register_fn(
+        "command",
+        |command: &str| -> Result<String, Box<EvalAltResult>> {
+            run_command(command).map_err(|e| format!("{:#}", e).into())
+        },
+    );
+    engine.register_fn(
+        "read_file",
+        |path: &str| -> Result<String, Box<EvalAltResult>> {
+            std::fs::read_to_string(path)
+                .map_err(|e| format!("Could not read {}: {}", path, e).into())
+        },
+    );
+
+    engine
+}
+
+/// Runs a command line with the platform's shell, returning its output without the trailing
+/// newline. The command is killed if it runs for longer than [`command::TIMEOUT`].
+fn run_command(line: &str) -> Result<String> {
+    let mut cmd = if cfg!(windows) {
+        let mut cmd = Command::new("cmd");
+        cmd.args(["/C", line]);
+        cmd
+    } else {
+        let mut cmd = Command::new("sh");
+        cmd.args(["-c", line]);
+        cmd
+    };
+
+    let output = command::output(&mut cmd, command::TIMEOUT)
+        .with_context(|| format!("Could not run command: {}", line))?;
+    Ok(String::from_utf8_lossy(&output).trim_end().to_string())
+}
+
+/// A mouse event, delivered to the script by the component's task.
+enum Event {
+    Click(MouseButton, Point),
+    Scroll(i32),
+}
+
+struct Inner {
+    engine: Engine,
+    ast: AST,
+    format: Option<Template>,
+    /// The map bound to `this` when calling into the script. Only locked off the UI thread, since
+    /// calls into the script may block.
+    this: Mutex<Dynamic>,
+    rendered: Mutex<Rendered>,
+    styles: ComponentStyles,
+    /// The mouse events yet to be delivered by the component's task.
+    events: UnboundedSender<Event>,
+}
+
+impl Inner {
+    fn defines(&self, name: &str) -> bool {
+        self.ast.iter_functions().any(|f| f.name == name)
+    }
+
+    fn call(&self, name: &str, args: impl FuncArgs) -> Result<Dynamic> {
+        let mut this = self.this.lock().unwrap();
+        let options = CallFnOptions::new()
+            .eval_ast(false)
+            .bind_this_ptr(&mut this);
+        self.engine
+            .call_fn_with_options(options, &mut Scope::new(), &self.ast, name, args)
+            .map_err(|e| anyhow!("Script failed in {}: {}", name, e))
+    }
+
+    /// Calls `init`, if the script defines it.
+    fn init(&self) -> Result<()> {
+        if self.defines("init") {
+            // what init returns is of no use
+            let _ = self.call("init", ())?;
+        }
+
+        Ok(())
+    }
+
+    /// Calls `update` and stores what it returns to be drawn.
+    fn update(&self) -> Result<()> {
+        let update = Update::parse(self.call(UPDATE_FN, ())?, self.format.as_ref())?;
+        if let Some(state) = update.state {
+            self.styles.set_state(state);
+        }
+
+        *self.rendered.lock().unwrap() = Rendered {
+            commands: update.commands,
+            tooltip: update.tooltip,
+        };
+        Ok(())
+    }
+
+    /// Calls the handler of the event, if the script defines it, then updates the component.
+    fn handle(&self, event: Event) -> Result<()> {
+        let (name, args) = match event {
+            Event::Click(button, point) => {
+                let button = format!("{:?}", button).to_lowercase();
+                let args = vec![
+                    Dynamic::from(button),
+                    Dynamic::from(point.x as i64),
+                    Dynamic::from(point.y as i64),
+                ];
+                ("on_click", args)
+            }
+            Event::Scroll(delta) => ("on_scroll", vec![Dynamic::from(delta as i64)]),
+        };
+        if !self.defines(name) {
+            return Ok(());
+        }
+
+        let _ = self.call(name, args)?;
+        self.update()
+    }
+
+    /// Queues a mouse event, which is delivered by the component's task once it's started, so that
+    /// the script never runs on the UI thread.
+    fn event(&self, event: Event) {
+        // the receiver lives as long as the component
+        let _ = self.events.send(event);
+    }
+}
+
+/// A component implemented by a script.
+pub struct ScriptComponent {
+    inner: Arc<Inner>,
+    interval: Duration,
+    /// The events queued by [`Inner::event`], received by whichever task started the component.
+    events: tokio::sync::Mutex<UnboundedReceiver<Event>>,
+}
+
+impl ScriptComponent {
+    /// Loads the script at the path.
+    pub fn load(
+        path: &Path,
+        format: Option<Template>,
+        interval: Duration,
+        styles: ComponentStyles,
+    ) -> Result<Self> {
+        let source = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read script: {}", path.display()))?;
+        Self::new(&source, format, interval, styles)
+            .with_context(|| format!("Could not load script: {}", path.display()))
+    }
+
+    /// Compiles the script. Its `init` function, if it defines one, is called once the component is
+    /// started.
+    pub fn new(
+        source: &str,
+        format: Option<Template>,
+        interval: Duration,
+        styles: ComponentStyles,
+    ) -> Result<Self> {
+        let engine = engine();
+        let ast = engine
+            .compile(source)
+            .map_err(|e| anyhow!("Could not compile script: {}", e))?;
+
+        let (events, receiver) = unbounded_channel();
+        let inner = Inner {
+            engine,
+            ast,
+            format,
+            this: Mutex::new(Dynamic::from_map(Map::new())),
+            rendered: Mutex::new(Rendered::default()),
+            styles,
+            events,
+        };
+        if !inner.defines(UPDATE_FN) {
+            bail!("Script does not define {}", UPDATE_FN);
+        }
+
+        Ok(Self {
+            inner: Arc::new(inner),
+            interval,
+            events: tokio::sync::Mutex::new(receiver),
+        })
+    }
+
+    fn with_rendered<T>(&self, f: impl FnOnce(&[RenderCommand], Arc<StyleOptions>) -> T) -> T {
+        let rendered = self.inner.rendered.lock().unwrap();
+        let styles = self.inner.styles.resolve(&render::text(&rendered.commands));
+        f(&rendered.commands, styles)
+    }
+}
+
+#[async_trait]
+impl Component for ScriptComponent {
+    fn styles(&self) -> Arc<StyleOptions> {
+        self.with_rendered(|_, styles| styles)
+    }
+
+    fn set_state(&self, state: Option<String>) {
+        self.inner.styles.set_state(state);
+    }
+
+    fn width(&self, _hwnd: HWND, hdc: HDC) -> i32 {
+        self.with_rendered(|commands, styles| render::width(hdc, commands, &styles))
+    }
+
+    fn draw(&self, _hwnd: HWND, rect: Rect, hdc: HDC) {
+        self.with_rendered(|commands, styles| render::draw(hdc, rect, commands, &styles))
+    }
+
+    fn on_click(&self, button: MouseButton, point: Point) {
+        self.inner.event(Event::Click(button, point));
+    }
+
+    fn on_scroll(&self, delta: i32) {
+        self.inner.event(Event::Scroll(delta));
+    }
+
+    fn tooltip(&self) -> Option<String> {
+        self.inner.rendered.lock().unwrap().tooltip.clone()
+    }
+
+    async fn start(&self, ctx: WinbarContext, _hwnd: HWND, _rect: Rect) {
+        let mut events = self.events.lock().await;
+
+        // init may block like any other call, so it's run off the async workers too
+        let inner = self.inner.clone();
+        let initialized = tokio::task::spawn_blocking(move || inner.init())
+            .await
+            .unwrap_or_else(|e| Err(anyhow!("Script panicked: {}", e)));
+        if let Err(e) = initialized {
+            ctx.fail(format!("{:#}", e));
+            return;
+        }
+
+        let mut ticker = Ticker::new(self.interval);
+        let mut errors = 0;
+        loop {
+            // first tick completes immediately
+            let event = tokio::select! {
+                _ = ticker.tick() => None,
+                Some(event) = events.recv() => Some(event),
+            };
+            let is_update = event.is_none();
+
+            // scripts may block, e.g. on commands, so they're run off the async workers
+            let inner = self.inner.clone();
+            let called = tokio::task::spawn_blocking(move || match event {
+                Some(event) => inner.handle(event),
+                None => inner.update(),
+            })
+            .await
+            .unwrap_or_else(|e| Err(anyhow!("Script panicked: {}", e)));
+            match called {
+                Ok(()) if is_update => errors = 0,
+                Ok(()) => {}
+                Err(e) => {
+                    tracing::error!("{:#}", e);
+                    // what the script last returned stays displayed, unless it keeps failing
+                    if is_update {
+                        errors += 1;
+                        if errors >= supervisor::MAX_UPDATE_ERRORS {
+                            ctx.fail(format!("{:#}", e));
+                            return;
+                        }
+                    }
+                }
+            }
+
+            if let Err(e) = ctx.request_redraw() {
+                tracing::error!("Could not send update window action over channel: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::mpsc;
+
+    use winbar::WinbarAction;
+
+    use super::*;
+
+    fn create(source: &str) -> Result<ScriptComponent> {
+        ScriptComponent::new(
+            source,
+            None,
+            Duration::from_secs(1),
+            StyleOptions::default().into(),
+        )
+    }
+
+    fn commands(component: &ScriptComponent) -> Vec<RenderCommand> {
+        component.with_rendered(|commands, _| commands.to_vec())
+    }
+
+    #[test]
+    fn update_parses_text_and_maps() {
+        assert_eq!(
+            Update::parse(Dynamic::from("cpu".to_string()), None).unwrap(),
+            Update {
+                commands: vec![RenderCommand::text("cpu")],
+                tooltip: None,
+                state: None,
+            }
+        );
+
+        let engine = engine();
+        let map = engine
+            .eval::<Dynamic>(
+                r##"#{ text: "42%", fg_color: "#ff0000", tooltip: "load", state: () }"##,
+            )
+            .unwrap();
+        assert_eq!(
+            Update::parse(map, None).unwrap(),
+            Update {
+                commands: vec![RenderCommand::Text {
+                    text: "42%".to_string(),
+                    fg_color: Some("#ff0000".to_string()),
+                }],
+                tooltip: Some("load".to_string()),
+                state: Some(None),
+            }
+        );
+
+        assert!(Update::parse(Dynamic::from(42_i64), None).is_err());
+        let map = engine.eval::<Dynamic>("#{ text: 42 }").unwrap();
+        assert!(Update::parse(map, None).is_err());
+    }
+
+    #[test]
+    fn update_renders_format_from_fields() {
+        let format = Template::parse("{name|upper} {load:.1}{?busy}!{/}").unwrap();
+        let map = engine()
+            .eval::<Dynamic>(r#"#{ name: "cpu", load: 42.25, busy: true, state: "high" }"#)
+            .unwrap();
+
+        assert_eq!(
+            Update::parse(map, Some(&format)).unwrap(),
+            Update {
+                commands: vec![RenderCommand::text("CPU 42.2!")],
+                tooltip: None,
+                state: Some(Some("high".to_string())),
+            }
+        );
+    }
+
+    #[test]
+    fn script_keeps_state_between_updates() {
+        let component = create(
+            r#"
+            fn init() { this.count = 0; }
+            fn update() {
+                this.count += 1;
+                #{ text: `${this.count}`, state: if this.count > 1 { "many" } else { () } }
+            }
+            fn on_scroll(delta) { this.count += delta; }
+            "#,
+        )
+        .unwrap();
+
+        component.inner.init().unwrap();
+        component.inner.update().unwrap();
+        assert_eq!(commands(&component), vec![RenderCommand::text("1")]);
+        assert_eq!(component.inner.styles.state(), None);
+
+        component.inner.update().unwrap();
+        assert_eq!(commands(&component), vec![RenderCommand::text("2")]);
+        assert_eq!(component.inner.styles.state().as_deref(), Some("many"));
+
+        // handling an event updates the component
+        component.inner.handle(Event::Scroll(10)).unwrap();
+        assert_eq!(commands(&component), vec![RenderCommand::text("13")]);
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn script_handles_events_on_its_task() {
+        let component = Arc::new(
+            create(
+                r#"
+                fn init() { this.x = 0; }
+                fn update() { `${this.x}` }
+                fn on_click(button, x, y) { this.x = x; }
+                "#,
+            )
+            .unwrap(),
+        );
+
+        // events don't wait for the script, e.g. while it's updating
+        {
+            let _updating = component.inner.this.lock().unwrap();
+            component.on_click(MouseButton::Left, Point { x: 7, y: 2 });
+        }
+        assert_eq!(commands(&component), vec![]);
+
+        let (send, recv) = mpsc::channel();
+        let ctx = WinbarContext::new(send);
+        let handle = tokio::spawn({
+            let component = component.clone();
+            async move { component.start(ctx, HWND(0), Rect::default()).await }
+        });
+        for _ in 0..2 {
+            assert!(matches!(
+                recv.recv_timeout(Duration::from_secs(5)),
+                Ok(WinbarAction::UpdateWindow)
+            ));
+        }
+        assert_eq!(commands(&component), vec![RenderCommand::text("7")]);
+
+        handle.abort();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn script_failing_to_update_keeps_its_render_until_it_keeps_failing() {
+        let component = ScriptComponent::new(
+            r#"
+            fn update() {
+                this.updates = (this.updates ?? 0) + 1;
+                if this.updates > 1 { throw "failed"; }
+                "ok"
+            }
+            "#,
+            None,
+            Duration::from_millis(10),
+            StyleOptions::default().into(),
+        )
+        .unwrap();
+        let (send, recv) = mpsc::channel();
+        let ctx = WinbarContext::new(send);
+
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            component.start(ctx.clone(), HWND(0), Rect::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(commands(&component), vec![RenderCommand::text("ok")]);
+        assert_eq!(recv.try_iter().count(), 3);
+        assert!(ctx.take_failure().unwrap().contains("failed"));
+    }
+
+    #[tokio::test]
+    async fn script_failing_to_init_fails_once_started() {
+        let component = create(r#"fn init() { throw "oops" } fn update() { "ok" }"#).unwrap();
+        let (send, _recv) = mpsc::channel();
+        let ctx = WinbarContext::new(send);
+
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            component.start(ctx.clone(), HWND(0), Rect::default()),
+        )
+        .await
+        .unwrap();
+        assert!(ctx.take_failure().unwrap().contains("oops"));
+        assert_eq!(commands(&component), vec![]);
+    }
+
+    #[test]
+    fn script_without_update_fails() {
+        assert!(create("fn init() {}").is_err());
+        assert!(create("fn update( {").is_err());
+    }
+
+    #[test]
+    fn script_running_forever_is_aborted() {
+        let component = create("fn update() { loop {} }").unwrap();
+        assert!(component.inner.update().is_err());
+    }
+
+    #[test]
+    fn script_reads_files_and_time() {
+        let path = std::env::temp_dir().join("winbar-script-test.txt");
+        std::fs::write(&path, "contents").unwrap();
+
+        let component = create(&format!(
+            r#"fn update() {{ read_file("{}") + now("%Y").len }}"#,
+            path.to_string_lossy().replace('\\', "\\\\")
+        ))
+        .unwrap();
+        component.inner.update().unwrap();
+        assert_eq!(commands(&component), vec![RenderCommand::text("contents4")]);
+
+        let component = create(r#"fn update() { now("%Q") }"#).unwrap();
+        assert!(component.inner.update().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn script_runs_commands_with_the_shell() {
+        let component = create(r#"fn update() { command("echo hello | tr a-z A-Z") }"#).unwrap();
+        component.inner.update().unwrap();
+        assert_eq!(commands(&component), vec![RenderCommand::text("HELLO")]);
+    }
+}
@@ -2,80 +2,75 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use winbar::{
-    styles::{StyleOptions, Styles},
+    styles::{ComponentStyles, StyleOptions, Styles},
     util::rect::Rect,
     Component, WinbarContext,
 };
-use windows::Win32::{
-    Foundation::{HWND, SIZE},
-    Graphics::Gdi::{DrawTextW, GetTextExtentPoint32W, DT_CENTER, DT_SINGLELINE, DT_VCENTER, HDC},
-};
-
-use crate::windows_api::WindowsApi;
+use windows::Win32::{Foundation::HWND, Graphics::Gdi::HDC};
 
 pub struct StaticTextComponent {
     text: String,
-    styles: Arc<StyleOptions>,
+    styles: ComponentStyles,
 }
 
 impl StaticTextComponent {
-    pub fn new(text: String, styles: StyleOptions) -> Self {
-        Self {
-            text,
-            styles: Arc::new(styles),
-        }
+    pub fn new(text: String, styles: ComponentStyles) -> Self {
+        Self { text, styles }
     }
 }
 
 #[async_trait]
 impl Component for StaticTextComponent {
     fn styles(&self) -> Arc<StyleOptions> {
-        self.styles.clone()
+        self.styles.resolve(&self.text)
     }
 
-    fn width(&self, _hwnd: HWND, hdc: HDC) -> i32 {
-        unsafe {
-            let mut length: SIZE = SIZE::default();
-
-            GetTextExtentPoint32W(hdc, &WindowsApi::str_to_u16_slice(&self.text), &mut length);
+    fn set_state(&self, state: Option<String>) {
+        self.styles.set_state(state);
+    }
 
-            length.cx + self.styles.padding_x * 2
+    /// The full text, if the width of the component is constrained such that it may be truncated.
+    fn tooltip(&self) -> Option<String> {
+        let styles = self.styles();
+        if styles.max_width.is_some() || styles.fixed_width.is_some() {
+            Some(self.text.clone())
+        } else {
+            None
         }
     }
 
+    fn width(&self, _hwnd: HWND, hdc: HDC) -> i32 {
+        Styles::text_component_width(hdc, &self.text, &self.styles())
+    }
+
     fn draw(&self, _hwnd: HWND, rect: Rect, hdc: HDC) {
-        Styles::draw_rect(hdc, &rect, &self.styles.border_style);
+        let styles = self.styles();
 
-        unsafe {
-            // let mut graphics = MaybeUninit::uninit();
-            // GdipCreateFromHDC(hdc, graphics.as_mut_ptr());
+        Styles::draw_rect(hdc, &rect, &styles);
 
-            // // based on: https://github.com/davidrios/gdiplus-rs
-            // let g = graphics.assume_init();
+        // let mut graphics = MaybeUninit::uninit();
+        // GdipCreateFromHDC(hdc, graphics.as_mut_ptr());
 
-            // let mut bg_pen = MaybeUninit::uninit();
-            // GdipCreatePen1(default_bg_color, 1.0, UnitPixel, bg_pen.as_mut_ptr());
+        // // based on: https://github.com/davidrios/gdiplus-rs
+        // let g = graphics.assume_init();
 
-            // let pen = bg_pen.assume_init();
+        // let mut bg_pen = MaybeUninit::uninit();
+        // GdipCreatePen1(default_bg_color, 1.0, UnitPixel, bg_pen.as_mut_ptr());
 
-            // let mut bg_brush = MaybeUninit::uninit();
-            // GdipGetPenBrushFill(pen, bg_brush.as_mut_ptr());
+        // let pen = bg_pen.assume_init();
 
-            // let brush = bg_brush.assume_init();
+        // let mut bg_brush = MaybeUninit::uninit();
+        // GdipGetPenBrushFill(pen, bg_brush.as_mut_ptr());
 
-            // GdipFillRectangleI(g, brush, rect.x, rect.y, rect.x2(), rect.y2());
+        // let brush = bg_brush.assume_init();
 
-            DrawTextW(
-                hdc,
-                &mut WindowsApi::str_to_u16_slice(&self.text),
-                &mut rect.into(),
-                DT_SINGLELINE | DT_VCENTER | DT_CENTER,
-            );
+        // GdipFillRectangleI(g, brush, rect.x, rect.y, rect.x2(), rect.y2());
 
-            // GdipDeleteBrush(brush);
-            // GdipDeletePen(pen);
-            // GdipDeleteGraphics(g);
-        }
+        Styles::draw_text(hdc, &rect, &self.text, &styles);
+
+        // GdipDeleteBrush(brush);
+        // GdipDeletePen(pen);
+        // GdipDeleteGraphics(g);
     }
 
     async fn start(&self, _ctx: WinbarContext, _hwnd: HWND, _rect: Rect) {}
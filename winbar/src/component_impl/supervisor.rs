@@ -0,0 +1,343 @@
+//! Supervision of component tasks.
+//!
+//! Component code runs in isolation from the rest of winbar: panics in a component's `start` future,
+//! and failures it reports through its context, are caught and the component is restarted with an
+//! exponential backoff, while panics in `width`
+//! or `draw` are caught by the painting code, which marks the component as failed and displays a
+//! placeholder in its place until it's restarted.
+use std::{
+    any::Any,
+    cmp,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tokio::task::JoinHandle;
+use winbar::{protocol::ComponentStatus, util::rect::Rect, Component, WinbarContext};
+use windows::Win32::Foundation::HWND;
+
+/// The text displayed in place of a failed component.
+pub const FAILED_PLACEHOLDER: &str = "\u{26A0}";
+
+/// The number of consecutive updates a component may fail before it's failed and restarted. Until
+/// then, the component keeps displaying what it last rendered.
+pub const MAX_UPDATE_ERRORS: u32 = 3;
+
+const INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// The health of a component, shared between the component's task and the painting code.
+#[derive(Clone, Default)]
+pub struct ComponentHealth {
+    inner: Arc<Mutex<HealthState>>,
+}
+
+#[derive(Clone, Default)]
+struct HealthState {
+    status: ComponentStatus,
+    failures: u32,
+    last_error: Option<String>,
+}
+
+impl ComponentHealth {
+    pub fn status(&self) -> ComponentStatus {
+        self.with(|state| state.status.clone())
+    }
+
+    pub fn failures(&self) -> u32 {
+        self.with(|state| state.failures)
+    }
+
+    pub fn last_error(&self) -> Option<String> {
+        self.with(|state| state.last_error.clone())
+    }
+
+    pub fn is_failed(&self) -> bool {
+        self.status() == ComponentStatus::Failed
+    }
+
+    pub fn set_status(&self, status: ComponentStatus) {
+        self.with(|state| state.status = status);
+    }
+
+    /// Marks the component as failed, returning the total number of failures.
+    pub fn fail(&self, error: String) -> u32 {
+        self.with(|state| {
+            state.status = ComponentStatus::Failed;
+            state.failures += 1;
+            state.last_error = Some(error);
+            state.failures
+        })
+    }
+
+    fn with<T>(&self, f: impl FnOnce(&mut HealthState) -> T) -> T {
+        // a poisoned lock still holds valid health data, so there is no reason to give up on it
+        let mut state = self
+            .inner
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        f(&mut state)
+    }
+}
+
+/// Returns the time to wait before restarting a component that has failed the given number of
+/// times. The backoff doubles with every failure, up to a maximum.
+pub fn backoff(failures: u32) -> Duration {
+    let exponent = failures.saturating_sub(1).min(16);
+    cmp::min(INITIAL_BACKOFF * 2u32.pow(exponent), MAX_BACKOFF)
+}
+
+/// Returns the message of a caught panic.
+pub fn panic_message(payload: &(dyn Any + Send)) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        "unknown panic".to_string()
+    }
+}
+
+/// Everything needed to run a component task.
+pub struct SupervisedTask {
+    pub component: Arc<dyn Component + Send + Sync>,
+    pub health: ComponentHealth,
+    pub ctx: WinbarContext,
+    pub hwnd: HWND,
+    pub location: Rect,
+}
+
+impl SupervisedTask {
+    /// Runs the component until its context is cancelled, restarting it with a backoff whenever
+    /// `start` panics or fails. `stop` is called once the component is cancelled.
+    ///
+    /// If a previous task is given, the component is only started once the previous task finishes.
+    /// The component is additionally only started after the given delay.
+    pub async fn run(self, previous: Option<JoinHandle<()>>, delay: Duration) {
+        let token = self.ctx.cancellation_token().clone();
+
+        if let Some(previous) = previous {
+            let _ = previous.await;
+        }
+
+        if !delay.is_zero() {
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = token.cancelled() => {}
+            }
+        }
+
+        while !token.is_cancelled() {
+            self.health.set_status(ComponentStatus::Running);
+
+            let run = tokio::spawn(Self::run_once(
+                self.component.clone(),
+                self.ctx.clone(),
+                self.hwnd,
+                self.location,
+            ));
+            let message = match run.await {
+                Err(e) if e.is_panic() => panic_message(e.into_panic().as_ref()),
+                Ok(Some(error)) => error,
+                _ => break,
+            };
+            let failures = self.health.fail(message.clone());
+            let delay = backoff(failures);
+            tracing::error!(
+                "Component failed ({} failures), restarting in {:?}: {}",
+                failures,
+                delay,
+                message
+            );
+            let _ = self.ctx.request_redraw();
+
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = token.cancelled() => {}
+            }
+        }
+
+        let component = self.component.clone();
+        if let Err(e) = tokio::spawn(async move { component.stop().await }).await {
+            if e.is_panic() {
+                tracing::error!(
+                    "Component panicked while stopping: {}",
+                    panic_message(e.into_panic().as_ref())
+                );
+            }
+        }
+
+        if !self.health.is_failed() {
+            self.health.set_status(ComponentStatus::Stopped);
+        }
+    }
+
+    /// Runs the component's `start` future until the context is cancelled, returning the error the
+    /// component failed with, if any. If `start` returns on its own without failing, the component
+    /// is still considered running until cancelled.
+    async fn run_once(
+        component: Arc<dyn Component + Send + Sync>,
+        ctx: WinbarContext,
+        hwnd: HWND,
+        location: Rect,
+    ) -> Option<String> {
+        let token = ctx.cancellation_token().clone();
+        tokio::select! {
+            _ = component.start(ctx.clone(), hwnd, location) => {
+                if let Some(error) = ctx.take_failure() {
+                    return Some(error);
+                }
+                token.cancelled().await;
+            }
+            _ = token.cancelled() => {}
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    };
+
+    use async_trait::async_trait;
+    use winbar::styles::StyleOptions;
+    use windows::Win32::Graphics::Gdi::HDC;
+
+    use super::*;
+
+    #[test]
+    fn backoff_doubles_up_to_max() {
+        assert_eq!(backoff(1), Duration::from_millis(500));
+        assert_eq!(backoff(2), Duration::from_secs(1));
+        assert_eq!(backoff(4), Duration::from_secs(4));
+        assert_eq!(backoff(100), MAX_BACKOFF);
+    }
+
+    #[test]
+    fn panic_message_works() {
+        assert_eq!(panic_message(&"oops"), "oops");
+        assert_eq!(panic_message(&"oops".to_string()), "oops");
+        assert_eq!(panic_message(&1), "unknown panic");
+    }
+
+    #[derive(Default)]
+    struct PanickingComponent {
+        starts: AtomicUsize,
+        stops: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Component for PanickingComponent {
+        fn styles(&self) -> Arc<StyleOptions> {
+            Arc::new(StyleOptions::default())
+        }
+
+        fn width(&self, _hwnd: HWND, _hdc: HDC) -> i32 {
+            0
+        }
+
+        fn draw(&self, _hwnd: HWND, _rect: Rect, _hdc: HDC) {}
+
+        async fn start(&self, _ctx: WinbarContext, _hwnd: HWND, _rect: Rect) {
+            if self.starts.fetch_add(1, Ordering::SeqCst) == 0 {
+                panic!("first start fails");
+            }
+        }
+
+        async fn stop(&self) {
+            self.stops.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn run_restarts_panicking_component() {
+        let component = Arc::new(PanickingComponent::default());
+        let health = ComponentHealth::default();
+        let (send, _recv) = mpsc::channel();
+        let ctx = WinbarContext::new(send);
+        let token = ctx.cancellation_token().clone();
+
+        let handle = tokio::spawn(
+            SupervisedTask {
+                component: component.clone(),
+                health: health.clone(),
+                ctx,
+                hwnd: HWND(0),
+                location: Rect::default(),
+            }
+            .run(None, Duration::ZERO),
+        );
+
+        while component.starts.load(Ordering::SeqCst) < 2 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(health.status(), ComponentStatus::Running);
+        assert_eq!(health.failures(), 1);
+        assert_eq!(health.last_error().as_deref(), Some("first start fails"));
+
+        token.cancel();
+        handle.await.unwrap();
+        assert_eq!(component.stops.load(Ordering::SeqCst), 1);
+        assert_eq!(health.status(), ComponentStatus::Stopped);
+    }
+
+    /// A component that reports a failure through its context on its first start.
+    #[derive(Default)]
+    struct FailingComponent {
+        starts: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl Component for FailingComponent {
+        fn styles(&self) -> Arc<StyleOptions> {
+            Arc::new(StyleOptions::default())
+        }
+
+        fn width(&self, _hwnd: HWND, _hdc: HDC) -> i32 {
+            0
+        }
+
+        fn draw(&self, _hwnd: HWND, _rect: Rect, _hdc: HDC) {}
+
+        async fn start(&self, ctx: WinbarContext, _hwnd: HWND, _rect: Rect) {
+            if self.starts.fetch_add(1, Ordering::SeqCst) == 0 {
+                ctx.fail("first start fails");
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn run_restarts_failed_component() {
+        let component = Arc::new(FailingComponent::default());
+        let health = ComponentHealth::default();
+        let (send, _recv) = mpsc::channel();
+        let ctx = WinbarContext::new(send);
+        let token = ctx.cancellation_token().clone();
+
+        let handle = tokio::spawn(
+            SupervisedTask {
+                component: component.clone(),
+                health: health.clone(),
+                ctx,
+                hwnd: HWND(0),
+                location: Rect::default(),
+            }
+            .run(None, Duration::ZERO),
+        );
+
+        while component.starts.load(Ordering::SeqCst) < 2 {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        assert_eq!(health.status(), ComponentStatus::Running);
+        assert_eq!(health.failures(), 1);
+        assert_eq!(health.last_error().as_deref(), Some("first start fails"));
+
+        token.cancel();
+        handle.await.unwrap();
+        assert_eq!(health.status(), ComponentStatus::Stopped);
+    }
+}
@@ -0,0 +1,977 @@
+//! Components implemented by sandboxed WebAssembly modules.
+//!
+//! Modules run in an embedded interpreter and can only interact with winbar through the host
+//! functions imported from the `winbar` module. Every call into a module is limited in how many
+//! instructions it may execute and how much memory it may grow to, and access to files, processes
+//! and HTTP has to be granted to each component explicitly through its [`WasmCapabilities`].
+//!
+//! See `docs/wasm.md` for the interface modules implement.
+use std::{
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender},
+    time::Instant,
+};
+use url::Url;
+use wasmi::{
+    Caller, Engine, Extern, Instance, Linker, Memory, Module, Store, StoreLimits,
+    StoreLimitsBuilder, WasmParams, WasmResults,
+};
+use winbar::{
+    input::{Hover, MouseButton, Point},
+    plugin::RenderCommand,
+    styles::{ComponentStyles, StyleOptions},
+    util::rect::Rect,
+    Component, WinbarContext,
+};
+use windows::Win32::{Foundation::HWND, Graphics::Gdi::HDC};
+
+use super::{
+    command,
+    render::{self, Rendered},
+    supervisor,
+};
+
+/// The module the host functions are imported from.
+const HOST_MODULE: &str = "winbar";
+
+/// The number of fuel units, roughly one per executed instruction, a single call into a module may
+/// consume before it's trapped.
+const FUEL_PER_CALL: u64 = 10_000_000;
+
+/// The maximum size, in bytes, the memory of a module may grow to.
+const MEMORY_LIMIT: usize = 16 * 1024 * 1024;
+
+/// The shortest interval at which a module may be ticked.
+const MIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The timeout of HTTP requests made by modules.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The number of redirects an HTTP request made by a module may follow.
+const MAX_REDIRECTS: usize = 5;
+
+/// Returned by capability-gated host functions when the capability was not granted.
+const DENIED: i32 = -1;
+
+/// Returned by capability-gated host functions when the operation failed.
+const FAILED: i32 = -2;
+
+/// The resources a module may access. Nothing is accessible by default.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct WasmCapabilities {
+    /// The files and directories the module may read from
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+    /// The programs the module may run, as given to `run_command`
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// The hosts the module may make HTTP requests to
+    #[serde(default)]
+    pub http: Vec<String>,
+}
+
+impl WasmCapabilities {
+    /// Whether the path is one of the allowed files or within one of the allowed directories.
+    /// Paths are canonicalized beforehand, so `..` and links can't be used to escape them.
+    pub fn allows_file(&self, path: &Path) -> Result<bool> {
+        let path = path
+            .canonicalize()
+            .with_context(|| format!("Could not resolve path: {}", path.display()))?;
+
+        Ok(self.files.iter().any(|allowed| {
+            allowed
+                .canonicalize()
+                .is_ok_and(|allowed| path.starts_with(allowed))
+        }))
+    }
+
+    pub fn allows_command(&self, program: &str) -> bool {
+        self.commands.iter().any(|allowed| allowed == program)
+    }
+
+    pub fn allows_url(&self, url: &str) -> bool {
+        url_host(url).is_some_and(|host| {
+            self.http
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(&host))
+        })
+    }
+}
+
+/// Returns the host of an HTTP or HTTPS URL, as parsed by the `url` crate that requests are made
+/// with.
+pub fn url_host(url: &str) -> Option<String> {
+    let url = Url::parse(url).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+
+    url.host_str().map(str::to_string)
+}
+
+/// The state of the host shared with the host functions.
+struct HostState {
+    capabilities: WasmCapabilities,
+    limits: StoreLimits,
+    /// The commands emitted by the current call to `render`.
+    commands: Vec<RenderCommand>,
+    tooltip: Option<String>,
+    /// A state set by the module, which is yet to be applied to the component's styles.
+    state: Option<Option<String>>,
+    interval: Option<Duration>,
+    /// The result of the last capability-gated call, retrieved by the module with `take_result`.
+    result: Vec<u8>,
+}
+
+struct WasmInstance {
+    store: Store<HostState>,
+    instance: Instance,
+}
+
+impl WasmInstance {
+    fn new(
+        wasm: &[u8],
+        config: &serde_json::Value,
+        capabilities: WasmCapabilities,
+        interval: Option<Duration>,
+    ) -> Result<Self> {
+        let mut engine_config = wasmi::Config::default();
+        engine_config.consume_fuel(true);
+        let engine = Engine::new(&engine_config);
+        let module = Module::new(&engine, wasm).context("Invalid WASM module")?;
+
+        let mut store = Store::new(
+            &engine,
+            HostState {
+                capabilities,
+                limits: StoreLimitsBuilder::new().memory_size(MEMORY_LIMIT).build(),
+                commands: Vec::new(),
+                tooltip: None,
+                state: None,
+                interval: interval.map(|interval| interval.max(MIN_INTERVAL)),
+                result: Vec::new(),
+            },
+        );
+        store.limiter(|state| &mut state.limits);
+        Self::refuel(&mut store)?;
+
+        let mut linker = Linker::new(&engine);
+        link(&mut linker)?;
+        let instance = linker
+            .instantiate(&mut store, &module)
+            .and_then(|instance| instance.start(&mut store))
+            .context("Could not instantiate WASM module")?;
+        if instance.get_memory(&store, "memory").is_none() {
+            bail!("WASM module does not export its memory");
+        }
+
+        let mut instance = Self { store, instance };
+        instance.init(config)?;
+        Ok(instance)
+    }
+
+    fn refuel(store: &mut Store<HostState>) -> Result<()> {
+        store
+            .set_fuel(FUEL_PER_CALL)
+            .map_err(|e| anyhow!("Could not fuel WASM module: {}", e))
+    }
+
+    /// Passes the config to the module, if it exports `alloc` and `init`.
+    fn init(&mut self, config: &serde_json::Value) -> Result<()> {
+        let has_init = self.instance.get_func(&self.store, "alloc").is_some()
+            && self.instance.get_func(&self.store, "init").is_some();
+        if !has_init {
+            return Ok(());
+        }
+
+        let config = config.to_string();
+        let ptr = self
+            .call::<i32, i32>("alloc", config.len() as i32)?
+            .unwrap_or_default();
+        let memory = self.memory();
+        memory
+            .write(&mut self.store, ptr as u32 as usize, config.as_bytes())
+            .map_err(|e| anyhow!("Could not write config to WASM memory: {}", e))?;
+
+        let status = self
+            .call::<(i32, i32), i32>("init", (ptr, config.len() as i32))?
+            .unwrap_or_default();
+        if status != 0 {
+            bail!("WASM module rejected its config with status {}", status);
+        }
+
+        Ok(())
+    }
+
+    fn memory(&self) -> Memory {
+        // the memory export is checked for when the instance is created
+        self.instance.get_memory(&self.store, "memory").unwrap()
+    }
+
+    /// Calls a function exported by the module, returning `None` if it's not exported.
+    fn call<P: WasmParams, R: WasmResults>(&mut self, name: &str, params: P) -> Result<Option<R>> {
+        let Some(func) = self.instance.get_func(&self.store, name) else {
+            return Ok(None);
+        };
+
+        Self::refuel(&mut self.store)?;
+        let results = func
+            .typed::<P, R>(&self.store)
+            .and_then(|func| func.call(&mut self.store, params))
+            .with_context(|| format!("WASM module failed in {}", name))?;
+        Ok(Some(results))
+    }
+
+    fn render(&mut self) -> Result<Rendered> {
+        self.store.data_mut().commands.clear();
+        if self.call::<(), ()>("render", ())?.is_none() {
+            bail!("WASM module does not export render");
+        }
+
+        let state = self.store.data_mut();
+        Ok(Rendered {
+            commands: std::mem::take(&mut state.commands),
+            tooltip: state.tooltip.clone(),
+        })
+    }
+}
+
+/// Reads a string from the memory of the calling module.
+fn read_string(caller: &Caller<'_, HostState>, ptr: i32, len: i32) -> Result<String, wasmi::Error> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmi::Error::new("module does not export its memory"))?;
+    let start = ptr as u32 as usize;
+    let end = start.saturating_add(len as u32 as usize);
+    let bytes = memory
+        .data(caller)
+        .get(start..end)
+        .ok_or_else(|| wasmi::Error::new("string is out of bounds"))?;
+
+    String::from_utf8(bytes.to_vec()).map_err(|e| wasmi::Error::new(e.to_string()))
+}
+
+/// Runs a capability-gated operation, storing its result for `take_result` and returning its
+/// length, or [`DENIED`] or [`FAILED`].
+fn gated(
+    caller: &mut Caller<'_, HostState>,
+    operation: &str,
+    allowed: Result<bool>,
+    f: impl FnOnce() -> Result<Vec<u8>>,
+) -> i32 {
+    match allowed {
+        Ok(true) => {}
+        Ok(false) => {
+            tracing::warn!("WASM module was denied {}", operation);
+            return DENIED;
+        }
+        Err(e) => {
+            tracing::warn!("WASM module failed {}: {}", operation, e);
+            return FAILED;
+        }
+    }
+
+    match f() {
+        Ok(result) => {
+            let len = result.len() as i32;
+            caller.data_mut().result = result;
+            len
+        }
+        Err(e) => {
+            tracing::warn!("WASM module failed {}: {}", operation, e);
+            FAILED
+        }
+    }
+}
+
+fn run_command(args: &[String]) -> Result<Vec<u8>> {
+    command::output(Command::new(&args[0]).args(&args[1..]), command::TIMEOUT)
+}
+
+/// Makes a GET request to the URL, following redirects only to hosts the capabilities allow.
+fn http_get(url: &str, capabilities: &WasmCapabilities) -> Result<Vec<u8>> {
+    let agent = ureq::AgentBuilder::new()
+        .redirects(0)
+        .timeout(HTTP_TIMEOUT)
+        .build();
+
+    let mut url = url.to_string();
+    for _ in 0..=MAX_REDIRECTS {
+        let request = agent.get(&url);
+        let response = request.clone().call()?;
+        if !(300..400).contains(&response.status()) {
+            return Ok(response.into_string()?.into_bytes());
+        }
+
+        let location = response
+            .header("Location")
+            .ok_or_else(|| anyhow!("Redirect from {} has no location", url))?;
+        let next = request.request_url()?.as_url().join(location)?.to_string();
+        if !capabilities.allows_url(&next) {
+            bail!("Redirect from {} to {} is not allowed", url, next);
+        }
+        url = next;
+    }
+
+    bail!("Too many redirects from {}", url)
+}
+
+/// Defines the host functions.
+fn link(linker: &mut Linker<HostState>) -> Result<()> {
+    linker
+        .func_wrap(
+            HOST_MODULE,
+            "text",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let text = read_string(&caller, ptr, len)?;
+                caller.data_mut().commands.push(RenderCommand::text(text));
+                Ok(())
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "colored_text",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32, cptr: i32, clen: i32| {
+                let text = read_string(&caller, ptr, len)?;
+                let color = read_string(&caller, cptr, clen)?;
+                caller.data_mut().commands.push(RenderCommand::Text {
+                    text,
+                    fg_color: Some(color),
+                });
+                Ok(())
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "space",
+            |mut caller: Caller<'_, HostState>, width: i32| {
+                caller.data_mut().commands.push(RenderCommand::Space(width));
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "set_state",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let state = read_string(&caller, ptr, len)?;
+                caller.data_mut().state = Some(Some(state));
+                Ok(())
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "clear_state",
+            |mut caller: Caller<'_, HostState>| {
+                caller.data_mut().state = Some(None);
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "tooltip",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let tooltip = read_string(&caller, ptr, len)?;
+                caller.data_mut().tooltip = (!tooltip.is_empty()).then_some(tooltip);
+                Ok(())
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "set_interval",
+            |mut caller: Caller<'_, HostState>, ms: i32| {
+                caller.data_mut().interval =
+                    (ms > 0).then(|| Duration::from_millis(ms as u64).max(MIN_INTERVAL));
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "log",
+            |caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let message = read_string(&caller, ptr, len)?;
+                tracing::info!("WASM module: {}", message);
+                Ok(())
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "read_file",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let path = PathBuf::from(read_string(&caller, ptr, len)?);
+                let allowed = caller.data().capabilities.allows_file(&path);
+                Ok(gated(&mut caller, "reading a file", allowed, || {
+                    Ok(std::fs::read(&path)?)
+                }))
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "run_command",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let args: Vec<String> = serde_json::from_str(&read_string(&caller, ptr, len)?)
+                    .map_err(|e| wasmi::Error::new(e.to_string()))?;
+                let allowed = Ok(args
+                    .first()
+                    .is_some_and(|program| caller.data().capabilities.allows_command(program)));
+                Ok(gated(&mut caller, "running a command", allowed, || {
+                    run_command(&args)
+                }))
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "http_get",
+            |mut caller: Caller<'_, HostState>, ptr: i32, len: i32| {
+                let url = read_string(&caller, ptr, len)?;
+                let capabilities = caller.data().capabilities.clone();
+                let allowed = Ok(capabilities.allows_url(&url));
+                Ok(gated(&mut caller, "an HTTP request", allowed, || {
+                    http_get(&url, &capabilities)
+                }))
+            },
+        )?
+        .func_wrap(
+            HOST_MODULE,
+            "take_result",
+            |mut caller: Caller<'_, HostState>, ptr: i32| {
+                let result = std::mem::take(&mut caller.data_mut().result);
+                let memory = caller
+                    .get_export("memory")
+                    .and_then(Extern::into_memory)
+                    .ok_or_else(|| wasmi::Error::new("module does not export its memory"))?;
+                memory
+                    .write(&mut caller, ptr as u32 as usize, &result)
+                    .map_err(|e| wasmi::Error::new(e.to_string()))
+            },
+        )?;
+
+    Ok(())
+}
+
+/// A call into the module made by the component's task.
+enum Call {
+    Tick,
+    Click(MouseButton, Point),
+    Scroll(i32),
+    Hover(Hover),
+}
+
+struct Inner {
+    /// Only locked off the UI thread, since calls into the module may block.
+    instance: Mutex<WasmInstance>,
+    rendered: Mutex<Rendered>,
+    styles: ComponentStyles,
+    /// The mouse events yet to be delivered by the component's task.
+    events: UnboundedSender<Call>,
+}
+
+impl Inner {
+    /// Calls a function exported by the module, then renders the module again.
+    fn invoke<P: WasmParams, R: WasmResults>(&self, name: &str, params: P) -> Result<()> {
+        let (rendered, state) = {
+            let mut instance = self.instance.lock().unwrap();
+            instance.call::<P, R>(name, params)?;
+            let rendered = instance.render()?;
+            (rendered, instance.store.data_mut().state.take())
+        };
+
+        if let Some(state) = state {
+            self.styles.set_state(state);
+        }
+        *self.rendered.lock().unwrap() = rendered;
+        Ok(())
+    }
+
+    fn call(&self, call: Call) -> Result<()> {
+        match call {
+            Call::Tick => self.invoke::<(), ()>("tick", ()),
+            Call::Click(button, point) => {
+                self.invoke::<_, ()>("on_click", (button.to_abi() as i32, point.x, point.y))
+            }
+            Call::Scroll(delta) => self.invoke::<_, ()>("on_scroll", delta),
+            Call::Hover(hover) => self.invoke::<_, ()>("on_hover", (hover == Hover::Enter) as i32),
+        }
+    }
+
+    /// Queues a mouse event, which is delivered by the component's task once it's started, so that
+    /// the module never runs on the UI thread.
+    fn event(&self, call: Call) {
+        // the receiver lives as long as the component
+        let _ = self.events.send(call);
+    }
+
+    fn interval(&self) -> Option<Duration> {
+        self.instance.lock().unwrap().store.data().interval
+    }
+}
+
+/// A component implemented by a WebAssembly module.
+pub struct WasmComponent {
+    inner: Arc<Inner>,
+    /// The events queued by [`Inner::event`], received by whichever task started the component.
+    events: tokio::sync::Mutex<UnboundedReceiver<Call>>,
+}
+
+impl WasmComponent {
+    /// Loads the module at the path, initializing it with the given config. The module is ticked at
+    /// the interval, if given, until it sets its own.
+    pub fn load(
+        path: &Path,
+        config: &serde_json::Value,
+        capabilities: WasmCapabilities,
+        interval: Option<Duration>,
+        styles: ComponentStyles,
+    ) -> Result<Self> {
+        let wasm = std::fs::read(path)
+            .with_context(|| format!("Could not read WASM module: {}", path.display()))?;
+        Self::new(&wasm, config, capabilities, interval, styles)
+            .with_context(|| format!("Could not load WASM module: {}", path.display()))
+    }
+
+    /// Creates the component from the binary format of a module.
+    pub fn new(
+        wasm: &[u8],
+        config: &serde_json::Value,
+        capabilities: WasmCapabilities,
+        interval: Option<Duration>,
+        styles: ComponentStyles,
+    ) -> Result<Self> {
+        let mut instance = WasmInstance::new(wasm, config, capabilities, interval)?;
+        let rendered = instance.render()?;
+        if let Some(state) = instance.store.data_mut().state.take() {
+            styles.set_state(state);
+        }
+
+        let (events, receiver) = unbounded_channel();
+        Ok(Self {
+            inner: Arc::new(Inner {
+                instance: Mutex::new(instance),
+                rendered: Mutex::new(rendered),
+                styles,
+                events,
+            }),
+            events: tokio::sync::Mutex::new(receiver),
+        })
+    }
+
+    fn with_rendered<T>(&self, f: impl FnOnce(&[RenderCommand], Arc<StyleOptions>) -> T) -> T {
+        let rendered = self.inner.rendered.lock().unwrap();
+        let styles = self.inner.styles.resolve(&render::text(&rendered.commands));
+        f(&rendered.commands, styles)
+    }
+}
+
+#[async_trait]
+impl Component for WasmComponent {
+    fn styles(&self) -> Arc<StyleOptions> {
+        self.with_rendered(|_, styles| styles)
+    }
+
+    fn set_state(&self, state: Option<String>) {
+        self.inner.styles.set_state(state);
+    }
+
+    fn width(&self, _hwnd: HWND, hdc: HDC) -> i32 {
+        self.with_rendered(|commands, styles| render::width(hdc, commands, &styles))
+    }
+
+    fn draw(&self, _hwnd: HWND, rect: Rect, hdc: HDC) {
+        self.with_rendered(|commands, styles| render::draw(hdc, rect, commands, &styles))
+    }
+
+    fn on_click(&self, button: MouseButton, point: Point) {
+        self.inner.event(Call::Click(button, point));
+    }
+
+    fn on_scroll(&self, delta: i32) {
+        self.inner.event(Call::Scroll(delta));
+    }
+
+    fn on_hover(&self, hover: Hover) {
+        self.inner.event(Call::Hover(hover));
+    }
+
+    fn tooltip(&self) -> Option<String> {
+        self.inner.rendered.lock().unwrap().tooltip.clone()
+    }
+
+    async fn start(&self, ctx: WinbarContext, _hwnd: HWND, _rect: Rect) {
+        let token = ctx.cancellation_token().clone();
+        let mut events = self.events.lock().await;
+        let mut next_tick = self
+            .inner
+            .interval()
+            .map(|interval| Instant::now() + interval);
+
+        let mut errors = 0;
+        loop {
+            let call = tokio::select! {
+                _ = async {
+                    match next_tick {
+                        Some(next_tick) => tokio::time::sleep_until(next_tick).await,
+                        None => std::future::pending().await,
+                    }
+                } => Call::Tick,
+                Some(event) = events.recv() => event,
+                _ = token.cancelled() => break,
+            };
+            let is_tick = matches!(call, Call::Tick);
+
+            // modules may block, e.g. on HTTP requests, so they're run off the async workers
+            let inner = self.inner.clone();
+            let called = tokio::task::spawn_blocking(move || inner.call(call))
+                .await
+                .unwrap_or_else(|e| Err(anyhow!("WASM module panicked: {}", e)));
+            match called {
+                Ok(()) if is_tick => errors = 0,
+                Ok(()) => {}
+                Err(e) => {
+                    tracing::error!("{:#}", e);
+                    // what the module last rendered stays displayed, unless it keeps failing
+                    if is_tick {
+                        errors += 1;
+                        if errors >= supervisor::MAX_UPDATE_ERRORS {
+                            ctx.fail(format!("{:#}", e));
+                            return;
+                        }
+                    }
+                }
+            }
+
+            // the module may change its interval whenever it's called
+            if is_tick || next_tick.is_none() {
+                next_tick = self
+                    .inner
+                    .interval()
+                    .map(|interval| Instant::now() + interval);
+            }
+
+            if let Err(e) = ctx.request_redraw() {
+                tracing::error!("Could not send update window action over channel: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+        sync::mpsc,
+    };
+
+    use winbar::WinbarAction;
+
+    use super::*;
+
+    /// Declares the imports and memory of a test module, followed by the given fields.
+    fn module(fields: &str) -> Vec<u8> {
+        wat::parse_str(format!(
+            r#"(module
+                (import "winbar" "text" (func $text (param i32 i32)))
+                (import "winbar" "space" (func $space (param i32)))
+                (import "winbar" "set_interval" (func $set_interval (param i32)))
+                (import "winbar" "read_file" (func $read_file (param i32 i32) (result i32)))
+                (import "winbar" "tooltip" (func $tooltip (param i32 i32)))
+                (memory (export "memory") 1)
+                {}
+            )"#,
+            fields
+        ))
+        .unwrap()
+    }
+
+    fn create(wasm: &[u8], config: serde_json::Value) -> Result<WasmComponent> {
+        WasmComponent::new(
+            wasm,
+            &config,
+            WasmCapabilities::default(),
+            None,
+            StyleOptions::default().into(),
+        )
+    }
+
+    fn commands(component: &WasmComponent) -> Vec<RenderCommand> {
+        component.with_rendered(|commands, _| commands.to_vec())
+    }
+
+    #[test]
+    fn module_renders_its_config() {
+        // the config is written at offset 1024 and rendered as is
+        let wasm = module(
+            r#"
+            (global $len (mut i32) (i32.const 0))
+            (data (i32.const 0) "config")
+            (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+            (func (export "init") (param i32 i32) (result i32)
+                (global.set $len (local.get 1))
+                (i32.const 0))
+            (func (export "render")
+                (call $text (i32.const 1024) (global.get $len))
+                (call $tooltip (i32.const 0) (i32.const 6)))
+            "#,
+        );
+        let component = create(&wasm, serde_json::json!({ "a": 1 })).unwrap();
+
+        assert_eq!(
+            commands(&component),
+            vec![RenderCommand::text(r#"{"a":1}"#)]
+        );
+        assert_eq!(component.tooltip().as_deref(), Some("config"));
+    }
+
+    #[test]
+    fn module_rejecting_config_fails() {
+        let wasm = module(
+            r#"
+            (func (export "alloc") (param i32) (result i32) (i32.const 1024))
+            (func (export "init") (param i32 i32) (result i32) (i32.const 1))
+            (func (export "render"))
+            "#,
+        );
+        assert!(create(&wasm, serde_json::json!({})).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn module_handles_clicks_on_its_task() {
+        let wasm = module(
+            r#"
+            (global $x (mut i32) (i32.const 0))
+            (func (export "on_click") (param i32 i32 i32) (global.set $x (local.get 1)))
+            (func (export "render") (call $space (global.get $x)))
+            "#,
+        );
+        let component = Arc::new(create(&wasm, serde_json::Value::Null).unwrap());
+        assert_eq!(commands(&component), vec![RenderCommand::Space(0)]);
+
+        // events don't wait for the module, e.g. while it's ticking
+        {
+            let _ticking = component.inner.instance.lock().unwrap();
+            component.on_hover(Hover::Enter);
+            component.on_click(MouseButton::Left, Point { x: 7, y: 2 });
+        }
+        assert_eq!(commands(&component), vec![RenderCommand::Space(0)]);
+
+        let (send, recv) = mpsc::channel();
+        let ctx = WinbarContext::new(send);
+        let handle = tokio::spawn({
+            let component = component.clone();
+            let ctx = ctx.clone();
+            async move { component.start(ctx, HWND(0), Rect::default()).await }
+        });
+        for _ in 0..2 {
+            assert!(matches!(
+                recv.recv_timeout(Duration::from_secs(5)),
+                Ok(WinbarAction::UpdateWindow)
+            ));
+        }
+        assert_eq!(commands(&component), vec![RenderCommand::Space(7)]);
+
+        ctx.cancellation_token().cancel();
+        handle.await.unwrap();
+    }
+
+    #[test]
+    fn module_is_denied_files_without_capability() {
+        let path = std::env::temp_dir().join("winbar-wasm-test.txt");
+        std::fs::write(&path, "secret").unwrap();
+        let path = path.to_string_lossy().into_owned();
+
+        let wasm = module(&format!(
+            r#"
+            (global $result (mut i32) (i32.const 0))
+            (data (i32.const 0) "{}")
+            (func (export "render") (call $space (global.get $result)))
+            (func $start
+                (global.set $result (call $read_file (i32.const 0) (i32.const {}))))
+            (start $start)
+            "#,
+            path.replace('\\', "\\\\"),
+            path.len()
+        ));
+
+        let component = create(&wasm, serde_json::Value::Null).unwrap();
+        assert_eq!(commands(&component), vec![RenderCommand::Space(DENIED)]);
+
+        let capabilities = WasmCapabilities {
+            files: vec![std::env::temp_dir()],
+            ..Default::default()
+        };
+        let component = WasmComponent::new(
+            &wasm,
+            &serde_json::Value::Null,
+            capabilities,
+            None,
+            StyleOptions::default().into(),
+        )
+        .unwrap();
+        assert_eq!(commands(&component), vec![RenderCommand::Space(6)]);
+    }
+
+    #[test]
+    fn module_running_forever_is_trapped() {
+        let wasm = module(r#"(func (export "render") (loop $forever (br $forever)))"#);
+        assert!(create(&wasm, serde_json::Value::Null).is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn module_ticks_at_its_interval() {
+        let wasm = module(
+            r#"
+            (global $ticks (mut i32) (i32.const 0))
+            (func (export "tick") (global.set $ticks (i32.add (global.get $ticks) (i32.const 1))))
+            (func (export "render") (call $space (global.get $ticks)))
+            (func $start (call $set_interval (i32.const 100)))
+            (start $start)
+            "#,
+        );
+        let component = Arc::new(create(&wasm, serde_json::Value::Null).unwrap());
+        let (send, recv) = mpsc::channel();
+        let ctx = WinbarContext::new(send);
+
+        let handle = tokio::spawn({
+            let component = component.clone();
+            let ctx = ctx.clone();
+            async move { component.start(ctx, HWND(0), Rect::default()).await }
+        });
+        assert!(matches!(
+            recv.recv_timeout(Duration::from_secs(5)),
+            Ok(WinbarAction::UpdateWindow)
+        ));
+        assert_ne!(commands(&component), vec![RenderCommand::Space(0)]);
+
+        ctx.cancellation_token().cancel();
+        handle.await.unwrap();
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn module_failing_to_tick_keeps_its_render_until_it_keeps_failing() {
+        let wasm = module(
+            r#"
+            (func (export "tick") (unreachable))
+            (func (export "render") (call $space (i32.const 7)))
+            (func $start (call $set_interval (i32.const 10)))
+            (start $start)
+            "#,
+        );
+        let component = create(&wasm, serde_json::Value::Null).unwrap();
+        let (send, recv) = mpsc::channel();
+        let ctx = WinbarContext::new(send);
+
+        // failed ticks don't clear what was rendered
+        tokio::time::timeout(
+            Duration::from_secs(5),
+            component.start(ctx.clone(), HWND(0), Rect::default()),
+        )
+        .await
+        .unwrap();
+        assert_eq!(commands(&component), vec![RenderCommand::Space(7)]);
+        assert_eq!(recv.try_iter().count(), 2);
+        assert!(ctx.take_failure().unwrap().contains("tick"));
+    }
+
+    #[test]
+    fn capabilities_allow_listed_resources() {
+        let capabilities = WasmCapabilities {
+            files: Vec::new(),
+            commands: vec!["git".to_string()],
+            http: vec!["api.example.com".to_string()],
+        };
+
+        assert!(capabilities.allows_command("git"));
+        assert!(!capabilities.allows_command("cmd"));
+        assert!(capabilities.allows_url("https://API.example.com/v1?q=1"));
+        assert!(!capabilities.allows_url("https://api.example.com.evil.net/"));
+        assert!(!capabilities.allows_url("https://api.example.com@evil.net/"));
+        assert!(!capabilities.allows_url("file://api.example.com/"));
+        assert!(capabilities.allows_url("https://user@api.example.com/"));
+        // a backslash ends the host, as it's a path separator in HTTP URLs
+        assert!(!capabilities.allows_url("http://evil.net\\@api.example.com/"));
+    }
+
+    /// Serves HTTP requests on a local port, redirecting `/redirect` to the location and answering
+    /// anything else with its path. Returns the port and the paths requested.
+    fn serve(location: impl Fn(u16) -> String + Send + 'static) -> (u16, Arc<Mutex<Vec<String>>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requested = Arc::new(Mutex::new(Vec::new()));
+
+        std::thread::spawn({
+            let requested = requested.clone();
+            move || {
+                for mut stream in listener.incoming().flatten() {
+                    let mut line = String::new();
+                    BufReader::new(&stream).read_line(&mut line).unwrap();
+                    let path = line.split(' ').nth(1).unwrap_or_default().to_string();
+                    requested.lock().unwrap().push(path.clone());
+
+                    let response = if path == "/redirect" {
+                        format!(
+                            "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\n\r\n",
+                            location(port)
+                        )
+                    } else {
+                        format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                            path.len(),
+                            path
+                        )
+                    };
+                    let _ = stream.write_all(response.as_bytes());
+                }
+            }
+        });
+
+        (port, requested)
+    }
+
+    #[test]
+    fn http_redirects_are_followed_to_allowed_hosts() {
+        let capabilities = WasmCapabilities {
+            http: vec!["127.0.0.1".to_string()],
+            ..Default::default()
+        };
+
+        let (port, _) = serve(|_| "/ok".to_string());
+        let url = format!("http://127.0.0.1:{}/redirect", port);
+        assert_eq!(http_get(&url, &capabilities).unwrap(), b"/ok");
+
+        // localhost is the same server, but not an allowed host
+        let (port, requested) = serve(|port| format!("http://localhost:{}/secret", port));
+        let url = format!("http://127.0.0.1:{}/redirect", port);
+        assert!(http_get(&url, &capabilities).is_err());
+        assert_eq!(*requested.lock().unwrap(), vec!["/redirect"]);
+    }
+
+    #[test]
+    fn url_host_works() {
+        assert_eq!(
+            url_host("http://example.com").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            url_host("https://user:pw@example.com:8080/a").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            url_host("http://user@example.com/").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(
+            url_host("http://evil.com\\@allowed.com/").as_deref(),
+            Some("evil.com")
+        );
+        assert_eq!(url_host("http://[::1]:80/").as_deref(), Some("[::1]"));
+        assert_eq!(url_host("example.com"), None);
+        assert_eq!(url_host("ftp://example.com"), None);
+        assert_eq!(url_host("http://"), None);
+    }
+}
@@ -0,0 +1,334 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use regex::Regex;
+use winbar::{
+    styles::{ComponentStyles, StyleOptions, Styles},
+    template::{Fields, Template, Value},
+    util::rect::Rect,
+    Component, WinbarContext,
+};
+use windows::Win32::{Foundation::HWND, Graphics::Gdi::HDC};
+
+use crate::foreground::{ForegroundSource, WindowInfo};
+
+pub const DEFAULT_FORMAT: &str = "{?icon}{icon} {/}{title}";
+
+/// A regex replaced in window titles. The replacement can refer to groups, e.g., `$1`.
+pub struct Rewrite {
+    pub pattern: Regex,
+    pub replacement: String,
+}
+
+/// Turns the foreground window into what is displayed.
+#[derive(Default)]
+pub struct TitleRules {
+    /// The rewrites of the title, applied in order.
+    pub rewrites: Vec<Rewrite>,
+    /// The icons of processes, by their normalized name. See [`TitleRules::process_key`].
+    pub icons: HashMap<String, String>,
+    /// The icon of processes without their own.
+    pub default_icon: Option<String>,
+}
+
+impl TitleRules {
+    /// The key processes are looked up by: their name in lower case, without the `.exe`
+    /// extension, so that `Code.exe` matches `code`.
+    pub fn process_key(process: &str) -> String {
+        let process = process.to_lowercase();
+        match process.strip_suffix(".exe") {
+            Some(name) => name.to_string(),
+            None => process,
+        }
+    }
+
+    pub fn apply(&self, window: WindowInfo) -> FocusedWindow {
+        let title = self
+            .rewrites
+            .iter()
+            .fold(window.title.clone(), |title, rewrite| {
+                rewrite
+                    .pattern
+                    .replace_all(&title, rewrite.replacement.as_str())
+                    .into_owned()
+            });
+        let icon = self
+            .icons
+            .get(&Self::process_key(&window.process))
+            .or(self.default_icon.as_ref())
+            .cloned();
+
+        FocusedWindow {
+            title,
+            icon,
+            window,
+        }
+    }
+}
+
+/// The foreground window as displayed.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FocusedWindow {
+    /// The rewritten title.
+    pub title: String,
+    pub icon: Option<String>,
+    /// The window as reported.
+    pub window: WindowInfo,
+}
+
+/// The fields available to the format.
+impl Fields for FocusedWindow {
+    fn field(&self, name: &str) -> Option<Value> {
+        match name {
+            "title" => Some(self.title.as_str().into()),
+            "icon" => Some(self.icon.as_deref()?.into()),
+            "process" => Some(self.window.process.as_str().into()),
+            "full_title" => Some(self.window.title.as_str().into()),
+            _ => None,
+        }
+    }
+}
+
+/// Displays the title of the foreground window, updated whenever the foreground window or its
+/// title changes.
+pub struct WindowTitleComponent {
+    source: Arc<dyn ForegroundSource>,
+    rules: TitleRules,
+    format: Template,
+    styles: ComponentStyles,
+    window: RwLock<Option<FocusedWindow>>,
+}
+
+impl WindowTitleComponent {
+    pub fn new(
+        source: Arc<dyn ForegroundSource>,
+        rules: TitleRules,
+        format: Template,
+        styles: ComponentStyles,
+    ) -> Self {
+        Self {
+            source,
+            rules,
+            format,
+            styles,
+            window: RwLock::new(None),
+        }
+    }
+
+    pub fn text(&self) -> String {
+        match self.window.read().unwrap().as_ref() {
+            Some(window) => self.format.render(window),
+            None => String::new(),
+        }
+    }
+
+    /// Sets the foreground window, returning whether the displayed text changed.
+    fn set_window(&self, window: WindowInfo) -> bool {
+        let previous = self.text();
+        *self.window.write().unwrap() = Some(self.rules.apply(window));
+        self.text() != previous
+    }
+}
+
+#[async_trait]
+impl Component for WindowTitleComponent {
+    fn styles(&self) -> Arc<StyleOptions> {
+        self.styles.resolve(&self.text())
+    }
+
+    fn set_state(&self, state: Option<String>) {
+        self.styles.set_state(state);
+    }
+
+    /// The full title and process, since the displayed title may be rewritten or truncated.
+    fn tooltip(&self) -> Option<String> {
+        let window = self.window.read().unwrap();
+        let window = &window.as_ref()?.window;
+        if window.process.is_empty() {
+            return Some(window.title.clone());
+        }
+
+        Some(format!("{}\n{}", window.title, window.process))
+    }
+
+    fn width(&self, _hwnd: HWND, hdc: HDC) -> i32 {
+        let text = self.text();
+        Styles::text_component_width(hdc, &text, &self.styles.resolve(&text))
+    }
+
+    fn draw(&self, _hwnd: HWND, rect: Rect, hdc: HDC) {
+        let text = self.text();
+        let styles = self.styles.resolve(&text);
+
+        Styles::draw_rect(hdc, &rect, &styles);
+        Styles::draw_text(hdc, &rect, &text, &styles);
+    }
+
+    async fn start(&self, ctx: WinbarContext, _hwnd: HWND, _rect: Rect) {
+        let mut windows = match self.source.watch() {
+            Ok(windows) => windows,
+            Err(e) => {
+                tracing::error!("Could not watch the foreground window: {:#}", e);
+                return;
+            }
+        };
+
+        while let Some(window) = windows.recv().await {
+            if !self.set_window(window) {
+                continue;
+            }
+            if let Err(e) = ctx.request_redraw() {
+                tracing::error!("Could not send update window action over channel: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{mpsc, Mutex},
+        time::Duration,
+    };
+
+    use anyhow::Result;
+    use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+    use winbar::WinbarAction;
+
+    use super::*;
+
+    fn window(title: &str, process: &str) -> WindowInfo {
+        WindowInfo {
+            title: title.to_string(),
+            process: process.to_string(),
+        }
+    }
+
+    fn rules() -> TitleRules {
+        let rewrite = |pattern: &str, replacement: &str| Rewrite {
+            pattern: Regex::new(pattern).unwrap(),
+            replacement: replacement.to_string(),
+        };
+
+        TitleRules {
+            rewrites: vec![
+                rewrite(" - Google Chrome$", ""),
+                rewrite(r"^(.*) - (.*) - Visual Studio Code$", "$2: $1"),
+            ],
+            icons: HashMap::from([
+                ("chrome".to_string(), "C".to_string()),
+                ("code".to_string(), "V".to_string()),
+            ]),
+            default_icon: None,
+        }
+    }
+
+    #[test]
+    fn rewrites_titles_and_maps_icons() {
+        let rules = rules();
+        let format = Template::parse(DEFAULT_FORMAT).unwrap();
+        let render =
+            |title: &str, process: &str| format.render(&rules.apply(window(title, process)));
+
+        assert_eq!(
+            render("Rust Programming Language - Google Chrome", "chrome.exe"),
+            "C Rust Programming Language"
+        );
+        assert_eq!(
+            render("main.rs - winbar - Visual Studio Code", "Code.exe"),
+            "V winbar: main.rs"
+        );
+        assert_eq!(
+            render("Untitled - Notepad", "notepad.exe"),
+            "Untitled - Notepad"
+        );
+
+        let rules = TitleRules {
+            default_icon: Some("*".to_string()),
+            ..rules
+        };
+        assert_eq!(
+            rules
+                .apply(window("Untitled - Notepad", "notepad.exe"))
+                .icon,
+            Some("*".to_string())
+        );
+    }
+
+    #[test]
+    fn format_truncates_title() {
+        let format = Template::parse("{title:.10}").unwrap();
+        let window = rules().apply(window("A very long document title", "winword.exe"));
+        assert_eq!(format.render(&window), "A very lon");
+    }
+
+    /// A source sending the windows sent by the test.
+    struct MockSource {
+        windows: Mutex<Option<UnboundedReceiver<WindowInfo>>>,
+    }
+
+    impl ForegroundSource for MockSource {
+        fn watch(&self) -> Result<UnboundedReceiver<WindowInfo>> {
+            Ok(self.windows.lock().unwrap().take().unwrap())
+        }
+    }
+
+    fn mock_source() -> (Arc<MockSource>, UnboundedSender<WindowInfo>) {
+        let (send, recv) = unbounded_channel();
+        let source = MockSource {
+            windows: Mutex::new(Some(recv)),
+        };
+        (Arc::new(source), send)
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn redraws_when_displayed_title_changes() {
+        let (source, windows) = mock_source();
+        let component = Arc::new(WindowTitleComponent::new(
+            source,
+            rules(),
+            Template::parse(DEFAULT_FORMAT).unwrap(),
+            StyleOptions::default().into(),
+        ));
+
+        let (send, recv) = mpsc::channel();
+        let ctx = WinbarContext::new(send);
+        let handle = tokio::spawn({
+            let component = component.clone();
+            async move { component.start(ctx, HWND(0), Rect::default()).await }
+        });
+
+        windows
+            .send(window("Inbox - Google Chrome", "chrome.exe"))
+            .unwrap();
+        assert!(matches!(
+            recv.recv_timeout(Duration::from_secs(5)),
+            Ok(WinbarAction::UpdateWindow)
+        ));
+        assert_eq!(component.text(), "C Inbox");
+        assert_eq!(
+            component.tooltip().as_deref(),
+            Some("Inbox - Google Chrome\nchrome.exe")
+        );
+
+        // the displayed title is unchanged
+        windows
+            .send(window("Inbox - Google Chrome", "chrome.exe"))
+            .unwrap();
+        assert!(recv.recv_timeout(Duration::from_millis(100)).is_err());
+
+        windows
+            .send(window("Untitled - Notepad", "notepad.exe"))
+            .unwrap();
+        assert!(matches!(
+            recv.recv_timeout(Duration::from_secs(5)),
+            Ok(WinbarAction::UpdateWindow)
+        ));
+        assert_eq!(component.text(), "Untitled - Notepad");
+
+        handle.abort();
+    }
+}
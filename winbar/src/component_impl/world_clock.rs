@@ -0,0 +1,333 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use winbar::{
+    input::{MouseButton, Point},
+    styles::{ComponentStyles, StyleOptions, Styles},
+    template::{Fields, Template, Value},
+    util::rect::Rect,
+    Component, WinbarContext,
+};
+use windows::Win32::{Foundation::HWND, Graphics::Gdi::HDC};
+
+use crate::scheduler::Ticker;
+
+use super::datetime::{Granularity, TimeFields};
+
+pub const DEFAULT_FORMAT: &str = "{label} {hour:0>2}:{minute:0>2}";
+pub const DEFAULT_SEPARATOR: &str = "  ";
+
+const TOOLTIP_FORMAT: &str =
+    "{label}: {weekday_short} {day} {month_short} {hour:0>2}:{minute:0>2} ({abbreviation})";
+
+/// A time zone displayed by a world clock.
+pub struct Zone {
+    pub label: String,
+    pub tz: Tz,
+}
+
+impl Zone {
+    /// The label of a zone without one: the last part of its name, e.g., `New York` for
+    /// `America/New_York`.
+    pub fn default_label(tz: Tz) -> String {
+        let name = tz.name();
+        name.rsplit('/').next().unwrap_or(name).replace('_', " ")
+    }
+}
+
+/// The fields of a zone available to the format: its label, name, offset and abbreviation, and the
+/// [`TimeFields`] of its local time.
+struct ZoneFields<'a> {
+    zone: &'a Zone,
+    time: DateTime<Tz>,
+}
+
+impl Fields for ZoneFields<'_> {
+    fn field(&self, name: &str) -> Option<Value> {
+        match name {
+            "label" => Some(self.zone.label.as_str().into()),
+            "zone" => Some(self.zone.tz.name().into()),
+            "offset" => Some(self.time.format("%:z").to_string().into()),
+            "abbreviation" => Some(self.time.format("%Z").to_string().into()),
+            _ => TimeFields(self.time).field(name),
+        }
+    }
+}
+
+/// Displays the time in several time zones, either side by side or one at a time, cycling through
+/// them on click, on scroll or at an interval.
+pub struct WorldClockComponent {
+    zones: Vec<Zone>,
+    format: Template,
+    /// The text between zones displayed side by side.
+    separator: String,
+    /// Whether a single zone is displayed at a time.
+    cycle: bool,
+    /// How often the displayed zone advances on its own, if it does.
+    cycle_interval: Option<Duration>,
+    /// How often the time is updated, aligned to the wall clock.
+    interval: Duration,
+    styles: ComponentStyles,
+    /// The index of the displayed zone when cycling.
+    current: AtomicUsize,
+}
+
+impl WorldClockComponent {
+    /// Creates the component, updating the time at the interval if given, or else whenever the
+    /// finest unit of time displayed by the format changes.
+    pub fn new(
+        zones: Vec<Zone>,
+        format: Template,
+        separator: String,
+        cycle: bool,
+        cycle_interval: Option<Duration>,
+        interval: Option<Duration>,
+        styles: ComponentStyles,
+    ) -> Self {
+        let interval = interval.unwrap_or(Granularity::of_template(&format).period());
+
+        Self {
+            zones,
+            format,
+            separator,
+            cycle,
+            cycle_interval,
+            interval,
+            styles,
+            current: AtomicUsize::new(0),
+        }
+    }
+
+    fn fields(&self, now: DateTime<Utc>) -> impl Iterator<Item = ZoneFields<'_>> {
+        self.zones.iter().map(move |zone| ZoneFields {
+            zone,
+            time: now.with_timezone(&zone.tz),
+        })
+    }
+
+    /// The displayed zone when cycling.
+    fn current(&self) -> Option<&Zone> {
+        if self.zones.is_empty() {
+            return None;
+        }
+
+        self.zones
+            .get(self.current.load(Ordering::SeqCst) % self.zones.len())
+    }
+
+    /// Moves the displayed zone by the given number of zones, wrapping around.
+    fn advance(&self, by: isize) {
+        let len = self.zones.len().max(1) as isize;
+        let _ = self
+            .current
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |current| {
+                Some((current as isize + by).rem_euclid(len) as usize)
+            });
+    }
+
+    fn text_at(&self, now: DateTime<Utc>) -> String {
+        if self.cycle {
+            return match self.current() {
+                Some(zone) => self.format.render(&ZoneFields {
+                    zone,
+                    time: now.with_timezone(&zone.tz),
+                }),
+                None => String::new(),
+            };
+        }
+
+        self.fields(now)
+            .map(|fields| self.format.render(&fields))
+            .collect::<Vec<String>>()
+            .join(&self.separator)
+    }
+
+    pub fn text(&self) -> String {
+        self.text_at(Utc::now())
+    }
+
+    /// The date and time of every zone, including those not displayed while cycling.
+    fn tooltip_at(&self, now: DateTime<Utc>) -> Option<String> {
+        let template = Template::parse(TOOLTIP_FORMAT).ok()?;
+        let lines = self
+            .fields(now)
+            .map(|fields| template.render(&fields))
+            .collect::<Vec<String>>();
+
+        (!lines.is_empty()).then(|| lines.join("\n"))
+    }
+}
+
+#[async_trait]
+impl Component for WorldClockComponent {
+    fn styles(&self) -> Arc<StyleOptions> {
+        self.styles.resolve(&self.text())
+    }
+
+    fn set_state(&self, state: Option<String>) {
+        self.styles.set_state(state);
+    }
+
+    fn tooltip(&self) -> Option<String> {
+        self.tooltip_at(Utc::now())
+    }
+
+    fn width(&self, _hwnd: HWND, hdc: HDC) -> i32 {
+        let text = self.text();
+        Styles::text_component_width(hdc, &text, &self.styles.resolve(&text))
+    }
+
+    fn draw(&self, _hwnd: HWND, rect: Rect, hdc: HDC) {
+        let text = self.text();
+        let styles = self.styles.resolve(&text);
+
+        Styles::draw_rect(hdc, &rect, &styles);
+        Styles::draw_text(hdc, &rect, &text, &styles);
+    }
+
+    /// Shows the next zone when cycling.
+    fn on_click(&self, button: MouseButton, _point: Point) {
+        if self.cycle && button == MouseButton::Left {
+            self.advance(1);
+        }
+    }
+
+    /// Shows the previous zone on scroll up and the next one on scroll down when cycling.
+    fn on_scroll(&self, delta: i32) {
+        if self.cycle && delta != 0 {
+            self.advance(if delta > 0 { -1 } else { 1 });
+        }
+    }
+
+    async fn start(&self, ctx: WinbarContext, _hwnd: HWND, _rect: Rect) {
+        let mut ticker = Ticker::aligned(self.interval);
+        let mut cycle_ticker = self.cycle_interval.filter(|_| self.cycle).map(Ticker::new);
+        let mut last = None;
+        loop {
+            // first ticks complete immediately
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = async {
+                    match &mut cycle_ticker {
+                        Some(cycle_ticker) => cycle_ticker.tick().await,
+                        None => std::future::pending().await,
+                    }
+                } => {
+                    if last.is_some() {
+                        self.advance(1);
+                    }
+                }
+            }
+
+            // only redraw when what's displayed changed
+            let text = Some(self.text());
+            if text == last {
+                continue;
+            }
+            last = text;
+
+            if let Err(e) = ctx.request_redraw() {
+                tracing::error!("Could not send update window action over channel: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn zones() -> Vec<Zone> {
+        let zone = |label: &str, tz: Tz| Zone {
+            label: label.to_string(),
+            tz,
+        };
+
+        vec![
+            zone("NYC", chrono_tz::America::New_York),
+            zone("LON", chrono_tz::Europe::London),
+            zone("BLR", chrono_tz::Asia::Kolkata),
+            zone("SYD", chrono_tz::Australia::Sydney),
+        ]
+    }
+
+    fn component(cycle: bool) -> WorldClockComponent {
+        WorldClockComponent::new(
+            zones(),
+            Template::parse(DEFAULT_FORMAT).unwrap(),
+            DEFAULT_SEPARATOR.to_string(),
+            cycle,
+            None,
+            None,
+            StyleOptions::default().into(),
+        )
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2026, 10, 18, 13, 5, 0).unwrap()
+    }
+
+    #[test]
+    fn zones_are_displayed_side_by_side() {
+        assert_eq!(
+            component(false).text_at(now()),
+            "NYC 09:05  LON 14:05  BLR 18:35  SYD 00:05"
+        );
+    }
+
+    #[test]
+    fn cycling_wraps_around() {
+        let component = component(true);
+        assert_eq!(component.text_at(now()), "NYC 09:05");
+
+        component.on_click(MouseButton::Left, Point::default());
+        assert_eq!(component.text_at(now()), "LON 14:05");
+        component.on_click(MouseButton::Right, Point::default());
+        assert_eq!(component.text_at(now()), "LON 14:05");
+
+        component.on_scroll(120);
+        component.on_scroll(120);
+        assert_eq!(component.text_at(now()), "SYD 00:05");
+        component.on_scroll(-120);
+        assert_eq!(component.text_at(now()), "NYC 09:05");
+    }
+
+    #[test]
+    fn tooltip_lists_all_zones() {
+        assert_eq!(
+            component(true).tooltip_at(now()).as_deref(),
+            Some(
+                "NYC: Sun 18 Oct 09:05 (EDT)\n\
+                 LON: Sun 18 Oct 14:05 (BST)\n\
+                 BLR: Sun 18 Oct 18:35 (IST)\n\
+                 SYD: Mon 19 Oct 00:05 (AEDT)"
+            )
+        );
+    }
+
+    #[test]
+    fn fields_include_zone_and_offset() {
+        let format = Template::parse("{zone} {offset}").unwrap();
+        let zone = Zone {
+            label: Zone::default_label(chrono_tz::America::New_York),
+            tz: chrono_tz::America::New_York,
+        };
+        let fields = ZoneFields {
+            zone: &zone,
+            time: now().with_timezone(&zone.tz),
+        };
+
+        assert_eq!(zone.label, "New York");
+        assert_eq!(format.render(&fields), "America/New_York -04:00");
+    }
+}
@@ -42,6 +42,14 @@ impl ColorConfig {
             _ => Some(self.into()),
         }
     }
+
+    /// Returns this color, or `other` if this color is `Default`.
+    pub fn or(self, other: ColorConfig) -> ColorConfig {
+        match self {
+            ColorConfig::Default => other,
+            _ => self,
+        }
+    }
 }
 
 impl From<ColorConfig> for Color {
@@ -1,27 +1,47 @@
 use std::{
+    collections::HashMap,
     fs,
     path::PathBuf,
     sync::{atomic::Ordering, Arc},
+    time::Duration,
 };
 
-use anyhow::{anyhow, Context, Result};
+use anyhow::{anyhow, bail, Context, Result};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
-use winbar::{
-    styles::{BorderStyle, StyleOptions},
-    Component,
-};
+use winbar::{styles::ComponentStyles, template::Template, util::gdi_cache, Component};
 
 use crate::{
     component_impl::{
-        datetime::DateTimeComponent, manager::ComponentLocation, static_text::StaticTextComponent,
+        battery::{self, BatteryComponent},
+        cpu::{self, CpuComponent},
+        datetime::DateTimeComponent,
+        disk::{self, DiskComponent, Drive, Drives},
+        komorebi::{self, KomorebiComponent},
+        manager::ComponentLocation,
+        memory::{self, MemoryComponent},
+        metric::Thresholds,
+        mouse::ComponentActions,
+        network::{self, NetworkComponent},
+        plugin::PluginComponent,
+        script::{self, ScriptComponent},
+        static_text::StaticTextComponent,
+        wasm::{WasmCapabilities, WasmComponent},
+        window_title::{self, Rewrite, TitleRules, WindowTitleComponent},
+        world_clock::{self, WorldClockComponent, Zone},
     },
-    COMPONENT_GAP, DEFAULT_BG_COLOR, DEFAULT_FG_COLOR, DEFAULT_FONT, DEFAULT_FONT_SIZE, HEIGHT,
-    POSITION_X, POSITION_Y, STATUS_BAR_BG_COLOR, WIDTH,
+    foreground, metrics, COMPONENT_GAP, DEFAULT_BG_COLOR, DEFAULT_FG_COLOR, DEFAULT_FONT,
+    DEFAULT_FONT_SIZE, HEIGHT, POPUP_FONT, POSITION_X, POSITION_Y, STATUS_BAR_BG_COLOR,
+    TOOLTIP_DELAY, WIDTH,
 };
 
-use self::color::ColorConfig;
+use self::{
+    color::ColorConfig,
+    style::{StyleConfig, StyleRuleConfig},
+};
 
 mod color;
+mod style;
 
 fn default_component_gap() -> i32 {
     10
@@ -31,6 +51,14 @@ fn default_font_size() -> i32 {
     18
 }
 
+fn default_tooltip_delay() -> u32 {
+    500
+}
+
+fn default_popup_font() -> String {
+    "Consolas".to_string()
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct Config {
     /// The width of the window
@@ -60,6 +88,15 @@ pub struct Config {
     /// The default font size of components
     #[serde(default = "default_font_size")]
     pub default_font_size: i32,
+    /// The time, in milliseconds, the mouse has to hover over a component before its tooltip shows
+    #[serde(default = "default_tooltip_delay")]
+    pub tooltip_delay: u32,
+    /// The font of popup panels, which should be monospace
+    #[serde(default = "default_popup_font")]
+    pub popup_font: String,
+    /// Named style classes that components can reference
+    #[serde(default)]
+    pub styles: HashMap<String, StyleConfig>,
     /// All components that should be displayed in the status bar
     pub components: Vec<ComponentConfig>,
 }
@@ -81,6 +118,7 @@ impl Config {
         POSITION_Y.store(self.position_y, Ordering::SeqCst);
         COMPONENT_GAP.store(self.component_gap, Ordering::SeqCst);
         DEFAULT_FONT_SIZE.store(self.default_font_size, Ordering::SeqCst);
+        TOOLTIP_DELAY.store(self.tooltip_delay, Ordering::SeqCst);
         {
             let mut status_bar_bg_color = STATUS_BAR_BG_COLOR
                 .lock()
@@ -105,6 +143,14 @@ impl Config {
                 .map_err(|e| anyhow!("Could not obtain default foreground color lock: {}", e))?;
             *font = self.default_font.to_string();
         }
+        {
+            let mut font = POPUP_FONT
+                .lock()
+                .map_err(|e| anyhow!("Could not obtain popup font lock: {}", e))?;
+            *font = self.popup_font.to_string();
+        }
+        // objects created with the previous defaults are no longer used
+        gdi_cache::invalidate();
 
         Ok(())
     }
@@ -131,23 +177,39 @@ impl Default for Config {
             },
             default_font: "Segoe IO Variable".to_string(),
             default_font_size: 18,
+            tooltip_delay: 500,
+            popup_font: default_popup_font(),
+            styles: HashMap::new(),
             components: vec![
                 ComponentConfig {
+                    id: None,
                     location: ComponentLocation::LEFT,
+                    class: Vec::new(),
+                    rules: Vec::new(),
+                    actions: ComponentActions::default(),
+                    tooltip: None,
+                    interval: None,
                     component: ComponentData::StaticText {
                         text: "Winbar!".to_string(),
                         styles: StyleConfig {
-                            padding_x: 10,
+                            padding_x: Some(10),
                             ..Default::default()
                         },
                     },
                 },
                 ComponentConfig {
+                    id: None,
                     location: ComponentLocation::LEFT,
+                    class: Vec::new(),
+                    rules: Vec::new(),
+                    actions: ComponentActions::default(),
+                    tooltip: None,
+                    interval: None,
                     component: ComponentData::DateTime {
                         format: "%F %r".to_string(),
+                        alt_format: None,
                         styles: StyleConfig {
-                            padding_x: 10,
+                            padding_x: Some(10),
                             ..Default::default()
                         },
                     },
@@ -157,74 +219,464 @@ impl Default for Config {
     }
 }
 
-#[derive(Clone, Default, Serialize, Deserialize)]
-pub enum BorderStyleConfig {
-    #[default]
-    Square,
-    Rounded {
-        radius: i32,
+#[derive(Serialize, Deserialize)]
+pub struct ComponentConfig {
+    /// An optional id used to refer to the component (e.g., through the protocol)
+    #[serde(default)]
+    pub id: Option<String>,
+    pub location: ComponentLocation,
+    /// The names of the style classes applied to the component, in increasing order of precedence
+    #[serde(default)]
+    pub class: Vec<String>,
+    /// Conditional styles applied on top of the component's styles, where the first matching rule
+    /// is used
+    #[serde(default)]
+    pub rules: Vec<StyleRuleConfig>,
+    /// The actions bound to mouse events over the component
+    #[serde(default)]
+    pub actions: ComponentActions,
+    /// A static tooltip, used instead of the component's own tooltip
+    #[serde(default)]
+    pub tooltip: Option<String>,
+    /// How often the component updates, in milliseconds, for components that update periodically
+    #[serde(default)]
+    pub interval: Option<u64>,
+    pub component: ComponentData,
+}
+
+impl ComponentConfig {
+    /// Creates the component, resolving its styles using the given style classes.
+    pub fn to_component(
+        &self,
+        classes: &HashMap<String, StyleConfig>,
+    ) -> Result<Arc<dyn Component + Sync + Send>> {
+        let styles = StyleConfig::resolve(classes, &self.class, self.component.styles())?;
+        let rules = self
+            .rules
+            .iter()
+            .map(|rule| rule.to_rule(classes, &styles))
+            .collect::<Result<Vec<_>>>()?;
+
+        self.component.to_component(
+            ComponentStyles::new(styles.into(), rules),
+            self.interval.map(Duration::from_millis),
+        )
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum ComponentData {
+    StaticText {
+        text: String,
+        #[serde(default)]
+        styles: StyleConfig,
+    },
+    DateTime {
+        format: String,
+        /// The format switched to by the `ToggleFormat` action
+        #[serde(default)]
+        alt_format: Option<String>,
+        #[serde(default)]
+        styles: StyleConfig,
+    },
+    Plugin {
+        /// The path to the plugin's dynamic library
+        path: PathBuf,
+        /// The options passed to the plugin, as is
+        #[serde(default)]
+        options: serde_json::Value,
+        #[serde(default)]
+        styles: StyleConfig,
+    },
+    Wasm {
+        /// The path to the WebAssembly module
+        module: PathBuf,
+        /// The config passed to the module's `init` function, as is
+        #[serde(default)]
+        config: serde_json::Value,
+        /// The resources the module is allowed to access
+        #[serde(default)]
+        capabilities: WasmCapabilities,
+        #[serde(default)]
+        styles: StyleConfig,
+    },
+    Script {
+        /// The source of the script, for scripts written inline
+        #[serde(default)]
+        source: Option<String>,
+        /// The path to the script, for scripts in their own file
+        #[serde(default)]
+        path: Option<PathBuf>,
+        /// The template the fields returned by the script are displayed with
+        #[serde(default)]
+        format: Option<String>,
+        #[serde(default)]
+        styles: StyleConfig,
+    },
+    Cpu {
+        /// The template the CPU usage is displayed with
+        #[serde(default)]
+        format: Option<String>,
+        /// The total usage at which the component is in the warning or critical state
+        #[serde(default)]
+        thresholds: Thresholds,
+        #[serde(default)]
+        styles: StyleConfig,
+    },
+    Memory {
+        /// The template the memory usage is displayed with
+        #[serde(default)]
+        format: Option<String>,
+        /// The memory usage at which the component is in the warning or critical state
+        #[serde(default)]
+        thresholds: Thresholds,
+        #[serde(default)]
+        styles: StyleConfig,
+    },
+    Disk {
+        /// The drives or paths whose file system usage is displayed
+        drives: Vec<DriveConfig>,
+        /// The template each drive is displayed with, unless the drive has its own
+        #[serde(default)]
+        format: Option<String>,
+        /// The text between drives
+        #[serde(default)]
+        separator: Option<String>,
+        /// Whether drives that can't be read are hidden
+        #[serde(default)]
+        hide_missing: bool,
+        /// The usage of the fullest drive at which the component is in the warning or critical
+        /// state
+        #[serde(default)]
+        thresholds: Thresholds,
+        #[serde(default)]
+        styles: StyleConfig,
+    },
+    Network {
+        /// The name of the interface, defaulting to the interface of the default route
+        #[serde(default)]
+        interface: Option<String>,
+        /// The template the interface's traffic and status are displayed with
+        #[serde(default)]
+        format: Option<String>,
+        /// The combined rate, in bytes per second, at which the component is in the warning or
+        /// critical state
+        #[serde(default)]
+        thresholds: Thresholds,
+        #[serde(default)]
+        styles: StyleConfig,
+    },
+    Battery {
+        /// The template the battery's charge and state are displayed with
+        #[serde(default)]
+        format: Option<String>,
+        /// The charge at and below which the discharging battery is in the warning or critical
+        /// state
+        #[serde(default)]
+        thresholds: Thresholds,
+        #[serde(default)]
+        styles: StyleConfig,
+    },
+    Komorebi {
+        /// The index of the monitor whose workspaces are displayed
+        #[serde(default)]
+        monitor: usize,
+        /// The name of the pipe komorebi sends notifications to
+        #[serde(default)]
+        pipe: Option<String>,
+        #[serde(default)]
+        styles: StyleConfig,
+    },
+    WindowTitle {
+        /// The template the foreground window is displayed with
+        #[serde(default)]
+        format: Option<String>,
+        /// The rewrites of the title, applied in order
+        #[serde(default)]
+        rewrites: Vec<RewriteConfig>,
+        /// The icons of processes, by the name of their executable
+        #[serde(default)]
+        icons: HashMap<String, String>,
+        /// The icon of processes without their own
+        #[serde(default)]
+        default_icon: Option<String>,
+        #[serde(default)]
+        styles: StyleConfig,
+    },
+    WorldClock {
+        /// The time zones displayed, in order
+        zones: Vec<ZoneConfig>,
+        /// The template each zone is displayed with
+        #[serde(default)]
+        format: Option<String>,
+        /// The text between zones displayed side by side
+        #[serde(default)]
+        separator: Option<String>,
+        /// Whether a single zone is displayed at a time, cycling through them on click or scroll
+        #[serde(default)]
+        cycle: bool,
+        /// How often, in milliseconds, the displayed zone advances on its own when cycling
+        #[serde(default)]
+        cycle_interval: Option<u64>,
+        #[serde(default)]
+        styles: StyleConfig,
     },
 }
 
-impl From<BorderStyleConfig> for BorderStyle {
-    fn from(value: BorderStyleConfig) -> Self {
-        match value {
-            BorderStyleConfig::Square => BorderStyle::Square,
-            BorderStyleConfig::Rounded { radius } => BorderStyle::Rounded { radius },
+impl ComponentData {
+    /// The inline styles of the component.
+    pub fn styles(&self) -> &StyleConfig {
+        match self {
+            Self::StaticText { styles, .. } => styles,
+            Self::DateTime { styles, .. } => styles,
+            Self::Plugin { styles, .. } => styles,
+            Self::Wasm { styles, .. } => styles,
+            Self::Script { styles, .. } => styles,
+            Self::Cpu { styles, .. } => styles,
+            Self::Memory { styles, .. } => styles,
+            Self::Disk { styles, .. } => styles,
+            Self::Network { styles, .. } => styles,
+            Self::Battery { styles, .. } => styles,
+            Self::Komorebi { styles, .. } => styles,
+            Self::WindowTitle { styles, .. } => styles,
+            Self::WorldClock { styles, .. } => styles,
         }
     }
+
+    /// Creates the component, updating at the given interval instead of its default one if it
+    /// updates periodically.
+    pub fn to_component(
+        &self,
+        styles: ComponentStyles,
+        interval: Option<Duration>,
+    ) -> Result<Arc<dyn Component + Sync + Send>> {
+        Ok(match self {
+            Self::StaticText { text, .. } => {
+                Arc::new(StaticTextComponent::new(text.to_string(), styles))
+            }
+            Self::DateTime {
+                format, alt_format, ..
+            } => Arc::new(DateTimeComponent::new(
+                format.to_string(),
+                alt_format.clone(),
+                interval,
+                styles,
+            )?),
+            Self::Plugin { path, options, .. } => {
+                Arc::new(PluginComponent::load(path, options, styles)?)
+            }
+            Self::Wasm {
+                module,
+                config,
+                capabilities,
+                ..
+            } => Arc::new(WasmComponent::load(
+                module,
+                config,
+                capabilities.clone(),
+                interval,
+                styles,
+            )?),
+            Self::Script {
+                source,
+                path,
+                format,
+                ..
+            } => {
+                let format = format.as_deref().map(Template::parse).transpose()?;
+                let interval = interval.unwrap_or(script::DEFAULT_INTERVAL);
+                Arc::new(match (source, path) {
+                    (Some(source), None) => ScriptComponent::new(source, format, interval, styles)?,
+                    (None, Some(path)) => ScriptComponent::load(path, format, interval, styles)?,
+                    _ => bail!("Script components need either a source or a path"),
+                })
+            }
+            Self::Cpu {
+                format, thresholds, ..
+            } => Arc::new(CpuComponent::new(
+                cpu::sampler(metrics::cpu()?),
+                Template::parse(format.as_deref().unwrap_or(cpu::DEFAULT_FORMAT))?,
+                interval.unwrap_or(cpu::DEFAULT_INTERVAL),
+                thresholds.clone(),
+                styles,
+            )),
+            Self::Memory {
+                format, thresholds, ..
+            } => Arc::new(MemoryComponent::new(
+                memory::sampler(metrics::system()),
+                Template::parse(format.as_deref().unwrap_or(memory::DEFAULT_FORMAT))?,
+                interval.unwrap_or(memory::DEFAULT_INTERVAL),
+                thresholds.clone(),
+                styles,
+            )),
+            Self::Disk {
+                drives,
+                format,
+                separator,
+                hide_missing,
+                thresholds,
+                ..
+            } => {
+                let drives = Drives {
+                    drives: drives
+                        .iter()
+                        .map(DriveConfig::to_drive)
+                        .collect::<Result<Vec<Drive>>>()?,
+                    separator: separator
+                        .clone()
+                        .unwrap_or_else(|| disk::DEFAULT_SEPARATOR.to_string()),
+                    hide_missing: *hide_missing,
+                };
+                Arc::new(DiskComponent::new(
+                    disk::sampler(metrics::system(), Arc::new(drives)),
+                    Template::parse(format.as_deref().unwrap_or(disk::DEFAULT_FORMAT))?,
+                    interval.unwrap_or(disk::DEFAULT_INTERVAL),
+                    thresholds.clone(),
+                    styles,
+                ))
+            }
+            Self::Network {
+                interface,
+                format,
+                thresholds,
+                ..
+            } => Arc::new(NetworkComponent::new(
+                network::sampler(metrics::system(), interface.clone()),
+                Template::parse(format.as_deref().unwrap_or(network::DEFAULT_FORMAT))?,
+                interval.unwrap_or(network::DEFAULT_INTERVAL),
+                thresholds.clone(),
+                styles,
+            )),
+            Self::Battery {
+                format, thresholds, ..
+            } => Arc::new(BatteryComponent::new(
+                battery::sampler(metrics::system()),
+                Template::parse(format.as_deref().unwrap_or(battery::DEFAULT_FORMAT))?,
+                interval.unwrap_or(battery::DEFAULT_INTERVAL),
+                thresholds.clone(),
+                styles,
+            )),
+            Self::Komorebi { monitor, pipe, .. } => Arc::new(KomorebiComponent::new(
+                pipe.clone()
+                    .unwrap_or_else(|| komorebi::DEFAULT_PIPE.to_string()),
+                *monitor,
+                styles,
+            )),
+            Self::WindowTitle {
+                format,
+                rewrites,
+                icons,
+                default_icon,
+                ..
+            } => {
+                let rules = TitleRules {
+                    rewrites: rewrites
+                        .iter()
+                        .map(RewriteConfig::to_rewrite)
+                        .collect::<Result<Vec<Rewrite>>>()?,
+                    icons: icons
+                        .iter()
+                        .map(|(process, icon)| (TitleRules::process_key(process), icon.clone()))
+                        .collect(),
+                    default_icon: default_icon.clone(),
+                };
+                Arc::new(WindowTitleComponent::new(
+                    foreground::system()?,
+                    rules,
+                    Template::parse(format.as_deref().unwrap_or(window_title::DEFAULT_FORMAT))?,
+                    styles,
+                ))
+            }
+            Self::WorldClock {
+                zones,
+                format,
+                separator,
+                cycle,
+                cycle_interval,
+                ..
+            } => Arc::new(WorldClockComponent::new(
+                zones
+                    .iter()
+                    .map(ZoneConfig::to_zone)
+                    .collect::<Result<Vec<Zone>>>()?,
+                Template::parse(format.as_deref().unwrap_or(world_clock::DEFAULT_FORMAT))?,
+                separator
+                    .clone()
+                    .unwrap_or_else(|| world_clock::DEFAULT_SEPARATOR.to_string()),
+                *cycle,
+                cycle_interval.map(Duration::from_millis),
+                interval,
+                styles,
+            )),
+        })
+    }
 }
 
-#[derive(Clone, Default, Serialize, Deserialize)]
-pub struct StyleConfig {
-    #[serde(deserialize_with = "color::parse_string_or_color_config", default)]
-    pub bg_color: ColorConfig,
-    #[serde(deserialize_with = "color::parse_string_or_color_config", default)]
-    pub fg_color: ColorConfig,
+#[derive(Serialize, Deserialize)]
+pub struct DriveConfig {
+    /// The drive (e.g., `C:\\`) or a path on the file system to display
+    pub path: PathBuf,
+    /// The label of the drive, defaulting to its path
     #[serde(default)]
-    pub border_style: BorderStyleConfig,
-    pub font: Option<String>,
-    pub font_size: Option<i32>,
+    pub label: Option<String>,
+    /// The template the drive is displayed with, instead of the component's format
     #[serde(default)]
-    pub padding_x: i32,
+    pub format: Option<String>,
 }
 
-impl From<StyleConfig> for StyleOptions {
-    fn from(value: StyleConfig) -> Self {
-        Self {
-            bg_color: value.bg_color.into_color_option(),
-            fg_color: value.fg_color.into_color_option(),
-            border_style: value.border_style.into(),
-            font: value.font,
-            font_size: value.font_size,
-            padding_x: value.padding_x,
-        }
+impl DriveConfig {
+    pub fn to_drive(&self) -> Result<Drive> {
+        Ok(Drive {
+            path: self.path.clone(),
+            label: self
+                .label
+                .clone()
+                .unwrap_or_else(|| self.path.display().to_string()),
+            format: self.format.as_deref().map(Template::parse).transpose()?,
+        })
     }
 }
 
 #[derive(Serialize, Deserialize)]
-pub struct ComponentConfig {
-    pub location: ComponentLocation,
-    pub component: ComponentData,
+pub struct RewriteConfig {
+    /// The regex replaced in the title
+    pub pattern: String,
+    /// The replacement, which can refer to groups of the regex (e.g., `$1`)
+    #[serde(default)]
+    pub replacement: String,
+}
+
+impl RewriteConfig {
+    pub fn to_rewrite(&self) -> Result<Rewrite> {
+        Ok(Rewrite {
+            pattern: Regex::new(&self.pattern)
+                .with_context(|| format!("Invalid rewrite regex: {}", self.pattern))?,
+            replacement: self.replacement.clone(),
+        })
+    }
 }
 
 #[derive(Serialize, Deserialize)]
-pub enum ComponentData {
-    StaticText { text: String, styles: StyleConfig },
-    DateTime { format: String, styles: StyleConfig },
+pub struct ZoneConfig {
+    /// The IANA name of the time zone, e.g., `America/New_York`
+    pub zone: String,
+    /// The label of the zone, defaulting to the last part of its name (e.g., `New York`)
+    #[serde(default)]
+    pub label: Option<String>,
 }
 
-impl ComponentData {
-    pub fn to_component(&self) -> Arc<dyn Component + Sync + Send> {
-        match self {
-            Self::StaticText { text, styles } => Arc::new(StaticTextComponent::new(
-                text.to_string(),
-                styles.clone().into(),
-            )),
-            Self::DateTime { format, styles } => Arc::new(DateTimeComponent::new(
-                format.to_string(),
-                styles.clone().into(),
-            )),
-        }
+impl ZoneConfig {
+    pub fn to_zone(&self) -> Result<Zone> {
+        let tz = self
+            .zone
+            .parse()
+            .map_err(|e| anyhow!("Invalid time zone {}: {}", self.zone, e))?;
+        Ok(Zone {
+            label: self
+                .label
+                .clone()
+                .unwrap_or_else(|| Zone::default_label(tz)),
+            tz,
+        })
     }
 }
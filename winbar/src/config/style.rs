@@ -0,0 +1,349 @@
+use std::{collections::HashMap, sync::Arc};
+
+use anyhow::{anyhow, Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use winbar::{
+    styles::{
+        BorderSides, BorderStyle, CornerRadii, StyleCondition, StyleOptions, StyleRule, TextAlign,
+    },
+    util::truncate::Truncation,
+};
+
+use super::color::{self, ColorConfig};
+
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub enum BorderStyleConfig {
+    #[default]
+    Square,
+    Rounded {
+        radius: i32,
+    },
+    Corners {
+        #[serde(default)]
+        top_left: i32,
+        #[serde(default)]
+        top_right: i32,
+        #[serde(default)]
+        bottom_right: i32,
+        #[serde(default)]
+        bottom_left: i32,
+    },
+}
+
+impl From<BorderStyleConfig> for BorderStyle {
+    fn from(value: BorderStyleConfig) -> Self {
+        match value {
+            BorderStyleConfig::Square => BorderStyle::Square,
+            BorderStyleConfig::Rounded { radius } => BorderStyle::Rounded { radius },
+            BorderStyleConfig::Corners {
+                top_left,
+                top_right,
+                bottom_right,
+                bottom_left,
+            } => BorderStyle::Corners(CornerRadii {
+                top_left,
+                top_right,
+                bottom_right,
+                bottom_left,
+            }),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum BorderSideConfig {
+    Top,
+    Right,
+    Bottom,
+    Left,
+}
+
+/// Converts a list of sides into `BorderSides`. No list means that the border is drawn on all
+/// sides.
+fn border_sides(sides: Option<Vec<BorderSideConfig>>) -> BorderSides {
+    match sides {
+        Some(sides) => BorderSides {
+            top: sides.contains(&BorderSideConfig::Top),
+            right: sides.contains(&BorderSideConfig::Right),
+            bottom: sides.contains(&BorderSideConfig::Bottom),
+            left: sides.contains(&BorderSideConfig::Left),
+        },
+        None => BorderSides::all(),
+    }
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub enum TextAlignConfig {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+impl From<TextAlignConfig> for TextAlign {
+    fn from(value: TextAlignConfig) -> Self {
+        match value {
+            TextAlignConfig::Left => TextAlign::Left,
+            TextAlignConfig::Center => TextAlign::Center,
+            TextAlignConfig::Right => TextAlign::Right,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub enum TruncationConfig {
+    Start,
+    Middle,
+    #[default]
+    End,
+}
+
+impl From<TruncationConfig> for Truncation {
+    fn from(value: TruncationConfig) -> Self {
+        match value {
+            TruncationConfig::Start => Truncation::Start,
+            TruncationConfig::Middle => Truncation::Middle,
+            TruncationConfig::End => Truncation::End,
+        }
+    }
+}
+
+/// The styles of a component as specified in the config. Every field is optional such that styles
+/// can be layered on top of each other (see `StyleConfig::merge`), with unset fields falling back to
+/// their defaults when converted into `StyleOptions`.
+#[derive(Clone, Default, Serialize, Deserialize)]
+pub struct StyleConfig {
+    #[serde(deserialize_with = "color::parse_string_or_color_config", default)]
+    pub bg_color: ColorConfig,
+    #[serde(deserialize_with = "color::parse_string_or_color_config", default)]
+    pub fg_color: ColorConfig,
+    pub border_style: Option<BorderStyleConfig>,
+    pub border_width: Option<i32>,
+    #[serde(deserialize_with = "color::parse_string_or_color_config", default)]
+    pub border_color: ColorConfig,
+    pub border_sides: Option<Vec<BorderSideConfig>>,
+    pub font: Option<String>,
+    pub font_size: Option<i32>,
+    pub font_weight: Option<i32>,
+    pub italic: Option<bool>,
+    pub underline: Option<bool>,
+    pub strikethrough: Option<bool>,
+    pub text_align: Option<TextAlignConfig>,
+    pub letter_spacing: Option<i32>,
+    pub padding_x: Option<i32>,
+    pub min_width: Option<i32>,
+    pub max_width: Option<i32>,
+    pub fixed_width: Option<i32>,
+    pub truncation: Option<TruncationConfig>,
+}
+
+impl StyleConfig {
+    /// Returns these styles with the fields set in `other` taking precedence.
+    pub fn merge(&self, other: &StyleConfig) -> StyleConfig {
+        StyleConfig {
+            bg_color: other.bg_color.clone().or(self.bg_color.clone()),
+            fg_color: other.fg_color.clone().or(self.fg_color.clone()),
+            border_style: other.border_style.clone().or(self.border_style.clone()),
+            border_width: other.border_width.or(self.border_width),
+            border_color: other.border_color.clone().or(self.border_color.clone()),
+            border_sides: other.border_sides.clone().or(self.border_sides.clone()),
+            font: other.font.clone().or(self.font.clone()),
+            font_size: other.font_size.or(self.font_size),
+            font_weight: other.font_weight.or(self.font_weight),
+            italic: other.italic.or(self.italic),
+            underline: other.underline.or(self.underline),
+            strikethrough: other.strikethrough.or(self.strikethrough),
+            text_align: other.text_align.or(self.text_align),
+            letter_spacing: other.letter_spacing.or(self.letter_spacing),
+            padding_x: other.padding_x.or(self.padding_x),
+            min_width: other.min_width.or(self.min_width),
+            max_width: other.max_width.or(self.max_width),
+            fixed_width: other.fixed_width.or(self.fixed_width),
+            truncation: other.truncation.or(self.truncation),
+        }
+    }
+
+    /// Resolves the styles of a component from the style classes it references and its inline
+    /// styles.
+    ///
+    /// Classes are applied in the order they are listed, meaning later classes take precedence
+    /// over earlier ones. Inline styles take precedence over all classes.
+    pub fn resolve(
+        classes: &HashMap<String, StyleConfig>,
+        class_names: &[String],
+        inline: &StyleConfig,
+    ) -> Result<StyleConfig> {
+        let mut styles = StyleConfig::default();
+        for name in class_names {
+            let class = classes
+                .get(name)
+                .ok_or_else(|| anyhow!("Unknown style class: {}", name))?;
+            styles = styles.merge(class);
+        }
+
+        Ok(styles.merge(inline))
+    }
+}
+
+#[derive(Clone, Serialize, Deserialize)]
+pub enum StyleConditionConfig {
+    Above(f64),
+    Below(f64),
+    Equals(String),
+    Matches(String),
+    State(String),
+}
+
+impl TryFrom<StyleConditionConfig> for StyleCondition {
+    type Error = anyhow::Error;
+
+    fn try_from(value: StyleConditionConfig) -> Result<Self> {
+        Ok(match value {
+            StyleConditionConfig::Above(threshold) => StyleCondition::Above(threshold),
+            StyleConditionConfig::Below(threshold) => StyleCondition::Below(threshold),
+            StyleConditionConfig::Equals(expected) => StyleCondition::Equals(expected),
+            StyleConditionConfig::Matches(re) => StyleCondition::Matches(
+                Regex::new(&re).with_context(|| format!("Invalid style rule regex: {}", re))?,
+            ),
+            StyleConditionConfig::State(state) => StyleCondition::State(state),
+        })
+    }
+}
+
+/// Styles applied on top of a component's styles when the condition matches.
+#[derive(Clone, Serialize, Deserialize)]
+pub struct StyleRuleConfig {
+    pub when: StyleConditionConfig,
+    #[serde(default)]
+    pub class: Vec<String>,
+    #[serde(default)]
+    pub styles: StyleConfig,
+}
+
+impl StyleRuleConfig {
+    /// Converts this rule into a `StyleRule`, with the rule's styles layered on top of the given
+    /// base styles.
+    pub fn to_rule(
+        &self,
+        classes: &HashMap<String, StyleConfig>,
+        base: &StyleConfig,
+    ) -> Result<StyleRule> {
+        let styles = StyleConfig::resolve(classes, &self.class, &self.styles)?;
+        Ok(StyleRule {
+            condition: self.when.clone().try_into()?,
+            styles: Arc::new(base.merge(&styles).into()),
+        })
+    }
+}
+
+impl From<StyleConfig> for StyleOptions {
+    fn from(value: StyleConfig) -> Self {
+        Self {
+            bg_color: value.bg_color.into_color_option(),
+            fg_color: value.fg_color.into_color_option(),
+            border_style: value.border_style.unwrap_or_default().into(),
+            border_width: value.border_width.unwrap_or_default(),
+            border_color: value.border_color.into_color_option(),
+            border_sides: border_sides(value.border_sides),
+            font: value.font,
+            font_size: value.font_size,
+            font_weight: value.font_weight,
+            italic: value.italic.unwrap_or_default(),
+            underline: value.underline.unwrap_or_default(),
+            strikethrough: value.strikethrough.unwrap_or_default(),
+            text_align: value.text_align.unwrap_or_default().into(),
+            letter_spacing: value.letter_spacing.unwrap_or_default(),
+            padding_x: value.padding_x.unwrap_or_default(),
+            min_width: value.min_width,
+            max_width: value.max_width,
+            fixed_width: value.fixed_width,
+            truncation: value.truncation.unwrap_or_default().into(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn classes() -> HashMap<String, StyleConfig> {
+        HashMap::from([
+            (
+                "pill".to_string(),
+                StyleConfig {
+                    border_style: Some(BorderStyleConfig::Rounded { radius: 8 }),
+                    padding_x: Some(10),
+                    fg_color: ColorConfig::Hex("#FFFFFF".to_string()),
+                    ..Default::default()
+                },
+            ),
+            (
+                "accent".to_string(),
+                StyleConfig {
+                    fg_color: ColorConfig::Hex("#21B550".to_string()),
+                    border_width: Some(2),
+                    ..Default::default()
+                },
+            ),
+        ])
+    }
+
+    #[test]
+    fn resolve_later_classes_take_precedence() {
+        let styles = StyleConfig::resolve(
+            &classes(),
+            &["pill".to_string(), "accent".to_string()],
+            &StyleConfig::default(),
+        )
+        .unwrap();
+
+        assert!(matches!(styles.fg_color, ColorConfig::Hex(hex) if hex == "#21B550"));
+        assert_eq!(styles.padding_x, Some(10));
+        assert_eq!(styles.border_width, Some(2));
+    }
+
+    #[test]
+    fn resolve_inline_styles_take_precedence() {
+        let inline = StyleConfig {
+            padding_x: Some(4),
+            ..Default::default()
+        };
+        let styles = StyleConfig::resolve(&classes(), &["pill".to_string()], &inline).unwrap();
+
+        assert_eq!(styles.padding_x, Some(4));
+        assert!(matches!(
+            styles.border_style,
+            Some(BorderStyleConfig::Rounded { radius: 8 })
+        ));
+    }
+
+    #[test]
+    fn rule_styles_are_layered_on_base_styles() {
+        let base = StyleConfig::resolve(&classes(), &["pill".to_string()], &StyleConfig::default())
+            .unwrap();
+        let rule = StyleRuleConfig {
+            when: StyleConditionConfig::Above(90.0),
+            class: vec!["accent".to_string()],
+            styles: StyleConfig {
+                padding_x: Some(2),
+                ..Default::default()
+            },
+        }
+        .to_rule(&classes(), &base)
+        .unwrap();
+
+        assert!(rule.condition.matches("95", None));
+        assert_eq!(rule.styles.padding_x, 2);
+        assert_eq!(rule.styles.border_width, 2);
+        assert_eq!(rule.styles.border_style.radii().top_left, 4);
+    }
+
+    #[test]
+    fn resolve_unknown_class_fails() {
+        assert!(
+            StyleConfig::resolve(&classes(), &["nope".to_string()], &StyleConfig::default())
+                .is_err()
+        );
+    }
+}
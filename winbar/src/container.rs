@@ -1,38 +1,64 @@
 use std::{
     collections::HashMap,
+    panic::{self, AssertUnwindSafe},
     sync::{atomic::Ordering, mpsc::Receiver, RwLock},
+    time::Instant,
 };
 
 use lazy_static::lazy_static;
-use tracing::instrument;
-use winbar::{color::Color, styles::Styles, WinbarAction};
+use tracing::{field, instrument, Span};
+use winbar::{
+    color::Color,
+    input::{MouseButton, Point},
+    styles::{StyleOptions, Styles},
+    util::{gdi_cache, rect::Rect},
+    WinbarAction, WinbarContext,
+};
 use windows::{
     core::w,
     Win32::{
-        Foundation::{COLORREF, HWND, LPARAM, LRESULT, WPARAM},
+        Foundation::{COLORREF, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM},
         Graphics::Gdi::{
             BeginPaint, BitBlt, CreateCompatibleBitmap, CreateCompatibleDC, CreateSolidBrush,
-            DeleteDC, DeleteObject, EndPaint, GetDC, InvalidateRect, SelectObject, SetBkColor,
-            SetTextColor, HBITMAP, HDC, PAINTSTRUCT, PS_SOLID, SRCCOPY,
+            DeleteDC, DeleteObject, EndPaint, GetDC, IntersectClipRect, InvalidateRect, ReleaseDC,
+            ScreenToClient, SelectObject, SetBkColor, SetTextCharacterExtra, SetTextColor, HBITMAP,
+            HDC, HGDIOBJ, PAINTSTRUCT, SRCCOPY,
         },
         System::{
             LibraryLoader::GetModuleHandleW,
             Threading::{GetStartupInfoW, STARTUPINFOW},
         },
-        UI::WindowsAndMessaging::{
-            CreateWindowExW, DefWindowProcW, DispatchMessageW, PeekMessageW, PostQuitMessage,
-            RegisterClassW, SetLayeredWindowAttributes, ShowWindow, TranslateMessage, LWA_COLORKEY,
-            MSG, PM_REMOVE, SW_SHOWNORMAL, WM_CLOSE, WM_DESTROY, WM_ERASEBKGND, WM_PAINT,
-            WNDCLASSW, WS_EX_LAYERED, WS_EX_TOOLWINDOW, WS_POPUP, WS_VISIBLE,
+        UI::{
+            Controls::WM_MOUSELEAVE,
+            Input::KeyboardAndMouse::{TrackMouseEvent, TME_LEAVE, TRACKMOUSEEVENT},
+            WindowsAndMessaging::{
+                CreateWindowExW, DefWindowProcW, DispatchMessageW, KillTimer, PeekMessageW,
+                PostQuitMessage, RegisterClassW, SetLayeredWindowAttributes, SetTimer, ShowWindow,
+                TranslateMessage, LWA_COLORKEY, MSG, PM_REMOVE, SW_SHOWNORMAL, WM_CLOSE,
+                WM_DESTROY, WM_ERASEBKGND, WM_LBUTTONUP, WM_MBUTTONUP, WM_MOUSEMOVE, WM_MOUSEWHEEL,
+                WM_PAINT, WM_RBUTTONUP, WM_TIMER, WNDCLASSW, WS_EX_LAYERED, WS_EX_TOOLWINDOW,
+                WS_POPUP, WS_VISIBLE,
+            },
         },
     },
 };
 
 use crate::{
-    windows_api::WindowsApi, COMPONENT_MANAGER, DEFAULT_BG_COLOR, DEFAULT_FG_COLOR, DEFAULT_FONT,
-    DEFAULT_FONT_SIZE, HEIGHT, WIDTH,
+    component_impl::{
+        mouse::{self, MouseEvent},
+        supervisor::{self, FAILED_PLACEHOLDER},
+    },
+    popup_window::{PopupRequest, PopupWindow},
+    scheduler::{Dirty, FrameScheduler},
+    tooltip::TooltipWindow,
+    windows_api::WindowsApi,
+    COMPONENT_MANAGER, DEFAULT_BG_COLOR, DEFAULT_FG_COLOR, DEFAULT_FONT, DEFAULT_FONT_SIZE, HEIGHT,
+    TOOLTIP_DELAY, WIDTH,
 };
 
+/// The id of the timer that shows the tooltip after the mouse hovers over a component.
+const TOOLTIP_TIMER_ID: usize = 1;
+
 lazy_static! {
     // HWND -> HBITMAP
     // we use isize since HWND is not hashable
@@ -92,20 +118,22 @@ pub fn create_window(bg_color: Color) -> HWND {
     }
 }
 
-#[instrument(name = "window_listener")]
-pub fn listen(hwnd: HWND, recv: Receiver<WinbarAction>) {
+#[instrument(name = "window_listener", skip(ctx))]
+pub fn listen(hwnd: HWND, recv: Receiver<WinbarAction>, ctx: WinbarContext) {
     let mut msg = MSG::default();
+    let mut tooltip = TooltipWindow::create(hwnd);
+    let mut popup: Option<PopupWindow> = None;
+
+    let mut frames = FrameScheduler::default();
 
     loop {
-        if let Ok(action) = recv.try_recv() {
+        while let Ok(action) = recv.try_recv() {
             match action {
                 WinbarAction::Shutdown => {
                     WindowsApi::send_window_shutdown_msg(hwnd);
                 }
-                WinbarAction::UpdateWindow => unsafe {
-                    // UpdateWindow(hwnd);
-                    InvalidateRect(hwnd, None, true);
-                },
+                WinbarAction::UpdateWindow => frames.request(),
+                WinbarAction::RedrawComponent(index) => frames.request_component(index),
                 WinbarAction::ShowWindow => {
                     WindowsApi::show_window(hwnd);
                 }
@@ -115,18 +143,43 @@ pub fn listen(hwnd: HWND, recv: Receiver<WinbarAction>) {
             }
         }
 
+        if let Some(dirty) = frames.poll(Instant::now()) {
+            invalidate(hwnd, dirty);
+        }
+
         unsafe {
-            if PeekMessageW(&mut msg, hwnd, 0, 0, PM_REMOVE).as_bool() {
+            // messages of all windows of this thread are processed, i.e., the bar, its tooltip and
+            // popups
+            if PeekMessageW(&mut msg, None, 0, 0, PM_REMOVE).as_bool() {
+                if msg.hwnd == hwnd {
+                    if let Some(event) = mouse_event(hwnd, &msg) {
+                        if let Some(request) = handle_mouse(hwnd, &ctx, &mut tooltip, event) {
+                            popup = toggle_popup(hwnd, popup.take(), request);
+                        }
+                    }
+                    if msg.message == WM_TIMER && msg.wParam.0 == TOOLTIP_TIMER_ID {
+                        show_tooltip(hwnd, &mut tooltip);
+                    }
+                } else if msg.message == WM_LBUTTONUP
+                    && popup.as_ref().is_some_and(|popup| popup.hwnd() == msg.hwnd)
+                {
+                    // clicking a popup closes it
+                    popup = None;
+                    continue;
+                }
+
                 TranslateMessage(&msg);
                 DispatchMessageW(&msg);
             }
         }
 
-        if msg.message == WM_CLOSE {
+        if msg.message == WM_CLOSE && msg.hwnd == hwnd {
             break;
         }
     }
 
+    drop(popup);
+
     // cleanup
 
     // release window buffer
@@ -142,8 +195,228 @@ pub fn listen(hwnd: HWND, recv: Receiver<WinbarAction>) {
     tracing::info!("Winbar shutting down...");
 }
 
-#[instrument(level = "trace")]
-pub fn paint(hwnd: HWND, hdc: HDC) {
+/// Converts a window message into a mouse event, if it is one.
+fn mouse_event(hwnd: HWND, msg: &MSG) -> Option<MouseEvent> {
+    // the low and high words of the lparam hold the signed coordinates of the mouse
+    let point = Point {
+        x: (msg.lParam.0 & 0xFFFF) as i16 as i32,
+        y: ((msg.lParam.0 >> 16) & 0xFFFF) as i16 as i32,
+    };
+    let click = |button| Some(MouseEvent::Click { button, point });
+
+    match msg.message {
+        WM_MOUSEMOVE => {
+            // ask for a WM_MOUSELEAVE once the mouse leaves the window
+            let mut track = TRACKMOUSEEVENT {
+                cbSize: std::mem::size_of::<TRACKMOUSEEVENT>() as u32,
+                dwFlags: TME_LEAVE,
+                hwndTrack: hwnd,
+                dwHoverTime: 0,
+            };
+            if let Err(e) = unsafe { TrackMouseEvent(&mut track) } {
+                tracing::error!("Could not track mouse leaving the window: {}", e);
+            }
+
+            Some(MouseEvent::Move(point))
+        }
+        WM_MOUSELEAVE => Some(MouseEvent::Leave),
+        WM_LBUTTONUP => click(MouseButton::Left),
+        WM_RBUTTONUP => click(MouseButton::Right),
+        WM_MBUTTONUP => click(MouseButton::Middle),
+        WM_MOUSEWHEEL => {
+            // wheel messages hold screen coordinates, and the delta in the high word of the wparam
+            let mut screen_point = POINT {
+                x: point.x,
+                y: point.y,
+            };
+            unsafe {
+                ScreenToClient(hwnd, &mut screen_point);
+            }
+
+            Some(MouseEvent::Scroll {
+                point: Point {
+                    x: screen_point.x,
+                    y: screen_point.y,
+                },
+                delta: ((msg.wParam.0 >> 16) & 0xFFFF) as i16 as i32,
+            })
+        }
+        _ => None,
+    }
+}
+
+/// Invalidates the dirty part of the window. Components are redrawn on their own unless the width
+/// of one of them changed, in which case the whole window is laid out again and redrawn.
+fn invalidate(hwnd: HWND, dirty: Dirty) {
+    let regions = match dirty {
+        Dirty::Window => None,
+        Dirty::Components(indices) => match COMPONENT_MANAGER.lock() {
+            Ok(mut manager) => unsafe {
+                let hdc = GetDC(hwnd);
+                let regions = manager.redraw_regions(hwnd, hdc, indices);
+                ReleaseDC(hwnd, hdc);
+                regions
+            },
+            Err(e) => {
+                tracing::error!("Error obtaining component manager lock: {}", e);
+                None
+            }
+        },
+    };
+
+    match regions {
+        Some(regions) => {
+            for region in regions {
+                unsafe {
+                    InvalidateRect(hwnd, Some(&RECT::from(region)), false);
+                }
+            }
+        }
+        None => invalidate_window(hwnd),
+    }
+}
+
+/// Invalidates the whole window, laying out the components again before it's painted.
+fn invalidate_window(hwnd: HWND) {
+    match COMPONENT_MANAGER.lock() {
+        Ok(mut manager) => manager.invalidate_layout(),
+        Err(e) => tracing::error!("Error obtaining component manager lock: {}", e),
+    }
+
+    unsafe {
+        InvalidateRect(hwnd, None, true);
+    }
+}
+
+/// Dispatches a mouse event to the components, then runs the bound actions once the component
+/// manager is released.
+///
+/// The tooltip is hidden whenever the mouse moves onto another component or clicks, and shown after
+/// the tooltip delay once the mouse is over a component.
+///
+/// Returns the popup to toggle, if any.
+fn handle_mouse(
+    hwnd: HWND,
+    ctx: &WinbarContext,
+    tooltip: &mut TooltipWindow,
+    event: MouseEvent,
+) -> Option<PopupRequest> {
+    let (dispatch, hovering) = match COMPONENT_MANAGER.lock() {
+        Ok(mut manager) => {
+            let dispatch = manager.handle_mouse(event);
+            (dispatch, manager.hovered().is_some())
+        }
+        Err(e) => {
+            tracing::error!("Error obtaining component manager lock: {}", e);
+            return None;
+        }
+    };
+
+    if dispatch.hover_changed || matches!(event, MouseEvent::Click { .. }) {
+        tooltip.hide();
+        unsafe {
+            let _ = KillTimer(hwnd, TOOLTIP_TIMER_ID);
+        }
+    }
+    if dispatch.hover_changed && hovering {
+        unsafe {
+            SetTimer(
+                hwnd,
+                TOOLTIP_TIMER_ID,
+                TOOLTIP_DELAY.load(Ordering::SeqCst),
+                None,
+            );
+        }
+    }
+
+    // components are laid out again only if their width changed
+    for index in dispatch.redraw {
+        if let Err(e) = ctx.sender().send(WinbarAction::RedrawComponent(index)) {
+            tracing::error!("Could not send redraw component action over channel: {}", e);
+        }
+    }
+
+    for action in dispatch.actions {
+        if let Err(e) = mouse::run_action(ctx, &action) {
+            tracing::error!("Error while running component action: {}", e);
+        }
+    }
+
+    dispatch.popup
+}
+
+/// Opens the requested popup, closing the open popup first. If the open popup belongs to the same
+/// component, it's only closed.
+fn toggle_popup(
+    hwnd: HWND,
+    open: Option<PopupWindow>,
+    request: PopupRequest,
+) -> Option<PopupWindow> {
+    let same_component = open
+        .as_ref()
+        .is_some_and(|popup| popup.index() == request.index);
+    drop(open);
+
+    if same_component {
+        None
+    } else {
+        Some(PopupWindow::open(hwnd, request))
+    }
+}
+
+/// Shows the tooltip of the component the mouse is over, once the tooltip delay has passed.
+fn show_tooltip(hwnd: HWND, tooltip: &mut TooltipWindow) {
+    unsafe {
+        let _ = KillTimer(hwnd, TOOLTIP_TIMER_ID);
+    }
+
+    let text = match COMPONENT_MANAGER.lock() {
+        Ok(mut manager) => manager.hovered_tooltip(),
+        Err(e) => {
+            tracing::error!("Error obtaining component manager lock: {}", e);
+            return;
+        }
+    };
+    if let Some(text) = text {
+        tooltip.show(&text);
+    }
+}
+
+/// Paints the components intersecting the dirty region, laying out the components first if the
+/// layout is stale.
+///
+/// The span records the paint duration, and the GDI cache lookups since the previous paint.
+#[instrument(
+    level = "debug",
+    skip(hwnd, hdc),
+    fields(
+        duration_us = field::Empty,
+        cache_hits = field::Empty,
+        cache_misses = field::Empty,
+        cache_hit_rate = field::Empty
+    )
+)]
+pub fn paint(hwnd: HWND, hdc: HDC, dirty: &Rect) {
+    let start = Instant::now();
+    // no cached objects are selected into a device context between paints, so they can be deleted
+    gdi_cache::collect();
+
+    draw_components(hwnd, hdc, dirty);
+
+    let stats = gdi_cache::take_stats();
+    let span = Span::current();
+    span.record("duration_us", start.elapsed().as_micros() as u64);
+    span.record("cache_hits", stats.hits);
+    span.record("cache_misses", stats.misses);
+    span.record("cache_hit_rate", stats.hit_rate());
+    tracing::debug!(
+        "Painted in {:?} with a GDI cache hit rate of {:.1}%",
+        start.elapsed(),
+        stats.hit_rate() * 100.0
+    );
+}
+
+fn draw_components(hwnd: HWND, hdc: HDC, dirty: &Rect) {
     let mut manager = match COMPONENT_MANAGER.lock() {
         Ok(manager) => manager,
         Err(e) => {
@@ -152,13 +425,61 @@ pub fn paint(hwnd: HWND, hdc: HDC) {
         }
     };
 
-    // FIXME: not ideal computing locations every time... optimize in the future
-    manager.compute_locations(hwnd, hdc);
+    manager.ensure_layout(hwnd, hdc);
+
+    let mut failures = Vec::new();
+    manager.for_each_indexed(|index, state| {
+        if !state.location().intersects(dirty) {
+            return;
+        }
+
+        if state.health().is_failed() {
+            draw_failed_placeholder(hdc, state.location());
+            return;
+        }
+
+        let drawn = panic::catch_unwind(AssertUnwindSafe(|| {
+            let styles = state.component().styles();
+            let _selected = SelectedStyles::select(hdc, &styles);
+
+            state.component().draw(hwnd, *state.location(), hdc);
+        }));
+        if let Err(e) = drawn {
+            failures.push((index, supervisor::panic_message(e.as_ref())));
+            draw_failed_placeholder(hdc, state.location());
+        }
+    });
+
+    for (index, error) in failures {
+        if let Err(e) = manager.fail(index, error) {
+            tracing::error!("Could not restart failed component: {}", e);
+        }
+    }
+}
 
-    manager.for_each(|state| {
-        let styles = state.component().styles();
+/// Draws the placeholder shown in place of a failed component.
+fn draw_failed_placeholder(hdc: HDC, rect: &Rect) {
+    let styles = StyleOptions::default();
+    let _selected = SelectedStyles::select(hdc, &styles);
 
-        // set styles
+    Styles::draw_rect(hdc, rect, &styles);
+    Styles::draw_text(hdc, rect, FAILED_PLACEHOLDER, &styles);
+}
+
+/// The GDI objects and text settings of a component's styles, selected into a device context.
+/// Values not set in the styles fall back to the configured defaults. The objects are taken from
+/// the [GDI cache](gdi_cache).
+///
+/// The previously selected objects are restored when this is dropped.
+pub struct SelectedStyles {
+    hdc: HDC,
+    old_brush: HGDIOBJ,
+    old_font: HGDIOBJ,
+    old_char_extra: i32,
+}
+
+impl SelectedStyles {
+    pub fn select(hdc: HDC, styles: &StyleOptions) -> Self {
         let font = match &styles.font {
             Some(font) => font.to_string(),
             None => {
@@ -185,26 +506,34 @@ pub fn paint(hwnd: HWND, hdc: HDC) {
             }
         };
 
-        let pen = Styles::pen(bg_color, PS_SOLID);
-        let brush = Styles::solid_brush(bg_color);
-        let font = Styles::font(font_size, &font);
+        let brush = gdi_cache::brush(bg_color);
+        let font = gdi_cache::font(font_size, &font, styles);
 
         unsafe {
-            SelectObject(hdc, pen);
-            SelectObject(hdc, brush);
-            SelectObject(hdc, font);
+            let old_brush = SelectObject(hdc, brush);
+            let old_font = SelectObject(hdc, font);
+            let old_char_extra = SetTextCharacterExtra(hdc, styles.letter_spacing);
             SetBkColor(hdc, COLORREF(bg_color));
             SetTextColor(hdc, COLORREF(fg_color));
-        }
 
-        state.component().draw(hwnd, *state.location(), hdc);
+            Self {
+                hdc,
+                old_brush,
+                old_font,
+                old_char_extra,
+            }
+        }
+    }
+}
 
+impl Drop for SelectedStyles {
+    fn drop(&mut self) {
         unsafe {
-            DeleteObject(pen);
-            DeleteObject(brush);
-            DeleteObject(font);
+            SelectObject(self.hdc, self.old_brush);
+            SelectObject(self.hdc, self.old_font);
+            SetTextCharacterExtra(self.hdc, self.old_char_extra);
         }
-    });
+    }
 }
 
 #[instrument(level = "trace", name = "window_process_function")]
@@ -220,24 +549,42 @@ pub extern "system" fn window_proc(
                 tracing::trace!("Starting painting...");
                 let mut ps = PAINTSTRUCT::default();
                 let hdc = BeginPaint(hwnd, &mut ps);
+                let dirty = Rect::from(ps.rcPaint);
 
-                // double buffered window
+                // double buffered window, where the buffer keeps the components outside of the
+                // dirty region from the previous paint
                 let buffers = WINDOW_BUFFERS.read().unwrap();
                 if let Some(buffer) = buffers.get(&hwnd.0) {
                     let hdc_buffer = CreateCompatibleDC(hdc);
                     let old_hdc = SelectObject(hdc_buffer, *buffer);
-                    let width = WIDTH.load(Ordering::SeqCst);
-                    let height = HEIGHT.load(Ordering::SeqCst);
-
-                    paint(hwnd, hdc_buffer);
-
-                    BitBlt(hdc, 0, 0, width, height, hdc_buffer, 0, 0, SRCCOPY).unwrap();
+                    IntersectClipRect(
+                        hdc_buffer,
+                        ps.rcPaint.left,
+                        ps.rcPaint.top,
+                        ps.rcPaint.right,
+                        ps.rcPaint.bottom,
+                    );
+
+                    paint(hwnd, hdc_buffer, &dirty);
+
+                    BitBlt(
+                        hdc,
+                        dirty.x,
+                        dirty.y,
+                        dirty.width,
+                        dirty.height,
+                        hdc_buffer,
+                        dirty.x,
+                        dirty.y,
+                        SRCCOPY,
+                    )
+                    .unwrap();
 
                     SelectObject(hdc_buffer, old_hdc);
 
                     DeleteDC(hdc_buffer);
                 } else {
-                    paint(hwnd, hdc);
+                    paint(hwnd, hdc, &dirty);
                 }
 
                 EndPaint(hwnd, &ps);
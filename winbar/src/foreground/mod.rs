@@ -0,0 +1,33 @@
+//! The foreground window, i.e., the window the user is working in.
+//!
+//! Changes come from a [`ForegroundSource`], which on Windows listens to window events instead of
+//! polling.
+use std::sync::Arc;
+
+use anyhow::{bail, Result};
+use tokio::sync::mpsc::UnboundedReceiver;
+
+pub mod win32;
+
+/// The title and process of a window.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct WindowInfo {
+    pub title: String,
+    /// The file name of the process's executable, e.g., `chrome.exe`, or empty if it's unknown.
+    pub process: String,
+}
+
+pub trait ForegroundSource: Send + Sync {
+    /// Watches the foreground window, sending the current one and then the foreground window
+    /// whenever it or its title changes. Watching stops once the receiver is dropped.
+    fn watch(&self) -> Result<UnboundedReceiver<WindowInfo>>;
+}
+
+/// Creates the foreground window source of the current platform.
+pub fn system() -> Result<Arc<dyn ForegroundSource>> {
+    if cfg!(windows) {
+        Ok(Arc::new(win32::Win32ForegroundSource))
+    } else {
+        bail!("The foreground window is only available on Windows")
+    }
+}
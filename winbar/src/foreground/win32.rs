@@ -0,0 +1,169 @@
+use std::{cell::RefCell, path::Path, sync::mpsc, thread};
+
+use anyhow::{anyhow, Context, Result};
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+use windows::{
+    core::PWSTR,
+    Win32::{
+        Foundation::{CloseHandle, HWND},
+        System::Threading::{
+            OpenProcess, QueryFullProcessImageNameW, PROCESS_NAME_WIN32,
+            PROCESS_QUERY_LIMITED_INFORMATION,
+        },
+        UI::{
+            Accessibility::{SetWinEventHook, UnhookWinEvent, HWINEVENTHOOK},
+            WindowsAndMessaging::{
+                DispatchMessageW, GetForegroundWindow, GetMessageW, GetWindowTextLengthW,
+                GetWindowTextW, GetWindowThreadProcessId, PostQuitMessage, TranslateMessage,
+                EVENT_OBJECT_NAMECHANGE, EVENT_SYSTEM_FOREGROUND, MSG, OBJID_WINDOW,
+                WINEVENT_OUTOFCONTEXT, WINEVENT_SKIPOWNPROCESS,
+            },
+        },
+    },
+};
+
+use super::{ForegroundSource, WindowInfo};
+
+thread_local! {
+    /// The sender of the watch whose hooks run on this thread. Out of context hooks are called on
+    /// the thread that set them, and their callback has no user data.
+    static SENDER: RefCell<Option<UnboundedSender<WindowInfo>>> = const { RefCell::new(None) };
+}
+
+/// Watches the foreground window with window event hooks, each watch on its own thread running a
+/// message loop.
+pub struct Win32ForegroundSource;
+
+impl ForegroundSource for Win32ForegroundSource {
+    fn watch(&self) -> Result<UnboundedReceiver<WindowInfo>> {
+        let (send, recv) = unbounded_channel();
+        let (hooked_send, hooked_recv) = mpsc::channel();
+        thread::Builder::new()
+            .name("foreground-window".to_string())
+            .spawn(move || watch_thread(send, hooked_send))
+            .context("Could not start the foreground window thread")?;
+
+        hooked_recv
+            .recv()
+            .context("The foreground window thread exited")??;
+        Ok(recv)
+    }
+}
+
+fn watch_thread(send: UnboundedSender<WindowInfo>, hooked: mpsc::Sender<Result<()>>) {
+    if let Some(window) = window_info(unsafe { GetForegroundWindow() }) {
+        let _ = send.send(window);
+    }
+    SENDER.with(|sender| *sender.borrow_mut() = Some(send));
+
+    let hooks = [EVENT_SYSTEM_FOREGROUND, EVENT_OBJECT_NAMECHANGE].map(|event| unsafe {
+        SetWinEventHook(
+            event,
+            event,
+            None,
+            Some(on_event),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT | WINEVENT_SKIPOWNPROCESS,
+        )
+    });
+    if hooks.iter().any(|hook| hook.0 == 0) {
+        unhook(&hooks);
+        let _ = hooked.send(Err(anyhow!("Could not hook the foreground window events")));
+        return;
+    }
+    let _ = hooked.send(Ok(()));
+
+    // runs until the receiver is dropped
+    let mut msg = MSG::default();
+    unsafe {
+        while GetMessageW(&mut msg, None, 0, 0).as_bool() {
+            TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+    }
+
+    unhook(&hooks);
+}
+
+fn unhook(hooks: &[HWINEVENTHOOK]) {
+    for hook in hooks.iter().filter(|hook| hook.0 != 0) {
+        unsafe {
+            UnhookWinEvent(*hook);
+        }
+    }
+}
+
+unsafe extern "system" fn on_event(
+    _hook: HWINEVENTHOOK,
+    event: u32,
+    hwnd: HWND,
+    object: i32,
+    _child: i32,
+    _thread: u32,
+    _time: u32,
+) {
+    // name changes are reported for every object of every window
+    if event == EVENT_OBJECT_NAMECHANGE
+        && (object != OBJID_WINDOW.0 || hwnd != GetForegroundWindow())
+    {
+        return;
+    }
+    let Some(window) = window_info(hwnd) else {
+        return;
+    };
+
+    let sent = SENDER.with(|sender| {
+        sender
+            .borrow()
+            .as_ref()
+            .is_some_and(|sender| sender.send(window).is_ok())
+    });
+    if !sent {
+        PostQuitMessage(0);
+    }
+}
+
+/// The title and process of the window, or `None` if there is no window.
+fn window_info(hwnd: HWND) -> Option<WindowInfo> {
+    if hwnd.0 == 0 {
+        return None;
+    }
+
+    unsafe {
+        let mut title = vec![0; GetWindowTextLengthW(hwnd) as usize + 1];
+        let length = GetWindowTextW(hwnd, &mut title);
+        let title = String::from_utf16_lossy(&title[..length.max(0) as usize]);
+
+        let mut process_id = 0;
+        GetWindowThreadProcessId(hwnd, Some(&mut process_id));
+
+        Some(WindowInfo {
+            title,
+            process: process_name(process_id).unwrap_or_default(),
+        })
+    }
+}
+
+/// The file name of the executable of the process.
+fn process_name(process_id: u32) -> Result<String> {
+    unsafe {
+        let process = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, process_id)?;
+        let mut path = vec![0u16; 1024];
+        let mut size = path.len() as u32;
+        let queried = QueryFullProcessImageNameW(
+            process,
+            PROCESS_NAME_WIN32,
+            PWSTR(path.as_mut_ptr()),
+            &mut size,
+        );
+        let _ = CloseHandle(process);
+        queried?;
+
+        let path = String::from_utf16_lossy(&path[..size as usize]);
+        Ok(Path::new(&path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or(path))
+    }
+}
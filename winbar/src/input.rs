@@ -0,0 +1,26 @@
+//! Mouse input delivered to components.
+use serde::{Deserialize, Serialize};
+
+/// A mouse button.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MouseButton {
+    Left,
+    Right,
+    Middle,
+}
+
+/// A change in whether the mouse is over a component.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Hover {
+    /// The mouse entered the component.
+    Enter,
+    /// The mouse left the component.
+    Leave,
+}
+
+/// A point, in pixels.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Point {
+    pub x: i32,
+    pub y: i32,
+}
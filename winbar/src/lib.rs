@@ -1,15 +1,28 @@
-use std::sync::{mpsc::Sender, Arc};
+use std::{
+    fmt::Display,
+    sync::{
+        mpsc::{SendError, Sender},
+        Arc, Mutex,
+    },
+};
 
 use async_trait::async_trait;
 use getset::Getters;
+use input::{Hover, MouseButton, Point};
+use popup::PopupContent;
 use styles::StyleOptions;
+use tokio_util::sync::CancellationToken;
 use util::rect::Rect;
 use windows::Win32::{Foundation::HWND, Graphics::Gdi::HDC};
 
 pub mod client;
 pub mod color;
+pub mod input;
+pub mod plugin;
+pub mod popup;
 pub mod protocol;
 pub mod styles;
+pub mod template;
 pub mod util;
 
 pub const DEFAULT_PORT: i32 = 10989;
@@ -18,6 +31,8 @@ pub const DEFAULT_HOSTNAME: &str = "localhost";
 pub enum WinbarAction {
     Shutdown,
     UpdateWindow,
+    /// Redraws only the component at the given index, relaying out the window if its width changed.
+    RedrawComponent(usize),
     ShowWindow,
     HideWindow,
 }
@@ -26,18 +41,81 @@ pub enum WinbarAction {
 pub struct WinbarContext {
     #[getset(get = "pub")]
     sender: Sender<WinbarAction>,
+    /// The token cancelled when the component (or winbar as a whole) should stop.
+    #[getset(get = "pub")]
+    cancellation_token: CancellationToken,
+    /// The index of the component this context was given to, if any.
+    #[getset(get = "pub")]
+    component: Option<usize>,
+    /// The error reported by the component through [`WinbarContext::fail`], if any.
+    failure: Arc<Mutex<Option<String>>>,
 }
 
 impl WinbarContext {
     pub fn new(sender: Sender<WinbarAction>) -> Self {
-        Self { sender }
+        Self {
+            sender,
+            cancellation_token: CancellationToken::new(),
+            component: None,
+            failure: Arc::default(),
+        }
+    }
+
+    /// Creates a context with a child cancellation token, i.e., a token that is cancelled either
+    /// when this context's token is cancelled or when it is cancelled itself.
+    pub fn child(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            cancellation_token: self.cancellation_token.child_token(),
+            component: self.component,
+            failure: self.failure.clone(),
+        }
+    }
+
+    /// Creates a child context for the component at the given index.
+    pub fn for_component(&self, index: usize) -> Self {
+        Self {
+            component: Some(index),
+            failure: Arc::default(),
+            ..self.child()
+        }
+    }
+
+    /// Reports that the component failed for a reason it can't recover from. The component should
+    /// return from `start` afterwards, after which it's restarted after a backoff, like a component
+    /// whose `start` panics.
+    pub fn fail(&self, error: impl Display) {
+        *self.failure.lock().unwrap_or_else(|e| e.into_inner()) = Some(error.to_string());
+    }
+
+    /// Takes the error reported through [`WinbarContext::fail`], if any.
+    pub fn take_failure(&self) -> Option<String> {
+        self.failure
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    }
+
+    /// Requests a redraw of the component this context was given to, or of the whole window if
+    /// the context doesn't belong to a component.
+    pub fn request_redraw(&self) -> Result<(), SendError<WinbarAction>> {
+        let action = match self.component {
+            Some(index) => WinbarAction::RedrawComponent(index),
+            None => WinbarAction::UpdateWindow,
+        };
+        self.sender.send(action)
     }
 }
 
 #[async_trait]
 pub trait Component {
+    /// The styles of the component, resolved against the component's current value and state.
     fn styles(&self) -> Arc<StyleOptions>;
 
+    /// Sets the explicit state of the component (e.g., "warning"), used to select conditional
+    /// styles.
+    fn set_state(&self, _state: Option<String>) {}
+
     /// The width of the component.
     fn width(&self, hwnd: HWND, hdc: HDC) -> i32;
 
@@ -45,6 +123,41 @@ pub trait Component {
     /// creates.
     fn draw(&self, hwnd: HWND, rect: Rect, hdc: HDC);
 
+    /// Called when the component is clicked. The point is relative to the component's top left
+    /// corner. The component is redrawn afterwards.
+    fn on_click(&self, _button: MouseButton, _point: Point) {}
+
+    /// Called when the mouse wheel is scrolled over the component. A positive delta is a scroll up,
+    /// where a single notch of a standard wheel is 120. The component is redrawn afterwards.
+    fn on_scroll(&self, _delta: i32) {}
+
+    /// Called when the mouse enters or leaves the component. The component is redrawn afterwards.
+    fn on_hover(&self, _hover: Hover) {}
+
+    /// The tooltip shown when the mouse hovers over the component, if any.
+    fn tooltip(&self) -> Option<String> {
+        None
+    }
+
+    /// The content of the popup panel opened from the component, if it has one.
+    fn popup(&self) -> Option<PopupContent> {
+        None
+    }
+
+    /// Switches to the component's alternative format, or back, if the component has one.
+    fn toggle_format(&self) {}
+
     /// Start any logic related to the component (e.g., a task to UpdateDraw).
+    ///
+    /// The component is stopped by cancelling the context's cancellation token. If this future has
+    /// not returned by then, it is dropped at its next await point, after which `stop` is called.
+    /// Long running synchronous work should check the token itself. Returning from this method
+    /// does not stop the component.
+    ///
+    /// If this future panics, or returns after reporting an error with [`WinbarContext::fail`], the
+    /// component is restarted (i.e., this method is called again) after a backoff.
     async fn start(&self, ctx: WinbarContext, hwnd: HWND, rect: Rect);
+
+    /// Cleanup any resources created by `start`. Called once the component has been stopped.
+    async fn stop(&self) {}
 }
@@ -1,7 +1,7 @@
 use std::{
     path::PathBuf,
     sync::{
-        atomic::{AtomicI32, Ordering},
+        atomic::{AtomicI32, AtomicU32, Ordering},
         mpsc, Arc, Mutex,
     },
     thread,
@@ -10,7 +10,7 @@ use std::{
 use anyhow::{anyhow, Context};
 use clap::Parser;
 use cli::WinbarCli;
-use component_impl::manager::ComponentManager;
+use component_impl::manager::{ComponentManager, COMPONENT_STOP_TIMEOUT};
 use config::Config;
 use lazy_static::lazy_static;
 use tokio::runtime;
@@ -30,7 +30,12 @@ pub mod cli;
 pub mod component_impl;
 pub mod config;
 pub mod container;
+pub mod foreground;
+pub mod metrics;
+pub mod popup_window;
+pub mod scheduler;
 pub mod server;
+pub mod tooltip;
 pub mod windows_api;
 
 // runtime variables
@@ -43,6 +48,7 @@ static POSITION_X: AtomicI32 = AtomicI32::new(0);
 static POSITION_Y: AtomicI32 = AtomicI32::new(0);
 static COMPONENT_GAP: AtomicI32 = AtomicI32::new(10);
 static DEFAULT_FONT_SIZE: AtomicI32 = AtomicI32::new(18);
+static TOOLTIP_DELAY: AtomicU32 = AtomicU32::new(500);
 
 lazy_static! {
     static ref STATUS_BAR_BG_COLOR: Arc<Mutex<Color>> = Arc::new(Mutex::new(Color::Transparent));
@@ -59,6 +65,7 @@ lazy_static! {
     // Segoe UI Variable is the default windows font
     static ref DEFAULT_FONT: Arc<Mutex<String>> =
         Arc::new(Mutex::new("Segoe UI Variable".to_string()));
+    static ref POPUP_FONT: Arc<Mutex<String>> = Arc::new(Mutex::new("Consolas".to_string()));
     static ref WINBAR_HWND: Arc<Mutex<HWND>> = Arc::new(Mutex::new(HWND(0)));
     static ref COMPONENT_MANAGER: Arc<Mutex<ComponentManager>> =
         Arc::new(Mutex::new(ComponentManager::new()));
@@ -92,9 +99,15 @@ pub fn read_config() -> anyhow::Result<()> {
     tracing::info!("Adding components from config");
     match COMPONENT_MANAGER.lock() {
         Ok(mut manager) => {
-            config.components.iter().for_each(|data| {
-                manager.add(data.location, data.component.to_component());
-            });
+            for data in config.components.iter() {
+                manager.add(
+                    data.id.clone(),
+                    data.location,
+                    data.to_component(&config.styles)?,
+                    data.actions.clone(),
+                    data.tooltip.clone(),
+                );
+            }
         }
         Err(e) => {
             tracing::error!("Error obtaining component manager lock: {}", e)
@@ -143,24 +156,23 @@ fn main() -> anyhow::Result<()> {
     let (send, recv) = mpsc::channel::<WinbarAction>();
     let winbar_ctx = WinbarContext::new(send);
 
-    tracing::info!("Starting component runner thread");
-    let cloned_ctx = winbar_ctx.clone();
-    thread::spawn(move || {
-        let rt = runtime::Runtime::new().unwrap();
-        match COMPONENT_MANAGER.lock() {
-            Ok(mut manager) => {
-                let set = manager.start(cloned_ctx, winbar_hwnd);
-                drop(manager);
-
-                rt.block_on(set);
-            }
-            Err(e) => {
-                tracing::error!("Error obtaining component manager lock {}", e);
-            }
+    tracing::info!("Starting components");
+    let component_rt = runtime::Runtime::new()?;
+    match COMPONENT_MANAGER.lock() {
+        Ok(mut manager) => {
+            manager.start(
+                winbar_ctx.clone(),
+                winbar_hwnd,
+                component_rt.handle().clone(),
+            );
         }
-    });
+        Err(e) => {
+            tracing::error!("Error obtaining component manager lock {}", e);
+        }
+    }
 
     tracing::info!("Starting server");
+    let server_ctx = winbar_ctx.clone();
     thread::spawn(move || {
         let rt = runtime::Runtime::new().unwrap();
 
@@ -171,7 +183,7 @@ fn main() -> anyhow::Result<()> {
                 DEFAULT_HOSTNAME,
                 SERVER_PORT.load(Ordering::SeqCst)
             );
-            match WinbarServer::new(&url, winbar_ctx).await {
+            match WinbarServer::new(&url, server_ctx).await {
                 Ok(mut server) => {
                     if let Err(e) = server.start_listening().await {
                         tracing::error!("Error while starting to listen for connections: {}", e);
@@ -185,7 +197,34 @@ fn main() -> anyhow::Result<()> {
     });
 
     tracing::info!("Starting window listener");
-    container::listen(winbar_hwnd, recv);
+    container::listen(winbar_hwnd, recv, winbar_ctx.clone());
+
+    tracing::info!("Stopping components");
+    winbar_ctx.cancellation_token().cancel();
+    match COMPONENT_MANAGER.lock() {
+        Ok(mut manager) => {
+            let handles = manager.stop_all();
+            drop(manager);
+
+            let stopped = component_rt.block_on(async {
+                tokio::time::timeout(COMPONENT_STOP_TIMEOUT, async {
+                    for handle in handles {
+                        let _ = handle.await;
+                    }
+                })
+                .await
+            });
+            if stopped.is_err() {
+                tracing::warn!(
+                    "Components did not stop within {:?}",
+                    COMPONENT_STOP_TIMEOUT
+                );
+            }
+        }
+        Err(e) => {
+            tracing::error!("Error obtaining component manager lock {}", e);
+        }
+    }
 
     tracing::info!("Shutting down GDI+");
     WindowsApi::shutdown_gdiplus(token);
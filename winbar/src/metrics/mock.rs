@@ -0,0 +1,56 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::{anyhow, Result};
+
+use super::{
+    BatteryStatus, CpuUsage, DiskUsage, MemoryUsage, NetworkInterface, SystemMetricsProvider,
+};
+
+/// A provider returning the metrics set by the test, or an error for metrics that aren't set.
+#[derive(Default)]
+pub struct MockMetricsProvider {
+    pub cpu: Mutex<Option<CpuUsage>>,
+    pub memory: Mutex<Option<MemoryUsage>>,
+    pub disks: Mutex<HashMap<PathBuf, DiskUsage>>,
+    pub network: Mutex<Vec<NetworkInterface>>,
+    pub battery: Mutex<Option<BatteryStatus>>,
+}
+
+impl SystemMetricsProvider for MockMetricsProvider {
+    fn cpu_usage(&self) -> Result<CpuUsage> {
+        self.cpu
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| anyhow!("No CPU usage"))
+    }
+
+    fn memory_usage(&self) -> Result<MemoryUsage> {
+        self.memory
+            .lock()
+            .unwrap()
+            .clone()
+            .ok_or_else(|| anyhow!("No memory usage"))
+    }
+
+    fn disk_usage(&self, path: &Path) -> Result<DiskUsage> {
+        self.disks
+            .lock()
+            .unwrap()
+            .get(path)
+            .copied()
+            .ok_or_else(|| anyhow!("No disk at {}", path.display()))
+    }
+
+    fn network_interfaces(&self) -> Result<Vec<NetworkInterface>> {
+        Ok(self.network.lock().unwrap().clone())
+    }
+
+    fn battery(&self) -> Result<Option<BatteryStatus>> {
+        Ok(self.battery.lock().unwrap().clone())
+    }
+}
@@ -0,0 +1,159 @@
+//! System metrics (e.g., CPU usage) displayed by the built-in components.
+//!
+//! Metrics come from a [`SystemMetricsProvider`]: on Windows the performance counters and system
+//! APIs, and elsewhere `/proc`, which allows developing the components on Linux.
+use std::{net::IpAddr, path::Path, sync::Arc, time::Duration};
+
+use anyhow::Result;
+
+#[cfg(test)]
+pub mod mock;
+pub mod proc;
+pub mod win32;
+
+/// The CPU usage over a sampling period, as percentages from 0 to 100.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CpuUsage {
+    pub total: f64,
+    /// The usage of each logical core, in order.
+    pub cores: Vec<f64>,
+}
+
+/// The memory and swap (i.e., page file) usage, in bytes.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MemoryUsage {
+    pub total: u64,
+    pub available: u64,
+    pub swap_total: u64,
+    pub swap_free: u64,
+}
+
+impl MemoryUsage {
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.available)
+    }
+
+    /// The percentage of the memory that is used.
+    pub fn percent(&self) -> f64 {
+        percent(self.used(), self.total)
+    }
+
+    pub fn swap_used(&self) -> u64 {
+        self.swap_total.saturating_sub(self.swap_free)
+    }
+
+    /// The percentage of the swap that is used, or 0 without swap.
+    pub fn swap_percent(&self) -> f64 {
+        percent(self.swap_used(), self.swap_total)
+    }
+}
+
+/// The space of the file system of a drive or path, in bytes.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DiskUsage {
+    pub total: u64,
+    /// The space available to the user, which may exclude space reserved for the system.
+    pub free: u64,
+}
+
+impl DiskUsage {
+    pub fn used(&self) -> u64 {
+        self.total.saturating_sub(self.free)
+    }
+
+    /// The percentage of the space that is used.
+    pub fn percent(&self) -> f64 {
+        percent(self.used(), self.total)
+    }
+}
+
+/// The status and traffic counters of a network interface.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NetworkInterface {
+    /// The name of the interface, e.g., `Wi-Fi` on Windows or `wlan0` on Linux.
+    pub name: String,
+    /// The first IPv4 address of the interface, or else its first IPv6 address.
+    pub address: Option<IpAddr>,
+    pub connected: bool,
+    /// Whether the default route goes through the interface.
+    pub default: bool,
+    /// The bytes received since the interface came up.
+    pub received: u64,
+    /// The bytes sent since the interface came up.
+    pub sent: u64,
+}
+
+/// Whether a battery is charging.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BatteryState {
+    Charging,
+    Discharging,
+    /// Plugged in and not charging, usually since the battery is full.
+    Full,
+}
+
+impl BatteryState {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Charging => "charging",
+            Self::Discharging => "discharging",
+            Self::Full => "full",
+        }
+    }
+}
+
+/// The charge of a battery.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatteryStatus {
+    /// The charge as a percentage from 0 to 100.
+    pub percent: f64,
+    pub state: BatteryState,
+    /// The estimated time until the battery is empty when discharging, or full when charging, if
+    /// it is known.
+    pub time_remaining: Option<Duration>,
+}
+
+fn percent(part: u64, total: u64) -> f64 {
+    if total == 0 {
+        return 0.0;
+    }
+
+    part as f64 / total as f64 * 100.0
+}
+
+pub trait SystemMetricsProvider: Send + Sync {
+    /// The CPU usage since the previous call, or since the provider was created for the first
+    /// call.
+    fn cpu_usage(&self) -> Result<CpuUsage>;
+
+    fn memory_usage(&self) -> Result<MemoryUsage>;
+
+    /// The usage of the file system containing the path (e.g., `C:\\` or `/`).
+    fn disk_usage(&self, path: &Path) -> Result<DiskUsage>;
+
+    fn network_interfaces(&self) -> Result<Vec<NetworkInterface>>;
+
+    /// The status of the battery, or `None` if the system has none.
+    fn battery(&self) -> Result<Option<BatteryStatus>>;
+}
+
+/// Creates the metrics provider of the current platform. On Windows, it doesn't measure the CPU
+/// usage, see [`cpu`].
+///
+/// Providers measure usage since their previous sample, so every component should have its own.
+pub fn system() -> Arc<dyn SystemMetricsProvider> {
+    if cfg!(windows) {
+        Arc::new(win32::Win32Provider::new())
+    } else {
+        Arc::new(proc::ProcProvider::new())
+    }
+}
+
+/// Creates the metrics provider of the current platform, measuring the CPU usage from now on.
+pub fn cpu() -> Result<Arc<dyn SystemMetricsProvider>> {
+    if cfg!(windows) {
+        Ok(Arc::new(win32::Win32Provider::with_cpu_usage()?))
+    } else {
+        Ok(Arc::new(proc::ProcProvider::new()))
+    }
+}
@@ -0,0 +1,510 @@
+use std::{collections::HashMap, fs, net::IpAddr, path::Path, sync::Mutex, time::Duration};
+
+use anyhow::{bail, Context, Result};
+
+use super::{
+    BatteryState, BatteryStatus, CpuUsage, DiskUsage, MemoryUsage, NetworkInterface,
+    SystemMetricsProvider,
+};
+
+const STAT_PATH: &str = "/proc/stat";
+const MEMINFO_PATH: &str = "/proc/meminfo";
+const NET_DEV_PATH: &str = "/proc/net/dev";
+const ROUTE_PATH: &str = "/proc/net/route";
+const POWER_SUPPLY_PATH: &str = "/sys/class/power_supply";
+
+/// Metrics read from `/proc`, for developing on Linux.
+pub struct ProcProvider {
+    /// The CPU times of the previous sample, the total first.
+    cpu_times: Mutex<Vec<CpuTimes>>,
+}
+
+impl ProcProvider {
+    pub fn new() -> Self {
+        // the first call measures the usage since the provider was created
+        let cpu_times = read_cpu_times().unwrap_or_default();
+
+        Self {
+            cpu_times: Mutex::new(cpu_times),
+        }
+    }
+}
+
+impl Default for ProcProvider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemMetricsProvider for ProcProvider {
+    fn cpu_usage(&self) -> Result<CpuUsage> {
+        let current = read_cpu_times()?;
+        let previous = std::mem::replace(&mut *self.cpu_times.lock().unwrap(), current.clone());
+
+        let mut usages = current.iter().enumerate().map(|(index, times)| {
+            previous
+                .get(index)
+                .map_or(0.0, |previous| times.usage_since(previous))
+        });
+
+        Ok(CpuUsage {
+            total: usages.next().unwrap_or_default(),
+            cores: usages.collect(),
+        })
+    }
+
+    fn memory_usage(&self) -> Result<MemoryUsage> {
+        let meminfo = fs::read_to_string(MEMINFO_PATH)
+            .with_context(|| format!("Could not read {}", MEMINFO_PATH))?;
+        parse_meminfo(&meminfo)
+    }
+
+    fn disk_usage(&self, path: &Path) -> Result<DiskUsage> {
+        statvfs(path)
+    }
+
+    fn network_interfaces(&self) -> Result<Vec<NetworkInterface>> {
+        let net_dev = fs::read_to_string(NET_DEV_PATH)
+            .with_context(|| format!("Could not read {}", NET_DEV_PATH))?;
+        // without a routing table, no interface is the default one
+        let default = fs::read_to_string(ROUTE_PATH)
+            .ok()
+            .and_then(|route| parse_default_route(&route));
+        let addresses = interface_addresses();
+
+        let interfaces = parse_net_dev(&net_dev)?
+            .into_iter()
+            .map(|(name, received, sent)| {
+                // loopback and some virtual interfaces are up but report an unknown state
+                let operstate = fs::read_to_string(format!("/sys/class/net/{}/operstate", name))
+                    .unwrap_or_default();
+                NetworkInterface {
+                    address: addresses.get(&name).copied(),
+                    connected: matches!(operstate.trim(), "up" | "unknown"),
+                    default: default.as_deref() == Some(name.as_str()),
+                    received,
+                    sent,
+                    name,
+                }
+            })
+            .collect();
+
+        Ok(interfaces)
+    }
+
+    fn battery(&self) -> Result<Option<BatteryStatus>> {
+        // without power supplies, e.g., in a container, there is no battery
+        let Ok(supplies) = fs::read_dir(POWER_SUPPLY_PATH) else {
+            return Ok(None);
+        };
+
+        for supply in supplies.flatten() {
+            let path = supply.path();
+            let attribute = |name: &str| fs::read_to_string(path.join(name)).ok();
+            if attribute("type").is_some_and(|kind| kind.trim() == "Battery") {
+                return parse_battery(attribute).map(Some);
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(unix)]
+fn statvfs(path: &Path) -> Result<DiskUsage> {
+    use std::{ffi::CString, io, os::unix::ffi::OsStrExt};
+
+    let c_path = CString::new(path.as_os_str().as_bytes())?;
+    let mut stat = unsafe { std::mem::zeroed::<libc::statvfs>() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(io::Error::last_os_error())
+            .with_context(|| format!("Could not get the file system of {}", path.display()));
+    }
+
+    // the field types differ between platforms
+    #[allow(clippy::unnecessary_cast)]
+    let (block_size, blocks, available) = (
+        stat.f_frsize as u64,
+        stat.f_blocks as u64,
+        stat.f_bavail as u64,
+    );
+    Ok(DiskUsage {
+        total: blocks * block_size,
+        free: available * block_size,
+    })
+}
+
+#[cfg(not(unix))]
+fn statvfs(path: &Path) -> Result<DiskUsage> {
+    bail!("Could not get the file system of {}", path.display())
+}
+
+/// The first IPv4 address of each interface, or else its first IPv6 address.
+#[cfg(unix)]
+fn interface_addresses() -> HashMap<String, IpAddr> {
+    use std::{
+        ffi::CStr,
+        net::{Ipv4Addr, Ipv6Addr},
+    };
+
+    let mut addresses = HashMap::new();
+    let mut ifaddrs = std::ptr::null_mut();
+    if unsafe { libc::getifaddrs(&mut ifaddrs) } != 0 {
+        return addresses;
+    }
+
+    let mut current = ifaddrs;
+    while let Some(ifaddr) = unsafe { current.as_ref() } {
+        current = ifaddr.ifa_next;
+
+        let Some(sockaddr) = (unsafe { ifaddr.ifa_addr.as_ref() }) else {
+            continue;
+        };
+        let address = match sockaddr.sa_family as i32 {
+            libc::AF_INET => {
+                let sockaddr = unsafe { &*(ifaddr.ifa_addr as *const libc::sockaddr_in) };
+                IpAddr::V4(Ipv4Addr::from(u32::from_be(sockaddr.sin_addr.s_addr)))
+            }
+            libc::AF_INET6 => {
+                let sockaddr = unsafe { &*(ifaddr.ifa_addr as *const libc::sockaddr_in6) };
+                IpAddr::V6(Ipv6Addr::from(sockaddr.sin6_addr.s6_addr))
+            }
+            _ => continue,
+        };
+
+        let name = unsafe { CStr::from_ptr(ifaddr.ifa_name) }
+            .to_string_lossy()
+            .into_owned();
+        addresses
+            .entry(name)
+            .and_modify(|existing: &mut IpAddr| {
+                if existing.is_ipv6() && address.is_ipv4() {
+                    *existing = address;
+                }
+            })
+            .or_insert(address);
+    }
+
+    unsafe { libc::freeifaddrs(ifaddrs) };
+    addresses
+}
+
+#[cfg(not(unix))]
+fn interface_addresses() -> HashMap<String, IpAddr> {
+    HashMap::new()
+}
+
+/// Parses the name and the received and sent bytes of each interface from `/proc/net/dev`.
+pub fn parse_net_dev(net_dev: &str) -> Result<Vec<(String, u64, u64)>> {
+    // the first two lines are headers
+    net_dev
+        .lines()
+        .skip(2)
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| {
+            let (name, counters) = line
+                .split_once(':')
+                .with_context(|| format!("Invalid interface counters: {}", line))?;
+            // the received bytes are the first of 8 received counters, followed by the sent ones
+            let counters = counters
+                .split_whitespace()
+                .map(str::parse::<u64>)
+                .collect::<Result<Vec<u64>, _>>()
+                .with_context(|| format!("Invalid interface counters: {}", line))?;
+            if counters.len() < 9 {
+                bail!("Invalid interface counters: {}", line);
+            }
+
+            Ok((name.trim().to_string(), counters[0], counters[8]))
+        })
+        .collect()
+}
+
+/// Parses the interface of the default route from `/proc/net/route`, if there is one.
+pub fn parse_default_route(route: &str) -> Option<String> {
+    route.lines().skip(1).find_map(|line| {
+        let mut columns = line.split_whitespace();
+        let name = columns.next()?;
+        let destination = columns.next()?;
+        (destination == "00000000").then(|| name.to_string())
+    })
+}
+
+/// Parses the status of a battery from the attributes of its power supply in sysfs, where
+/// energies are in µWh and charges in µAh.
+pub fn parse_battery(attribute: impl Fn(&str) -> Option<String>) -> Result<BatteryStatus> {
+    let number = |name: &str| attribute(name)?.trim().parse::<f64>().ok();
+
+    let percent = number("capacity").context("No capacity of the battery")?;
+    let state = match attribute("status").as_deref().map(str::trim) {
+        Some("Charging") => BatteryState::Charging,
+        Some("Discharging") => BatteryState::Discharging,
+        // "Full", "Not charging" or "Unknown" when plugged in
+        _ => BatteryState::Full,
+    };
+
+    // batteries report either their energy and power, or their charge and current
+    let (now, full, rate) = match number("energy_now") {
+        Some(energy) => (energy, number("energy_full"), number("power_now")),
+        None => (
+            number("charge_now").unwrap_or_default(),
+            number("charge_full"),
+            number("current_now"),
+        ),
+    };
+    let hours = match (state, full, rate) {
+        (_, _, None) => None,
+        (_, _, Some(rate)) if rate <= 0.0 => None,
+        (BatteryState::Discharging, _, Some(rate)) => Some(now / rate),
+        (BatteryState::Charging, Some(full), Some(rate)) => Some((full - now).max(0.0) / rate),
+        _ => None,
+    };
+
+    Ok(BatteryStatus {
+        percent,
+        state,
+        time_remaining: hours.map(|hours| Duration::from_secs_f64(hours * 3600.0)),
+    })
+}
+
+/// The time a CPU spent idle and in total since boot, in clock ticks.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CpuTimes {
+    pub idle: u64,
+    pub total: u64,
+}
+
+impl CpuTimes {
+    /// The percentage of the time since the previous times that the CPU wasn't idle.
+    pub fn usage_since(&self, previous: &CpuTimes) -> f64 {
+        let total = self.total.saturating_sub(previous.total);
+        let idle = self.idle.saturating_sub(previous.idle);
+        if total == 0 {
+            return 0.0;
+        }
+
+        total.saturating_sub(idle) as f64 / total as f64 * 100.0
+    }
+}
+
+fn read_cpu_times() -> Result<Vec<CpuTimes>> {
+    let stat =
+        fs::read_to_string(STAT_PATH).with_context(|| format!("Could not read {}", STAT_PATH))?;
+    parse_cpu_times(&stat)
+}
+
+/// Parses the `cpu` lines of `/proc/stat`, i.e., the total times followed by those of each core.
+pub fn parse_cpu_times(stat: &str) -> Result<Vec<CpuTimes>> {
+    let times = stat
+        .lines()
+        .filter(|line| line.starts_with("cpu"))
+        .map(|line| {
+            // user, nice, system, idle, iowait, irq, softirq, steal; guest time is part of user
+            let values = line
+                .split_whitespace()
+                .skip(1)
+                .take(8)
+                .map(str::parse::<u64>)
+                .collect::<Result<Vec<u64>, _>>()
+                .with_context(|| format!("Invalid CPU times: {}", line))?;
+            if values.len() < 4 {
+                bail!("Invalid CPU times: {}", line);
+            }
+
+            Ok(CpuTimes {
+                idle: values[3] + values.get(4).copied().unwrap_or_default(),
+                total: values.iter().sum(),
+            })
+        })
+        .collect::<Result<Vec<CpuTimes>>>()?;
+
+    if times.is_empty() {
+        bail!("No CPU times in {}", STAT_PATH);
+    }
+
+    Ok(times)
+}
+
+/// Parses the memory usage from `/proc/meminfo`, where sizes are in KiB.
+pub fn parse_meminfo(meminfo: &str) -> Result<MemoryUsage> {
+    let field = |name: &str| -> Result<u64> {
+        let line = meminfo
+            .lines()
+            .find_map(|line| line.strip_prefix(name)?.strip_prefix(':'))
+            .with_context(|| format!("No {} in {}", name, MEMINFO_PATH))?;
+        let kib = line
+            .trim()
+            .trim_end_matches("kB")
+            .trim()
+            .parse::<u64>()
+            .with_context(|| format!("Invalid {}: {}", name, line.trim()))?;
+        Ok(kib * 1024)
+    };
+
+    Ok(MemoryUsage {
+        total: field("MemTotal")?,
+        available: field("MemAvailable")?,
+        swap_total: field("SwapTotal")?,
+        swap_free: field("SwapFree")?,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const STAT: &str = "cpu  400 0 100 1400 100 0 0 0 0 0
+cpu0 300 0 50 600 50 0 0 0 0 0
+cpu1 100 0 50 800 50 0 0 0 0 0
+intr 12345
+ctxt 67890
+";
+
+    #[test]
+    fn parses_cpu_times() {
+        let times = parse_cpu_times(STAT).unwrap();
+        assert_eq!(
+            times,
+            vec![
+                CpuTimes {
+                    idle: 1500,
+                    total: 2000
+                },
+                CpuTimes {
+                    idle: 650,
+                    total: 1000
+                },
+                CpuTimes {
+                    idle: 850,
+                    total: 1000
+                },
+            ]
+        );
+
+        assert!(parse_cpu_times("intr 12345").is_err());
+        assert!(parse_cpu_times("cpu 1 2 x 4").is_err());
+    }
+
+    #[test]
+    fn parses_meminfo() {
+        let meminfo = "MemTotal:       16384000 kB
+MemFree:         1024000 kB
+MemAvailable:    4096000 kB
+SwapCached:            0 kB
+SwapTotal:       2048000 kB
+SwapFree:        1536000 kB
+";
+        let usage = parse_meminfo(meminfo).unwrap();
+        assert_eq!(
+            usage,
+            MemoryUsage {
+                total: 16384000 * 1024,
+                available: 4096000 * 1024,
+                swap_total: 2048000 * 1024,
+                swap_free: 1536000 * 1024,
+            }
+        );
+        assert_eq!(usage.percent(), 75.0);
+        assert_eq!(usage.swap_percent(), 25.0);
+
+        assert!(parse_meminfo("MemTotal: 1 kB").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn disk_usage_of_root() {
+        let usage = ProcProvider::new().disk_usage(Path::new("/")).unwrap();
+        assert!(usage.total > 0);
+        assert!(usage.free <= usage.total);
+
+        assert!(ProcProvider::new()
+            .disk_usage(Path::new("/does/not/exist"))
+            .is_err());
+    }
+
+    #[test]
+    fn parses_network_interfaces() {
+        let net_dev = "Inter-|   Receive                                                |  Transmit
+ face |bytes    packets errs drop fifo frame compressed multicast|bytes    packets errs drop fifo colls carrier compressed
+    lo:    1000      10    0    0    0     0          0         0     1000      10    0    0    0     0       0          0
+ wlan0: 5000000    4000    0    0    0     0          0         0   250000    2000    0    0    0     0       0          0
+";
+        assert_eq!(
+            parse_net_dev(net_dev).unwrap(),
+            vec![
+                ("lo".to_string(), 1000, 1000),
+                ("wlan0".to_string(), 5000000, 250000)
+            ]
+        );
+        assert!(parse_net_dev("\n\n eth0: 1 2 3").is_err());
+
+        let route = "Iface	Destination	Gateway 	Flags	RefCnt	Use	Metric	Mask		MTU	Window	IRTT
+wlan0	0000A8C0	00000000	0001	0	0	600	00FFFFFF	0	0	0
+wlan0	00000000	0100A8C0	0003	0	0	600	00000000	0	0	0
+";
+        assert_eq!(parse_default_route(route).as_deref(), Some("wlan0"));
+        assert_eq!(parse_default_route(route.lines().next().unwrap()), None);
+    }
+
+    #[test]
+    fn parses_battery() {
+        let battery = |attributes: &[(&str, &str)]| {
+            let attributes = attributes
+                .iter()
+                .map(|(name, value)| (name.to_string(), format!("{}\n", value)))
+                .collect::<HashMap<String, String>>();
+            parse_battery(|name| attributes.get(name).cloned())
+        };
+
+        assert_eq!(
+            battery(&[
+                ("capacity", "50"),
+                ("status", "Discharging"),
+                ("energy_now", "20000000"),
+                ("energy_full", "40000000"),
+                ("power_now", "10000000"),
+            ])
+            .unwrap(),
+            BatteryStatus {
+                percent: 50.0,
+                state: BatteryState::Discharging,
+                time_remaining: Some(Duration::from_secs(2 * 3600)),
+            }
+        );
+        assert_eq!(
+            battery(&[
+                ("capacity", "75"),
+                ("status", "Charging"),
+                ("charge_now", "3000000"),
+                ("charge_full", "4000000"),
+                ("current_now", "2000000"),
+            ])
+            .unwrap()
+            .time_remaining,
+            Some(Duration::from_secs(30 * 60))
+        );
+        assert_eq!(
+            battery(&[("capacity", "100"), ("status", "Not charging")]).unwrap(),
+            BatteryStatus {
+                percent: 100.0,
+                state: BatteryState::Full,
+                time_remaining: None,
+            }
+        );
+        assert!(battery(&[("status", "Full")]).is_err());
+    }
+
+    #[test]
+    fn usage_is_busy_fraction_of_elapsed_time() {
+        let previous = CpuTimes {
+            idle: 1500,
+            total: 2000,
+        };
+        let current = CpuTimes {
+            idle: 1575,
+            total: 2100,
+        };
+        assert_eq!(current.usage_since(&previous), 25.0);
+        assert_eq!(previous.usage_since(&previous), 0.0);
+    }
+}
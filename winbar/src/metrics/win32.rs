@@ -0,0 +1,410 @@
+use std::{
+    mem,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr},
+    path::Path,
+    ptr,
+    sync::Mutex,
+    time::Duration,
+};
+
+use anyhow::{bail, Result};
+use windows::{
+    core::{w, HSTRING},
+    Win32::{
+        Foundation::{ERROR_BUFFER_OVERFLOW, ERROR_NO_DATA, NO_ERROR, WIN32_ERROR},
+        NetworkManagement::{
+            IpHelper::{
+                GetAdaptersAddresses, GetBestInterface, GetIfEntry2, GAA_FLAG_SKIP_ANYCAST,
+                GAA_FLAG_SKIP_DNS_SERVER, GAA_FLAG_SKIP_MULTICAST, IP_ADAPTER_ADDRESSES_LH,
+                IP_ADAPTER_UNICAST_ADDRESS_LH, MIB_IF_ROW2,
+            },
+            Ndis::IfOperStatusUp,
+        },
+        Networking::WinSock::{AF_INET, AF_INET6, AF_UNSPEC, SOCKADDR_IN, SOCKADDR_IN6},
+        Storage::FileSystem::GetDiskFreeSpaceExW,
+        System::{
+            Performance::{
+                PdhAddEnglishCounterW, PdhCloseQuery, PdhCollectQueryData,
+                PdhGetFormattedCounterArrayW, PdhOpenQueryW, PDH_CSTATUS_VALID_DATA,
+                PDH_FMT_COUNTERVALUE_ITEM_W, PDH_FMT_DOUBLE, PDH_MORE_DATA,
+            },
+            Power::{GetSystemPowerStatus, SYSTEM_POWER_STATUS},
+            SystemInformation::{GlobalMemoryStatusEx, MEMORYSTATUSEX},
+        },
+    },
+};
+
+use super::{
+    BatteryState, BatteryStatus, CpuUsage, DiskUsage, MemoryUsage, NetworkInterface,
+    SystemMetricsProvider,
+};
+
+/// The status returned by PDH functions on success.
+const SUCCESS: u32 = 0;
+
+/// The instance of the processor counter for all cores together.
+const TOTAL_INSTANCE: &str = "_Total";
+
+/// The battery flag of systems without a battery.
+const NO_BATTERY: u8 = 128;
+/// The battery flag of a charging battery.
+const CHARGING: u8 = 8;
+/// The value of unknown battery fields.
+const UNKNOWN: u8 = 255;
+
+/// The initial size of the adapter addresses buffer recommended by the documentation.
+const ADAPTER_ADDRESSES_SIZE: u32 = 15_000;
+
+/// Metrics read from the Windows performance counters (PDH) and system APIs.
+pub struct Win32Provider {
+    /// The processor time query, only opened for providers of the CPU usage so that providers of
+    /// other metrics don't depend on PDH. It's locked while collecting since a query is collected
+    /// for all its counters at once.
+    processor: Option<Mutex<ProcessorQuery>>,
+}
+
+/// A PDH query of the processor time of each core, closed when dropped.
+struct ProcessorQuery {
+    query: isize,
+    processor_time: isize,
+}
+
+impl ProcessorQuery {
+    fn open() -> Result<Self> {
+        unsafe {
+            let mut query = 0;
+            check(PdhOpenQueryW(None, 0, &mut query), "open query")?;
+
+            let mut processor_time = 0;
+            let status = PdhAddEnglishCounterW(
+                query,
+                w!("\\Processor(*)\\% Processor Time"),
+                0,
+                &mut processor_time,
+            );
+            if let Err(e) = check(status, "add processor time counter") {
+                PdhCloseQuery(query);
+                return Err(e);
+            }
+
+            // rates are computed between two collections, so the first one is only a baseline
+            PdhCollectQueryData(query);
+
+            Ok(Self {
+                query,
+                processor_time,
+            })
+        }
+    }
+
+    /// The values of each instance of a counter, by instance name.
+    fn instances(&self) -> Result<Vec<(String, f64)>> {
+        let counter = self.processor_time;
+        unsafe {
+            let mut size = 0;
+            let mut count = 0;
+            let status =
+                PdhGetFormattedCounterArrayW(counter, PDH_FMT_DOUBLE, &mut size, &mut count, None);
+            if status != PDH_MORE_DATA {
+                check(status, "get counter array size")?;
+                return Ok(Vec::new());
+            }
+
+            // the instance names are stored in the same buffer, after the items
+            let item_size = mem::size_of::<PDH_FMT_COUNTERVALUE_ITEM_W>();
+            let mut items =
+                Vec::<PDH_FMT_COUNTERVALUE_ITEM_W>::with_capacity(size as usize / item_size + 1);
+            let status = PdhGetFormattedCounterArrayW(
+                counter,
+                PDH_FMT_DOUBLE,
+                &mut size,
+                &mut count,
+                Some(items.as_mut_ptr()),
+            );
+            check(status, "get counter array")?;
+            items.set_len(count as usize);
+
+            Ok(items
+                .iter()
+                .filter(|item| item.FmtValue.CStatus == PDH_CSTATUS_VALID_DATA)
+                .filter_map(|item| {
+                    let name = item.szName.to_string().ok()?;
+                    Some((name, item.FmtValue.Anonymous.doubleValue))
+                })
+                .collect())
+        }
+    }
+}
+
+impl Drop for ProcessorQuery {
+    fn drop(&mut self) {
+        unsafe {
+            PdhCloseQuery(self.query);
+        }
+    }
+}
+
+impl Win32Provider {
+    /// Creates a provider of every metric but the CPU usage.
+    pub fn new() -> Self {
+        Self { processor: None }
+    }
+
+    /// Creates a provider of every metric, measuring the CPU usage from now on.
+    pub fn with_cpu_usage() -> Result<Self> {
+        Ok(Self {
+            processor: Some(Mutex::new(ProcessorQuery::open()?)),
+        })
+    }
+}
+
+impl Default for Win32Provider {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SystemMetricsProvider for Win32Provider {
+    fn cpu_usage(&self) -> Result<CpuUsage> {
+        let Some(processor) = &self.processor else {
+            bail!("The provider was not created to measure the CPU usage");
+        };
+        let processor = processor.lock().unwrap();
+        check(
+            unsafe { PdhCollectQueryData(processor.query) },
+            "collect query data",
+        )?;
+
+        let mut total = 0.0;
+        let mut cores = Vec::new();
+        for (name, value) in processor.instances()? {
+            if name == TOTAL_INSTANCE {
+                total = value;
+            } else if let Ok(index) = name.parse::<usize>() {
+                cores.push((index, value));
+            }
+        }
+        cores.sort_by_key(|(index, _)| *index);
+
+        Ok(CpuUsage {
+            total,
+            cores: cores.into_iter().map(|(_, value)| value).collect(),
+        })
+    }
+
+    fn memory_usage(&self) -> Result<MemoryUsage> {
+        let mut status = MEMORYSTATUSEX {
+            dwLength: mem::size_of::<MEMORYSTATUSEX>() as u32,
+            ..Default::default()
+        };
+        unsafe { GlobalMemoryStatusEx(&mut status)? };
+
+        // the page file figures are the commit limit, i.e., the physical memory plus the page file
+        Ok(MemoryUsage {
+            total: status.ullTotalPhys,
+            available: status.ullAvailPhys,
+            swap_total: status.ullTotalPageFile.saturating_sub(status.ullTotalPhys),
+            swap_free: status.ullAvailPageFile.saturating_sub(status.ullAvailPhys),
+        })
+    }
+
+    fn disk_usage(&self, path: &Path) -> Result<DiskUsage> {
+        let mut free = 0;
+        let mut total = 0;
+        unsafe {
+            GetDiskFreeSpaceExW(
+                &HSTRING::from(path.to_string_lossy().as_ref()),
+                Some(&mut free),
+                Some(&mut total),
+                None,
+            )?;
+        }
+
+        Ok(DiskUsage { total, free })
+    }
+
+    fn network_interfaces(&self) -> Result<Vec<NetworkInterface>> {
+        let default = default_interface_index();
+        let adapters = adapter_addresses()?;
+
+        let mut interfaces = Vec::new();
+        unsafe {
+            let mut adapter = adapters.first().map_or(ptr::null(), |first| {
+                (first as *const u64).cast::<IP_ADAPTER_ADDRESSES_LH>()
+            });
+            while let Some(current) = adapter.as_ref() {
+                adapter = current.Next;
+
+                let index = current.Anonymous1.Anonymous.IfIndex;
+                let mut row = MIB_IF_ROW2 {
+                    InterfaceIndex: index,
+                    ..Default::default()
+                };
+                // adapters removed since they were listed have no counters
+                if GetIfEntry2(&mut row).is_err() {
+                    continue;
+                }
+
+                interfaces.push(NetworkInterface {
+                    name: current.FriendlyName.to_string()?,
+                    address: first_address(current.FirstUnicastAddress),
+                    connected: current.OperStatus == IfOperStatusUp,
+                    default: default == Some(index),
+                    received: row.InOctets,
+                    sent: row.OutOctets,
+                });
+            }
+        }
+
+        Ok(interfaces)
+    }
+
+    fn battery(&self) -> Result<Option<BatteryStatus>> {
+        let mut status = SYSTEM_POWER_STATUS::default();
+        unsafe { GetSystemPowerStatus(&mut status)? };
+
+        Ok(battery_status(&status))
+    }
+}
+
+/// The addresses of the network adapters, as a buffer of linked `IP_ADAPTER_ADDRESSES_LH`, which
+/// is empty without adapters.
+fn adapter_addresses() -> Result<Vec<u64>> {
+    let flags = GAA_FLAG_SKIP_ANYCAST | GAA_FLAG_SKIP_MULTICAST | GAA_FLAG_SKIP_DNS_SERVER;
+    let mut size = ADAPTER_ADDRESSES_SIZE;
+    loop {
+        // a buffer of u64 for the alignment of the adapters
+        let mut buffer = vec![0u64; (size as usize).div_ceil(mem::size_of::<u64>())];
+        let status = unsafe {
+            GetAdaptersAddresses(
+                AF_UNSPEC.0 as u32,
+                flags,
+                None,
+                Some(buffer.as_mut_ptr().cast()),
+                &mut size,
+            )
+        };
+
+        // the size is updated when the buffer is too small, e.g., if an adapter was added
+        match WIN32_ERROR(status) {
+            NO_ERROR => return Ok(buffer),
+            ERROR_NO_DATA => return Ok(Vec::new()),
+            ERROR_BUFFER_OVERFLOW => continue,
+            _ => bail!("Could not get the network adapters (error {:#x})", status),
+        }
+    }
+}
+
+/// The first IPv4 address in a list of unicast addresses, or else the first IPv6 address.
+unsafe fn first_address(mut unicast: *const IP_ADAPTER_UNICAST_ADDRESS_LH) -> Option<IpAddr> {
+    let mut addresses = Vec::new();
+    while let Some(current) = unicast.as_ref() {
+        unicast = current.Next;
+
+        let sockaddr = current.Address.lpSockaddr;
+        let Some(family) = sockaddr.as_ref().map(|sockaddr| sockaddr.sa_family) else {
+            continue;
+        };
+        if family == AF_INET {
+            let sockaddr = &*(sockaddr as *const SOCKADDR_IN);
+            let address = u32::from_be(sockaddr.sin_addr.S_un.S_addr);
+            addresses.push(IpAddr::V4(Ipv4Addr::from(address)));
+        } else if family == AF_INET6 {
+            let sockaddr = &*(sockaddr as *const SOCKADDR_IN6);
+            addresses.push(IpAddr::V6(Ipv6Addr::from(sockaddr.sin6_addr.u.Byte)));
+        }
+    }
+
+    addresses
+        .iter()
+        .find(|address| address.is_ipv4())
+        .or(addresses.first())
+        .copied()
+}
+
+/// The index of the interface of the default route, found by looking up the route to a public
+/// address, which sends no traffic.
+fn default_interface_index() -> Option<u32> {
+    // in network byte order
+    let destination = u32::from_ne_bytes([1, 1, 1, 1]);
+    let mut index = 0;
+    let status = unsafe { GetBestInterface(destination, &mut index) };
+    (status == NO_ERROR.0).then_some(index)
+}
+
+/// The status of the battery from the power status, or `None` without a battery.
+fn battery_status(status: &SYSTEM_POWER_STATUS) -> Option<BatteryStatus> {
+    if status.BatteryFlag & NO_BATTERY != 0
+        || status.BatteryFlag == UNKNOWN
+        || status.BatteryLifePercent == UNKNOWN
+    {
+        return None;
+    }
+
+    let state = if status.BatteryFlag & CHARGING != 0 {
+        BatteryState::Charging
+    } else if status.ACLineStatus == 1 {
+        BatteryState::Full
+    } else {
+        BatteryState::Discharging
+    };
+
+    // only the time until the battery is empty is known, and -1 if it isn't
+    let time_remaining = (state == BatteryState::Discharging && status.BatteryLifeTime != u32::MAX)
+        .then(|| Duration::from_secs(status.BatteryLifeTime.into()));
+
+    Some(BatteryStatus {
+        percent: status.BatteryLifePercent.into(),
+        state,
+        time_remaining,
+    })
+}
+
+fn check(status: u32, action: &str) -> Result<()> {
+    if status != SUCCESS {
+        bail!("Could not {} (PDH status {:#x})", action, status);
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn battery_status_from_power_status() {
+        let power_status = |ac_line, flag, percent, life_time| SYSTEM_POWER_STATUS {
+            ACLineStatus: ac_line,
+            BatteryFlag: flag,
+            BatteryLifePercent: percent,
+            BatteryLifeTime: life_time,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            battery_status(&power_status(0, 0, 42, 3600)),
+            Some(BatteryStatus {
+                percent: 42.0,
+                state: BatteryState::Discharging,
+                time_remaining: Some(Duration::from_secs(3600)),
+            })
+        );
+        assert_eq!(
+            battery_status(&power_status(1, CHARGING, 42, u32::MAX)).map(|status| status.state),
+            Some(BatteryState::Charging)
+        );
+        assert_eq!(
+            battery_status(&power_status(1, 1, 100, u32::MAX)),
+            Some(BatteryStatus {
+                percent: 100.0,
+                state: BatteryState::Full,
+                time_remaining: None,
+            })
+        );
+        assert_eq!(
+            battery_status(&power_status(1, NO_BATTERY, UNKNOWN, u32::MAX)),
+            None
+        );
+    }
+}
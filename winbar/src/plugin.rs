@@ -0,0 +1,319 @@
+//! The C ABI of component plugins.
+//!
+//! A plugin is a dynamic library (a `.dll` on Windows, or a `.so` elsewhere) exporting a function
+//! named [`ENTRY_SYMBOL`] of type [`PluginEntry`], which returns the plugin's [`PluginVTable`]. The
+//! vtable mirrors the [`Component`](crate::Component) trait: winbar creates an instance of the
+//! plugin per configured component and calls into it to render, start and stop the component, and
+//! to deliver mouse events.
+//!
+//! Strings crossing the boundary are null-terminated UTF-8. Strings returned by the plugin are owned
+//! by the plugin and handed back to it through `free_string` once winbar is done with them. The
+//! component is rendered from a JSON encoded list of [`RenderCommand`]s, which are laid out left to
+//! right.
+//!
+//! Functions of an instance may be called from any thread, but never concurrently with `destroy`.
+//!
+//! Plugins written in Rust can implement the [`Plugin`] trait and export it with
+//! [`export_plugin!`](crate::export_plugin) instead of implementing the ABI by hand.
+use std::{
+    ffi::{c_char, c_void, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::input::{Hover, MouseButton, Point};
+
+/// The version of the ABI. Plugins built against a different version are not loaded.
+pub const ABI_VERSION: u32 = 1;
+
+/// The name of the function exported by plugins.
+pub const ENTRY_SYMBOL: &str = "winbar_plugin_entry";
+
+/// The function exported by plugins, returning a pointer to a vtable that lives as long as the
+/// library is loaded.
+pub type PluginEntry = unsafe extern "C" fn() -> *const PluginVTable;
+
+/// Requests winbar to redraw the component. Called by the plugin with the `host` pointer given to
+/// `start`, until `stop` returns.
+pub type NotifyFn = extern "C" fn(host: *mut c_void);
+
+/// The mouse buttons passed to `on_click`.
+pub const MOUSE_BUTTON_LEFT: u32 = 0;
+pub const MOUSE_BUTTON_RIGHT: u32 = 1;
+pub const MOUSE_BUTTON_MIDDLE: u32 = 2;
+
+#[repr(C)]
+pub struct PluginVTable {
+    /// Must be [`ABI_VERSION`].
+    pub abi_version: u32,
+    /// Creates an instance from the JSON encoded options of the component, returning null if the
+    /// options are invalid.
+    pub create: unsafe extern "C" fn(options: *const c_char) -> *mut c_void,
+    /// Destroys an instance. It's stopped beforehand if it was started.
+    pub destroy: unsafe extern "C" fn(instance: *mut c_void),
+    /// Returns the JSON encoded render commands of the component. The commands are drawn until the
+    /// plugin calls `notify` or receives a mouse event, after which it's rendered again.
+    pub render: unsafe extern "C" fn(instance: *mut c_void) -> *mut c_char,
+    /// Returns the tooltip of the component, or null if it has none.
+    pub tooltip: unsafe extern "C" fn(instance: *mut c_void) -> *mut c_char,
+    /// Frees a string returned by the plugin.
+    pub free_string: unsafe extern "C" fn(string: *mut c_char),
+    /// Starts the component. This must not block; long running work belongs on a thread of the
+    /// plugin's own.
+    pub start: unsafe extern "C" fn(instance: *mut c_void, notify: NotifyFn, host: *mut c_void),
+    /// Stops the component. `notify` must not be called once this returns.
+    pub stop: unsafe extern "C" fn(instance: *mut c_void),
+    /// Called when the component is clicked, with one of the `MOUSE_BUTTON_*` buttons and the
+    /// point relative to the component.
+    pub on_click: unsafe extern "C" fn(instance: *mut c_void, button: u32, x: i32, y: i32),
+    /// Called when the mouse wheel is scrolled over the component.
+    pub on_scroll: unsafe extern "C" fn(instance: *mut c_void, delta: i32),
+    /// Called when the mouse enters (`true`) or leaves (`false`) the component.
+    pub on_hover: unsafe extern "C" fn(instance: *mut c_void, entered: bool),
+}
+
+/// A drawing instruction of a plugin component.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum RenderCommand {
+    /// Draws text with the component's styles, optionally with a different foreground color given
+    /// as a hex string (e.g., "#ff0000").
+    Text {
+        text: String,
+        #[serde(default)]
+        fg_color: Option<String>,
+    },
+    /// Leaves empty space of the given width, in pixels.
+    Space(i32),
+}
+
+impl RenderCommand {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text {
+            text: text.into(),
+            fg_color: None,
+        }
+    }
+}
+
+impl MouseButton {
+    /// The button as passed to `on_click`.
+    pub fn to_abi(self) -> u32 {
+        match self {
+            Self::Left => MOUSE_BUTTON_LEFT,
+            Self::Right => MOUSE_BUTTON_RIGHT,
+            Self::Middle => MOUSE_BUTTON_MIDDLE,
+        }
+    }
+
+    pub fn from_abi(button: u32) -> Option<Self> {
+        match button {
+            MOUSE_BUTTON_LEFT => Some(Self::Left),
+            MOUSE_BUTTON_RIGHT => Some(Self::Right),
+            MOUSE_BUTTON_MIDDLE => Some(Self::Middle),
+            _ => None,
+        }
+    }
+}
+
+/// Requests winbar to redraw a plugin component.
+pub struct Notifier {
+    notify: NotifyFn,
+    host: *mut c_void,
+}
+
+// the host pointer is only ever passed back to winbar, which allows it to be used from any thread
+unsafe impl Send for Notifier {}
+unsafe impl Sync for Notifier {}
+
+impl Notifier {
+    pub fn notify(&self) {
+        (self.notify)(self.host);
+    }
+}
+
+/// A component plugin written in Rust, exported with [`export_plugin!`](crate::export_plugin).
+///
+/// Panics are caught at the ABI boundary.
+pub trait Plugin: Send + Sync + Sized + 'static {
+    /// Creates the plugin from the options of the component.
+    fn create(options: serde_json::Value) -> Result<Self, String>;
+
+    /// The commands drawing the component.
+    fn render(&self) -> Vec<RenderCommand>;
+
+    fn tooltip(&self) -> Option<String> {
+        None
+    }
+
+    /// Starts the component. The notifier may be used to redraw the component until `stop` is
+    /// called.
+    fn start(&self, _notifier: Notifier) {}
+
+    fn stop(&self) {}
+
+    fn on_click(&self, _button: MouseButton, _point: Point) {}
+
+    fn on_scroll(&self, _delta: i32) {}
+
+    fn on_hover(&self, _hover: Hover) {}
+}
+
+/// Exports a type implementing [`Plugin`] as the plugin of the library.
+#[macro_export]
+macro_rules! export_plugin {
+    ($plugin:ty) => {
+        #[no_mangle]
+        pub unsafe extern "C" fn winbar_plugin_entry() -> *const $crate::plugin::PluginVTable {
+            $crate::plugin::vtable::<$plugin>()
+        }
+    };
+}
+
+/// Returns the vtable of a plugin. The vtable is leaked, so this should be called once per library.
+pub fn vtable<P: Plugin>() -> *const PluginVTable {
+    Box::into_raw(Box::new(PluginVTable {
+        abi_version: ABI_VERSION,
+        create: create::<P>,
+        destroy: destroy::<P>,
+        render: render::<P>,
+        tooltip: tooltip::<P>,
+        free_string,
+        start: start::<P>,
+        stop: stop::<P>,
+        on_click: on_click::<P>,
+        on_scroll: on_scroll::<P>,
+        on_hover: on_hover::<P>,
+    }))
+}
+
+/// Runs the function, returning the default if it panics, since unwinding across the ABI boundary
+/// is undefined behavior.
+fn guard<T>(default: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(default)
+}
+
+fn into_c_string(string: String) -> *mut c_char {
+    // interior null bytes can't be represented, so they're dropped
+    let string = string.replace('\0', "");
+    CString::new(string)
+        .map(CString::into_raw)
+        .unwrap_or(ptr::null_mut())
+}
+
+unsafe fn instance<'a, P>(instance: *mut c_void) -> &'a P {
+    &*(instance as *const P)
+}
+
+unsafe extern "C" fn create<P: Plugin>(options: *const c_char) -> *mut c_void {
+    guard(ptr::null_mut(), || {
+        let options = match CStr::from_ptr(options)
+            .to_str()
+            .ok()
+            .and_then(|options| serde_json::from_str(options).ok())
+        {
+            Some(options) => options,
+            None => return ptr::null_mut(),
+        };
+
+        match P::create(options) {
+            Ok(plugin) => Box::into_raw(Box::new(plugin)) as *mut c_void,
+            Err(_) => ptr::null_mut(),
+        }
+    })
+}
+
+unsafe extern "C" fn destroy<P: Plugin>(instance: *mut c_void) {
+    guard((), || drop(Box::from_raw(instance as *mut P)))
+}
+
+unsafe extern "C" fn render<P: Plugin>(instance: *mut c_void) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        let commands = self::instance::<P>(instance).render();
+        serde_json::to_string(&commands)
+            .map(into_c_string)
+            .unwrap_or(ptr::null_mut())
+    })
+}
+
+unsafe extern "C" fn tooltip<P: Plugin>(instance: *mut c_void) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        self::instance::<P>(instance)
+            .tooltip()
+            .map(into_c_string)
+            .unwrap_or(ptr::null_mut())
+    })
+}
+
+unsafe extern "C" fn free_string(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+unsafe extern "C" fn start<P: Plugin>(instance: *mut c_void, notify: NotifyFn, host: *mut c_void) {
+    guard((), || {
+        self::instance::<P>(instance).start(Notifier { notify, host })
+    })
+}
+
+unsafe extern "C" fn stop<P: Plugin>(instance: *mut c_void) {
+    guard((), || self::instance::<P>(instance).stop())
+}
+
+unsafe extern "C" fn on_click<P: Plugin>(instance: *mut c_void, button: u32, x: i32, y: i32) {
+    guard((), || {
+        if let Some(button) = MouseButton::from_abi(button) {
+            self::instance::<P>(instance).on_click(button, Point { x, y });
+        }
+    })
+}
+
+unsafe extern "C" fn on_scroll<P: Plugin>(instance: *mut c_void, delta: i32) {
+    guard((), || self::instance::<P>(instance).on_scroll(delta))
+}
+
+unsafe extern "C" fn on_hover<P: Plugin>(instance: *mut c_void, entered: bool) {
+    guard((), || {
+        let hover = if entered { Hover::Enter } else { Hover::Leave };
+        self::instance::<P>(instance).on_hover(hover)
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn render_commands_deserialize() {
+        let commands: Vec<RenderCommand> = serde_json::from_str(
+            r##"[
+                { "Text": { "text": "cpu" } },
+                { "Space": 4 },
+                { "Text": { "text": "42%", "fg_color": "#ff0000" } }
+            ]"##,
+        )
+        .unwrap();
+
+        assert_eq!(
+            commands,
+            vec![
+                RenderCommand::text("cpu"),
+                RenderCommand::Space(4),
+                RenderCommand::Text {
+                    text: "42%".to_string(),
+                    fg_color: Some("#ff0000".to_string()),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn mouse_button_abi_round_trips() {
+        for button in [MouseButton::Left, MouseButton::Right, MouseButton::Middle] {
+            assert_eq!(MouseButton::from_abi(button.to_abi()), Some(button));
+        }
+        assert_eq!(MouseButton::from_abi(42), None);
+    }
+}
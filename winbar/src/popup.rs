@@ -0,0 +1,70 @@
+//! The content of popup panels opened from components.
+
+/// A run of text within a line of a popup.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PopupSpan {
+    pub text: String,
+    /// Whether the span is drawn with the foreground and background colors swapped.
+    pub emphasized: bool,
+}
+
+impl PopupSpan {
+    pub fn plain(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            emphasized: false,
+        }
+    }
+
+    pub fn emphasized(text: impl Into<String>) -> Self {
+        Self {
+            text: text.into(),
+            emphasized: true,
+        }
+    }
+}
+
+/// The content of a popup: lines of text drawn top to bottom in a monospace font, where each line is
+/// made up of spans.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct PopupContent {
+    pub lines: Vec<Vec<PopupSpan>>,
+}
+
+impl PopupContent {
+    /// Creates content with a single plain span per line of the text.
+    pub fn from_text(text: &str) -> Self {
+        Self {
+            lines: text
+                .lines()
+                .map(|line| vec![PopupSpan::plain(line)])
+                .collect(),
+        }
+    }
+
+    /// Returns the text of each line, without emphasis.
+    pub fn text_lines(&self) -> Vec<String> {
+        self.lines
+            .iter()
+            .map(|spans| spans.iter().map(|span| span.text.as_str()).collect())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn from_text_works() {
+        let content = PopupContent::from_text("first\nsecond");
+        assert_eq!(
+            content.lines,
+            vec![
+                vec![PopupSpan::plain("first")],
+                vec![PopupSpan::plain("second")]
+            ]
+        );
+        assert_eq!(content.text_lines(), vec!["first", "second"]);
+    }
+}
@@ -0,0 +1,309 @@
+use std::{
+    collections::HashMap,
+    sync::{atomic::Ordering, Once, RwLock},
+};
+
+use lazy_static::lazy_static;
+use winbar::{
+    popup::PopupContent,
+    styles::{StyleOptions, Styles, TextAlign},
+    util::rect::Rect,
+};
+use windows::{
+    core::w,
+    Win32::{
+        Foundation::{COLORREF, HWND, LPARAM, LRESULT, POINT, RECT, WPARAM},
+        Graphics::Gdi::{
+            BeginPaint, ClientToScreen, EndPaint, GetDC, ReleaseDC, SetBkColor, SetTextColor, HDC,
+            PAINTSTRUCT,
+        },
+        System::LibraryLoader::GetModuleHandleW,
+        UI::WindowsAndMessaging::{
+            CreateWindowExW, DefWindowProcW, DestroyWindow, GetClientRect, GetSystemMetrics,
+            RegisterClassW, SetWindowPos, ShowWindow, HWND_TOPMOST, SM_CXSCREEN, SM_CYSCREEN,
+            SWP_NOACTIVATE, SW_SHOWNOACTIVATE, WM_PAINT, WNDCLASSW, WS_BORDER, WS_EX_TOOLWINDOW,
+            WS_EX_TOPMOST, WS_POPUP,
+        },
+    },
+};
+
+use crate::{
+    container::SelectedStyles, DEFAULT_BG_COLOR, DEFAULT_FG_COLOR, DEFAULT_FONT_SIZE, POPUP_FONT,
+};
+
+/// The padding, in pixels, between the edge of a popup and its content.
+const PADDING: i32 = 8;
+
+/// The space, in pixels, between the lines of a popup.
+const LINE_SPACING: i32 = 2;
+
+lazy_static! {
+    // HWND -> content of the popup
+    // we use isize since HWND is not hashable
+    static ref POPUP_CONTENTS: RwLock<HashMap<isize, PopupContent>> = RwLock::new(HashMap::new());
+}
+
+static REGISTER_CLASS: Once = Once::new();
+
+/// A request to toggle the popup panel of a component.
+#[derive(Debug)]
+pub struct PopupRequest {
+    /// The index of the component.
+    pub index: usize,
+    /// The location of the component in the status bar, which the popup is anchored to.
+    pub anchor: Rect,
+    pub content: PopupContent,
+}
+
+/// A popup panel anchored to a component. The window is destroyed when this is dropped.
+pub struct PopupWindow {
+    hwnd: HWND,
+    index: usize,
+}
+
+impl PopupWindow {
+    /// Opens a popup below the anchor, or above it if there is no room below.
+    pub fn open(parent: HWND, request: PopupRequest) -> Self {
+        let class_name = w!("winbar_popup");
+        unsafe {
+            let h_inst = GetModuleHandleW(None).unwrap_or_default();
+            REGISTER_CLASS.call_once(|| {
+                let wc = WNDCLASSW {
+                    lpfnWndProc: Some(popup_proc),
+                    hInstance: h_inst.into(),
+                    lpszClassName: class_name,
+                    ..Default::default()
+                };
+                RegisterClassW(&wc);
+            });
+
+            let (width, height) = measure(parent, &request.content);
+            let mut top_left = POINT {
+                x: request.anchor.x,
+                y: request.anchor.y,
+            };
+            ClientToScreen(parent, &mut top_left);
+            let anchor = Rect {
+                x: top_left.x,
+                y: top_left.y,
+                ..request.anchor
+            };
+            let screen = (GetSystemMetrics(SM_CXSCREEN), GetSystemMetrics(SM_CYSCREEN));
+            let (x, y) = position(&anchor, (width, height), screen);
+
+            let hwnd = CreateWindowExW(
+                WS_EX_TOOLWINDOW | WS_EX_TOPMOST,
+                class_name,
+                w!("winbar popup"),
+                WS_POPUP | WS_BORDER,
+                x,
+                y,
+                width,
+                height,
+                parent,
+                None,
+                h_inst,
+                None,
+            );
+
+            {
+                let mut contents = POPUP_CONTENTS.write().unwrap();
+                contents.insert(hwnd.0, request.content);
+            }
+
+            let _ = SetWindowPos(hwnd, HWND_TOPMOST, x, y, width, height, SWP_NOACTIVATE);
+            let _ = ShowWindow(hwnd, SW_SHOWNOACTIVATE);
+
+            Self {
+                hwnd,
+                index: request.index,
+            }
+        }
+    }
+
+    pub fn hwnd(&self) -> HWND {
+        self.hwnd
+    }
+
+    /// The index of the component the popup belongs to.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+}
+
+impl Drop for PopupWindow {
+    fn drop(&mut self) {
+        {
+            let mut contents = POPUP_CONTENTS.write().unwrap();
+            contents.remove(&self.hwnd.0);
+        }
+
+        unsafe {
+            if let Err(e) = DestroyWindow(self.hwnd) {
+                tracing::error!("Could not destroy popup window: {}", e);
+            }
+        }
+    }
+}
+
+/// Returns the top left corner of a popup of the given size, anchored below the anchor, or above
+/// it if there is no room below. The popup is kept within the horizontal bounds of the screen.
+pub fn position(
+    anchor: &Rect,
+    (width, height): (i32, i32),
+    (screen_width, screen_height): (i32, i32),
+) -> (i32, i32) {
+    let x = anchor.x.min(screen_width - width).max(0);
+    let y = if anchor.y2() + height > screen_height {
+        anchor.y - height
+    } else {
+        anchor.y2()
+    };
+
+    (x, y)
+}
+
+/// The styles the content of popups is drawn with.
+fn content_styles() -> StyleOptions {
+    StyleOptions {
+        font: Some(POPUP_FONT.lock().unwrap().to_string()),
+        text_align: TextAlign::Left,
+        ..Default::default()
+    }
+}
+
+fn line_height() -> i32 {
+    DEFAULT_FONT_SIZE.load(Ordering::SeqCst) + LINE_SPACING
+}
+
+/// Measures the size of the popup window needed to display the content.
+fn measure(parent: HWND, content: &PopupContent) -> (i32, i32) {
+    let styles = content_styles();
+    let width = unsafe {
+        let hdc = GetDC(parent);
+        let width = {
+            let _selected = SelectedStyles::select(hdc, &styles);
+            content
+                .text_lines()
+                .iter()
+                .map(|line| Styles::text_width(hdc, line))
+                .max()
+                .unwrap_or(0)
+        };
+        ReleaseDC(parent, hdc);
+        width
+    };
+
+    (
+        width + PADDING * 2,
+        line_height() * content.lines.len() as i32 + PADDING * 2,
+    )
+}
+
+fn paint(hdc: HDC, rect: &Rect, content: &PopupContent) {
+    let styles = content_styles();
+    let _selected = SelectedStyles::select(hdc, &styles);
+    Styles::draw_rect(hdc, rect, &styles);
+
+    let bg_color = DEFAULT_BG_COLOR.lock().unwrap().bgr();
+    let fg_color = DEFAULT_FG_COLOR.lock().unwrap().bgr();
+    let line_height = line_height();
+
+    for (line_index, spans) in content.lines.iter().enumerate() {
+        let mut x = rect.x + PADDING;
+        let y = rect.y + PADDING + line_height * line_index as i32;
+        for span in spans {
+            let width = Styles::text_width(hdc, &span.text);
+            let (bg, fg) = if span.emphasized {
+                (fg_color, bg_color)
+            } else {
+                (bg_color, fg_color)
+            };
+
+            unsafe {
+                SetBkColor(hdc, COLORREF(bg));
+                SetTextColor(hdc, COLORREF(fg));
+            }
+            Styles::draw_text(
+                hdc,
+                &Rect {
+                    x,
+                    y,
+                    width,
+                    height: line_height,
+                },
+                &span.text,
+                &styles,
+            );
+
+            x += width;
+        }
+    }
+}
+
+extern "system" fn popup_proc(hwnd: HWND, msg: u32, wparam: WPARAM, lparam: LPARAM) -> LRESULT {
+    unsafe {
+        match msg {
+            WM_PAINT => {
+                let mut ps = PAINTSTRUCT::default();
+                let hdc = BeginPaint(hwnd, &mut ps);
+
+                let contents = POPUP_CONTENTS.read().unwrap();
+                let mut client = RECT::default();
+                if let (Some(content), Ok(_)) =
+                    (contents.get(&hwnd.0), GetClientRect(hwnd, &mut client))
+                {
+                    let rect = Rect {
+                        x: 0,
+                        y: 0,
+                        width: client.right,
+                        height: client.bottom,
+                    };
+                    paint(hdc, &rect, content);
+                }
+
+                EndPaint(hwnd, &ps);
+                LRESULT(0)
+            }
+            _ => DefWindowProcW(hwnd, msg, wparam, lparam),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn position_prefers_below_anchor() {
+        let anchor = Rect {
+            x: 100,
+            y: 0,
+            width: 50,
+            height: 25,
+        };
+        assert_eq!(position(&anchor, (200, 150), (1920, 1080)), (100, 25));
+    }
+
+    #[test]
+    fn position_flips_above_anchor_at_bottom_of_screen() {
+        let anchor = Rect {
+            x: 100,
+            y: 1055,
+            width: 50,
+            height: 25,
+        };
+        assert_eq!(position(&anchor, (200, 150), (1920, 1080)), (100, 905));
+    }
+
+    #[test]
+    fn position_stays_within_screen() {
+        let anchor = Rect {
+            x: 1900,
+            y: 0,
+            width: 20,
+            height: 25,
+        };
+        assert_eq!(position(&anchor, (200, 150), (1920, 1080)), (1720, 25));
+    }
+}
@@ -16,12 +16,28 @@
 use serde::{Deserialize, Serialize};
 
 /// A message sent to the server by the client.
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum ServerMessage {
     Shutdown,
     UpdateWindow,
     ShowWindow,
     HideWindow,
+    /// Sets the explicit state of the component with the given id, used to select conditional
+    /// styles. A state of `None` clears the state.
+    SetComponentState {
+        id: String,
+        state: Option<String>,
+    },
+    /// Restarts the component with the given id.
+    RestartComponent {
+        id: String,
+    },
+    /// Requests the status of all components.
+    GetComponentStatus,
+    /// Requests the tooltip of the component with the given id.
+    GetTooltip {
+        id: String,
+    },
 }
 
 /// A server-bound payload.
@@ -45,6 +61,34 @@ pub enum ClientMessage {
     Success,
     /// Denotes that the sent message was not successful
     Error(String),
+    /// The status of all components, in the order they are defined in the config
+    ComponentStatus(Vec<ComponentStatusReport>),
+    /// The tooltip of a component, if it has one
+    Tooltip(Option<String>),
+}
+
+/// The status of a component's task.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum ComponentStatus {
+    /// The component has not been started yet.
+    #[default]
+    Pending,
+    Running,
+    /// The component panicked and is waiting to be restarted.
+    Failed,
+    Stopped,
+}
+
+/// A report of a component's status.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ComponentStatusReport {
+    /// The id of the component, if it has one.
+    pub id: Option<String>,
+    pub status: ComponentStatus,
+    /// The number of times the component has failed.
+    pub failures: u32,
+    /// The message of the most recent failure.
+    pub last_error: Option<String>,
 }
 
 /// A client-bound payload.
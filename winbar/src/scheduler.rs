@@ -0,0 +1,173 @@
+//! Scheduling of component updates and repaints.
+//!
+//! Components tick at their own intervals with a [`Ticker`], optionally aligned to the wall clock,
+//! and request repaints of their own region with [`WinbarContext::request_redraw`]. The requests
+//! are coalesced by the window's [`FrameScheduler`], such that the window is repainted at most once
+//! per frame no matter how many components update at once.
+//!
+//! [`WinbarContext::request_redraw`]: winbar::WinbarContext::request_redraw
+use std::{
+    collections::BTreeSet,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use tokio::time;
+
+/// The shortest time between two repaints of the window.
+pub const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// The part of the window waiting to be repainted.
+#[derive(Debug, PartialEq)]
+pub enum Dirty {
+    Window,
+    /// The components at the given indices.
+    Components(BTreeSet<usize>),
+}
+
+/// Coalesces repaint requests into at most one repaint per frame.
+#[derive(Debug, Default)]
+pub struct FrameScheduler {
+    dirty: Option<Dirty>,
+    last_repaint: Option<Instant>,
+}
+
+impl FrameScheduler {
+    /// Requests a repaint of the whole window.
+    pub fn request(&mut self) {
+        self.dirty = Some(Dirty::Window);
+    }
+
+    /// Requests a repaint of the component at the given index.
+    pub fn request_component(&mut self, index: usize) {
+        match &mut self.dirty {
+            Some(Dirty::Window) => {}
+            Some(Dirty::Components(indices)) => {
+                indices.insert(index);
+            }
+            None => self.dirty = Some(Dirty::Components(BTreeSet::from([index]))),
+        }
+    }
+
+    /// The part of the window that should be repainted now, if any, in which case the pending
+    /// requests are considered handled.
+    pub fn poll(&mut self, now: Instant) -> Option<Dirty> {
+        let frame_elapsed = self
+            .last_repaint
+            .is_none_or(|last| now.duration_since(last) >= FRAME_INTERVAL);
+        if self.dirty.is_none() || !frame_elapsed {
+            return None;
+        }
+
+        self.last_repaint = Some(now);
+        self.dirty.take()
+    }
+}
+
+/// Ticks at a fixed period, where the first tick completes immediately.
+pub struct Ticker {
+    period: Duration,
+    /// Whether ticks happen at multiples of the period since the Unix epoch, e.g., at the start of
+    /// every minute for a period of a minute, rather than a period after the previous tick.
+    aligned: bool,
+    first: bool,
+}
+
+impl Ticker {
+    pub fn new(period: Duration) -> Self {
+        Self {
+            period: period.max(Duration::from_millis(1)),
+            aligned: false,
+            first: true,
+        }
+    }
+
+    /// A ticker aligned to the wall clock.
+    pub fn aligned(period: Duration) -> Self {
+        Self {
+            aligned: true,
+            ..Self::new(period)
+        }
+    }
+
+    pub async fn tick(&mut self) {
+        if std::mem::take(&mut self.first) {
+            return;
+        }
+
+        let delay = if self.aligned {
+            let since_epoch = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .unwrap_or_default();
+            until_aligned(since_epoch, self.period)
+        } else {
+            self.period
+        };
+        time::sleep(delay).await;
+    }
+}
+
+/// The time until the next multiple of the period since the Unix epoch. Exactly on a multiple, this
+/// is a full period, so that the same boundary isn't ticked twice.
+pub fn until_aligned(since_epoch: Duration, period: Duration) -> Duration {
+    let period = period.as_millis().max(1);
+    let elapsed = since_epoch.as_millis() % period;
+    Duration::from_millis((period - elapsed) as u64)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn frame_scheduler_coalesces_requests() {
+        let mut scheduler = FrameScheduler::default();
+        let start = Instant::now();
+        assert_eq!(scheduler.poll(start), None);
+
+        scheduler.request();
+        scheduler.request();
+        assert_eq!(scheduler.poll(start), Some(Dirty::Window));
+        assert_eq!(scheduler.poll(start), None);
+
+        // requests within a frame of the last repaint wait for the frame to end
+        scheduler.request();
+        assert_eq!(scheduler.poll(start + FRAME_INTERVAL / 2), None);
+        assert_eq!(scheduler.poll(start + FRAME_INTERVAL), Some(Dirty::Window));
+    }
+
+    #[test]
+    fn frame_scheduler_collects_dirty_components() {
+        let mut scheduler = FrameScheduler::default();
+        let start = Instant::now();
+
+        scheduler.request_component(2);
+        scheduler.request_component(0);
+        scheduler.request_component(2);
+        assert_eq!(
+            scheduler.poll(start),
+            Some(Dirty::Components(BTreeSet::from([0, 2])))
+        );
+
+        // a request of the whole window covers all components
+        scheduler.request_component(1);
+        scheduler.request();
+        scheduler.request_component(3);
+        assert_eq!(scheduler.poll(start + FRAME_INTERVAL), Some(Dirty::Window));
+    }
+
+    #[test]
+    fn until_aligned_waits_for_next_boundary() {
+        let second = Duration::from_secs(1);
+        let minute = Duration::from_secs(60);
+
+        assert_eq!(
+            until_aligned(Duration::from_millis(10_250), second),
+            Duration::from_millis(750)
+        );
+        assert_eq!(
+            until_aligned(Duration::from_secs(125), minute),
+            Duration::from_secs(55)
+        );
+        assert_eq!(until_aligned(Duration::from_secs(120), minute), minute);
+    }
+}
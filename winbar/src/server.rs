@@ -1,4 +1,4 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use getset::Getters;
 use tokio::{
     io::{AsyncReadExt, AsyncWriteExt},
@@ -6,10 +6,12 @@ use tokio::{
     task::JoinHandle,
 };
 use winbar::{
-    protocol::{ServerMessage, WinbarClientPayload, WinbarServerPayload},
+    protocol::{ClientMessage, ServerMessage, WinbarClientPayload, WinbarServerPayload},
     WinbarAction, WinbarContext,
 };
 
+use crate::COMPONENT_MANAGER;
+
 #[derive(Getters)]
 pub struct Connection {
     #[getset(get = "pub")]
@@ -57,10 +59,10 @@ impl WinbarServer {
                         }
                     };
                     match Self::process(&ctx, &payload, &stream) {
-                        Ok(_) => {
+                        Ok(message) => {
                             let client_payload = WinbarClientPayload {
                                 id: payload.id,
-                                message: winbar::protocol::ClientMessage::Success,
+                                message,
                             };
                             Self::serialize_and_send(&mut stream, &client_payload).await;
                         }
@@ -68,7 +70,7 @@ impl WinbarServer {
                             tracing::error!("Error while processing server payload: {}", e);
                             let client_payload = WinbarClientPayload {
                                 id: payload.id,
-                                message: winbar::protocol::ClientMessage::Error(e.to_string()),
+                                message: ClientMessage::Error(e.to_string()),
                             };
                             Self::serialize_and_send(&mut stream, &client_payload).await;
                         }
@@ -91,8 +93,14 @@ impl WinbarServer {
         ctx: &WinbarContext,
         payload: &WinbarServerPayload,
         _stream: &TcpStream,
-    ) -> Result<()> {
-        match payload.message {
+    ) -> Result<ClientMessage> {
+        Self::handle_message(ctx, &payload.message)
+    }
+
+    /// Handles a server-bound message, returning the message to respond with. This is also used to
+    /// handle messages bound to component actions.
+    pub fn handle_message(ctx: &WinbarContext, message: &ServerMessage) -> Result<ClientMessage> {
+        match message {
             ServerMessage::Shutdown => {
                 ctx.sender().send(WinbarAction::Shutdown)?;
             }
@@ -105,9 +113,35 @@ impl WinbarServer {
             ServerMessage::HideWindow => {
                 ctx.sender().send(WinbarAction::HideWindow)?;
             }
+            ServerMessage::SetComponentState { id, state } => {
+                let index = COMPONENT_MANAGER
+                    .lock()
+                    .map_err(|e| anyhow!("Could not obtain component manager lock: {}", e))?
+                    .set_state(id, state.clone())?;
+                // the window is only laid out again if the state changed the component's width
+                ctx.sender().send(WinbarAction::RedrawComponent(index))?;
+            }
+            ServerMessage::RestartComponent { id } => {
+                let mut manager = COMPONENT_MANAGER
+                    .lock()
+                    .map_err(|e| anyhow!("Could not obtain component manager lock: {}", e))?;
+                manager.restart(id)?;
+            }
+            ServerMessage::GetComponentStatus => {
+                let manager = COMPONENT_MANAGER
+                    .lock()
+                    .map_err(|e| anyhow!("Could not obtain component manager lock: {}", e))?;
+                return Ok(ClientMessage::ComponentStatus(manager.status_reports()));
+            }
+            ServerMessage::GetTooltip { id } => {
+                let mut manager = COMPONENT_MANAGER
+                    .lock()
+                    .map_err(|e| anyhow!("Could not obtain component manager lock: {}", e))?;
+                return Ok(ClientMessage::Tooltip(manager.find_tooltip(id)?));
+            }
         }
 
-        Ok(())
+        Ok(ClientMessage::Success)
     }
 
     async fn serialize_and_send(stream: &mut TcpStream, payload: &WinbarClientPayload) {
@@ -1,24 +1,191 @@
+use std::sync::{Arc, RwLock};
+
+use lazy_static::lazy_static;
+use regex::Regex;
 use windows::{
     core::HSTRING,
     Win32::{
-        Foundation::COLORREF,
+        Foundation::{COLORREF, RECT, SIZE},
         Graphics::Gdi::{
-            CreateFontW, CreatePen, CreateSolidBrush, RoundRect, ANSI_CHARSET, CLIP_DEFAULT_PRECIS,
-            DEFAULT_PITCH, FF_DONTCARE, FW_DONTCARE, HBRUSH, HDC, HFONT, HPEN, OUT_TT_PRECIS,
-            PEN_STYLE, PROOF_QUALITY,
+            ArcTo, BeginPath, CreateFontW, CreatePen, CreateSolidBrush, DrawTextW, EndPath,
+            ExtCreatePen, FillPath, FillRect, GetStockObject, GetTextColor, GetTextExtentPoint32W,
+            LineTo, MoveToEx, SelectObject, SetArcDirection, StrokePath, AD_CLOCKWISE, BS_SOLID,
+            CLIP_DEFAULT_PRECIS, DEFAULT_CHARSET, DEFAULT_PITCH, DRAW_TEXT_FORMAT, DT_CENTER,
+            DT_LEFT, DT_NOPREFIX, DT_RIGHT, DT_SINGLELINE, DT_VCENTER, FF_DONTCARE, FW_DONTCARE,
+            HBRUSH, HDC, HFONT, HPEN, LOGBRUSH, NULL_PEN, OUT_TT_PRECIS, PEN_STYLE, PROOF_QUALITY,
+            PS_ENDCAP_FLAT, PS_GEOMETRIC, PS_JOIN_MITER, PS_SOLID,
         },
     },
 };
 
-use crate::{color::Color, util::rect::Rect};
+use crate::{
+    color::Color,
+    util::{
+        gdi_cache,
+        rect::Rect,
+        truncate::{truncate, Truncation},
+    },
+};
 
 #[derive(Clone, Default)]
 pub enum BorderStyle {
     #[default]
     Square,
-    Rounded {
-        radius: i32,
-    },
+    /// Rounded corners, where the radius is the diameter of the corners' ellipses as with GDI's
+    /// `RoundRect`, i.e., corners are rounded by half of it.
+    Rounded { radius: i32 },
+    /// Rounded corners with an individual radius per corner.
+    Corners(CornerRadii),
+}
+
+impl BorderStyle {
+    /// Returns the radius of each corner.
+    pub fn radii(&self) -> CornerRadii {
+        match self {
+            BorderStyle::Square => CornerRadii::default(),
+            BorderStyle::Rounded { radius } => CornerRadii::uniform(*radius / 2),
+            BorderStyle::Corners(radii) => *radii,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CornerRadii {
+    pub top_left: i32,
+    pub top_right: i32,
+    pub bottom_right: i32,
+    pub bottom_left: i32,
+}
+
+impl CornerRadii {
+    pub fn uniform(radius: i32) -> Self {
+        Self {
+            top_left: radius,
+            top_right: radius,
+            bottom_right: radius,
+            bottom_left: radius,
+        }
+    }
+
+    /// Returns the radii clamped such that no corner is larger than half the width or height of
+    /// the given rect, and no radius is negative.
+    pub fn clamp_to(&self, rect: &Rect) -> Self {
+        let max = (rect.width.min(rect.height) / 2).max(0);
+        Self {
+            top_left: self.top_left.clamp(0, max),
+            top_right: self.top_right.clamp(0, max),
+            bottom_right: self.bottom_right.clamp(0, max),
+            bottom_left: self.bottom_left.clamp(0, max),
+        }
+    }
+}
+
+/// The sides of a rect that a border is drawn on.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BorderSides {
+    pub top: bool,
+    pub right: bool,
+    pub bottom: bool,
+    pub left: bool,
+}
+
+impl BorderSides {
+    pub fn all() -> Self {
+        Self {
+            top: true,
+            right: true,
+            bottom: true,
+            left: true,
+        }
+    }
+
+    pub fn none() -> Self {
+        Self {
+            top: false,
+            right: false,
+            bottom: false,
+            left: false,
+        }
+    }
+
+    pub fn is_all(&self) -> bool {
+        self.top && self.right && self.bottom && self.left
+    }
+
+    pub fn is_none(&self) -> bool {
+        !(self.top || self.right || self.bottom || self.left)
+    }
+
+    /// Returns the rects covered by a border of the given width on each enabled side. Each side is
+    /// shortened by the radius of the corners it touches so it doesn't stick out of rounded
+    /// corners.
+    pub fn side_rects(&self, rect: &Rect, width: i32, radii: &CornerRadii) -> Vec<Rect> {
+        let mut rects = Vec::new();
+        if width <= 0 {
+            return rects;
+        }
+
+        let width = width.min(rect.width).min(rect.height);
+        if self.top {
+            rects.push(Rect {
+                x: rect.x + radii.top_left,
+                y: rect.y,
+                width: rect.width - radii.top_left - radii.top_right,
+                height: width,
+            });
+        }
+        if self.right {
+            rects.push(Rect {
+                x: rect.x2() - width,
+                y: rect.y + radii.top_right,
+                width,
+                height: rect.height - radii.top_right - radii.bottom_right,
+            });
+        }
+        if self.bottom {
+            rects.push(Rect {
+                x: rect.x + radii.bottom_left,
+                y: rect.y2() - width,
+                width: rect.width - radii.bottom_left - radii.bottom_right,
+                height: width,
+            });
+        }
+        if self.left {
+            rects.push(Rect {
+                x: rect.x,
+                y: rect.y + radii.top_left,
+                width,
+                height: rect.height - radii.top_left - radii.bottom_left,
+            });
+        }
+
+        rects
+    }
+}
+
+impl Default for BorderSides {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+/// The horizontal alignment of text within a component.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum TextAlign {
+    Left,
+    #[default]
+    Center,
+    Right,
+}
+
+impl TextAlign {
+    fn draw_text_format(&self) -> DRAW_TEXT_FORMAT {
+        match self {
+            TextAlign::Left => DT_LEFT,
+            TextAlign::Center => DT_CENTER,
+            TextAlign::Right => DT_RIGHT,
+        }
+    }
 }
 
 #[derive(Clone, Default)]
@@ -26,9 +193,159 @@ pub struct StyleOptions {
     pub bg_color: Option<Color>,
     pub fg_color: Option<Color>,
     pub border_style: BorderStyle,
+    /// The width of the border in pixels. A width of 0 disables the border.
+    pub border_width: i32,
+    /// The color of the border. Defaults to the foreground color if not set.
+    pub border_color: Option<Color>,
+    pub border_sides: BorderSides,
     pub font: Option<String>,
     pub font_size: Option<i32>,
+    /// The weight of the font, from 100 (thin) to 900 (black). The font's default weight is used
+    /// if not set.
+    pub font_weight: Option<i32>,
+    pub italic: bool,
+    pub underline: bool,
+    pub strikethrough: bool,
+    pub text_align: TextAlign,
+    /// Extra spacing, in pixels, added between characters.
+    pub letter_spacing: i32,
     pub padding_x: i32,
+    /// The minimum width of the component, including padding.
+    pub min_width: Option<i32>,
+    /// The maximum width of the component, including padding. Text that doesn't fit is truncated.
+    pub max_width: Option<i32>,
+    /// The exact width of the component, including padding. Takes precedence over the minimum and
+    /// maximum widths.
+    pub fixed_width: Option<i32>,
+    /// Where text is truncated when it doesn't fit within the component.
+    pub truncation: Truncation,
+}
+
+impl StyleOptions {
+    /// Applies the fixed, minimum and maximum widths to the given width.
+    pub fn constrain_width(&self, width: i32) -> i32 {
+        if let Some(fixed) = self.fixed_width {
+            return fixed;
+        }
+
+        let width = match self.max_width {
+            Some(max) => width.min(max),
+            None => width,
+        };
+        match self.min_width {
+            Some(min) => width.max(min),
+            None => width,
+        }
+    }
+}
+
+/// A condition on the value or state of a component, used to select styles at draw time.
+#[derive(Clone, Debug)]
+pub enum StyleCondition {
+    /// The first number in the value is greater than the given number.
+    Above(f64),
+    /// The first number in the value is less than the given number.
+    Below(f64),
+    /// The value is equal to the given string.
+    Equals(String),
+    /// The value matches the given regex.
+    Matches(Regex),
+    /// The component's explicit state (e.g., set through the protocol) is equal to the given
+    /// string.
+    State(String),
+}
+
+impl StyleCondition {
+    pub fn matches(&self, value: &str, state: Option<&str>) -> bool {
+        match self {
+            StyleCondition::Above(threshold) => {
+                first_number(value).is_some_and(|number| number > *threshold)
+            }
+            StyleCondition::Below(threshold) => {
+                first_number(value).is_some_and(|number| number < *threshold)
+            }
+            StyleCondition::Equals(expected) => value == expected,
+            StyleCondition::Matches(re) => re.is_match(value),
+            StyleCondition::State(expected) => state == Some(expected.as_str()),
+        }
+    }
+}
+
+/// Returns the first number found in the string, if any (e.g., 93.5 for "CPU 93.5%").
+fn first_number(value: &str) -> Option<f64> {
+    lazy_static! {
+        static ref NUMBER: Regex = Regex::new("-?[0-9]+(\\.[0-9]+)?").unwrap();
+    }
+
+    NUMBER
+        .find(value)
+        .and_then(|number| number.as_str().parse().ok())
+}
+
+/// Styles that apply when a condition matches.
+#[derive(Clone)]
+pub struct StyleRule {
+    pub condition: StyleCondition,
+    pub styles: Arc<StyleOptions>,
+}
+
+/// The styles of a component: base styles along with rules that override them depending on the
+/// component's current value and state.
+pub struct ComponentStyles {
+    base: Arc<StyleOptions>,
+    rules: Vec<StyleRule>,
+    state: RwLock<Option<String>>,
+}
+
+impl ComponentStyles {
+    pub fn new(base: StyleOptions, rules: Vec<StyleRule>) -> Self {
+        Self {
+            base: Arc::new(base),
+            rules,
+            state: RwLock::new(None),
+        }
+    }
+
+    /// The styles used when no rule matches.
+    pub fn base(&self) -> Arc<StyleOptions> {
+        self.base.clone()
+    }
+
+    /// Sets the explicit state of the component, matched by `StyleCondition::State`.
+    pub fn set_state(&self, state: Option<String>) {
+        match self.state.write() {
+            Ok(mut current) => *current = state,
+            Err(e) => tracing::error!("Error obtaining component state lock: {}", e),
+        }
+    }
+
+    pub fn state(&self) -> Option<String> {
+        self.state.read().ok().and_then(|state| state.clone())
+    }
+
+    /// Returns the styles of the first rule matching the given value and the current state, or the
+    /// base styles if no rule matches.
+    pub fn resolve(&self, value: &str) -> Arc<StyleOptions> {
+        self.resolve_with_state(value, None)
+    }
+
+    /// Like `resolve`, but matches the given state while no explicit state is set (e.g., a state
+    /// derived from the component's value).
+    pub fn resolve_with_state(&self, value: &str, state: Option<&str>) -> Arc<StyleOptions> {
+        let explicit = self.state();
+        let state = explicit.as_deref().or(state);
+        self.rules
+            .iter()
+            .find(|rule| rule.condition.matches(value, state))
+            .map(|rule| rule.styles.clone())
+            .unwrap_or_else(|| self.base.clone())
+    }
+}
+
+impl From<StyleOptions> for ComponentStyles {
+    fn from(value: StyleOptions) -> Self {
+        Self::new(value, Vec::new())
+    }
 }
 
 pub struct Styles {}
@@ -42,6 +359,26 @@ impl Styles {
         unsafe { CreatePen(style, 0, COLORREF(color)) }
     }
 
+    /// Creates a new solid pen with a flat end cap and the given width.
+    ///
+    /// Note that it is the caller's responsibility to call SelectObject to use the object, and
+    /// DeleteObject to cleanup the resource.
+    pub fn geometric_pen(color: u32, width: i32) -> HPEN {
+        let brush = LOGBRUSH {
+            lbStyle: BS_SOLID,
+            lbColor: COLORREF(color),
+            lbHatch: 0,
+        };
+        unsafe {
+            ExtCreatePen(
+                PS_GEOMETRIC | PS_SOLID | PS_ENDCAP_FLAT | PS_JOIN_MITER,
+                width.max(1) as u32,
+                &brush,
+                None,
+            )
+        }
+    }
+
     /// Creates a new solid brush.
     ///
     /// Note that it is the caller's responsibility to call SelectObject to use the object, and
@@ -50,22 +387,28 @@ impl Styles {
         unsafe { CreateSolidBrush(COLORREF(color)) }
     }
 
-    /// Creates a new font.
+    /// Creates a new font. The weight, italic, underline and strikethrough options are taken from
+    /// the given styles.
     ///
     /// Note that it is the caller's responsibility to call SelectObject to use the object, and
     /// DeleteObject to cleanup the resource.
-    pub fn font(size: i32, name: &str) -> HFONT {
+    pub fn font(size: i32, name: &str, styles: &StyleOptions) -> HFONT {
+        let weight = match styles.font_weight {
+            Some(weight) => weight.clamp(1, 1000),
+            None => FW_DONTCARE.0 as i32,
+        };
+
         unsafe {
             CreateFontW(
                 size,
                 0,
                 0,
                 0,
-                FW_DONTCARE.0 as i32,
-                0,
-                0,
-                0,
-                ANSI_CHARSET.0.into(),
+                weight,
+                styles.italic.into(),
+                styles.underline.into(),
+                styles.strikethrough.into(),
+                DEFAULT_CHARSET.0.into(),
                 OUT_TT_PRECIS.0.into(),
                 CLIP_DEFAULT_PRECIS.0.into(),
                 PROOF_QUALITY.0.into(),
@@ -75,16 +418,346 @@ impl Styles {
         }
     }
 
-    pub fn draw_rect(hdc: HDC, rect: &Rect, border: &BorderStyle) {
+    /// Returns the width of the given text using the font and character spacing currently selected
+    /// into the device context.
+    pub fn text_width(hdc: HDC, text: &str) -> i32 {
+        let text = text.encode_utf16().collect::<Vec<u16>>();
+        let mut size = SIZE::default();
+        unsafe {
+            GetTextExtentPoint32W(hdc, &text, &mut size);
+        }
+
+        size.cx
+    }
+
+    /// Returns the width of a component displaying the given text, i.e., the width of the text
+    /// with padding, constrained by the fixed, minimum and maximum widths in the styles.
+    pub fn text_component_width(hdc: HDC, text: &str, styles: &StyleOptions) -> i32 {
+        styles.constrain_width(Self::text_width(hdc, text) + styles.padding_x * 2)
+    }
+
+    /// Draws single line text within the given rect, vertically centered and horizontally aligned
+    /// according to the styles' text alignment. The horizontal padding in the styles is applied to
+    /// both sides of the rect, and text that doesn't fit is truncated.
+    pub fn draw_text(hdc: HDC, rect: &Rect, text: &str, styles: &StyleOptions) {
+        let text_rect = Rect {
+            x: rect.x + styles.padding_x,
+            y: rect.y,
+            width: rect.width - styles.padding_x * 2,
+            height: rect.height,
+        };
+        let text = truncate(text, text_rect.width, styles.truncation, |s| {
+            Self::text_width(hdc, s)
+        });
+
+        let mut text = text.encode_utf16().collect::<Vec<u16>>();
+        let mut text_rect: RECT = text_rect.into();
+
+        unsafe {
+            DrawTextW(
+                hdc,
+                &mut text,
+                &mut text_rect,
+                DT_SINGLELINE | DT_VCENTER | DT_NOPREFIX | styles.text_align.draw_text_format(),
+            );
+        }
+    }
+
+    /// Draws the background and border of a component.
+    ///
+    /// The background is filled with the currently selected brush. The border is drawn with the
+    /// border color in the given styles, or the current text color if no border color is set.
+    pub fn draw_rect(hdc: HDC, rect: &Rect, styles: &StyleOptions) {
+        let radii = styles.border_style.radii().clamp_to(rect);
+
         unsafe {
-            match border {
-                BorderStyle::Square => {
-                    RoundRect(hdc, rect.x, rect.y, rect.x2(), rect.y2(), 0, 0);
-                }
-                BorderStyle::Rounded { radius } => {
-                    RoundRect(hdc, rect.x, rect.y, rect.x2(), rect.y2(), *radius, *radius);
-                }
+            // fill without an outline; the border is drawn separately below
+            let old_pen = SelectObject(hdc, GetStockObject(NULL_PEN));
+            Self::rounded_rect_path(hdc, rect, &radii);
+            FillPath(hdc);
+            SelectObject(hdc, old_pen);
+        }
+
+        if styles.border_width <= 0 || styles.border_sides.is_none() {
+            return;
+        }
+
+        let border_color = match &styles.border_color {
+            Some(color) => color.bgr(),
+            None => unsafe { GetTextColor(hdc).0 },
+        };
+
+        if styles.border_sides.is_all() {
+            // stroke along a path inset by half the border width so the whole border stays within
+            // the rect
+            let half = styles.border_width / 2;
+            let inset = Rect {
+                x: rect.x + half,
+                y: rect.y + half,
+                width: rect.width - styles.border_width,
+                height: rect.height - styles.border_width,
+            };
+            let inset_radii = CornerRadii {
+                top_left: radii.top_left - half,
+                top_right: radii.top_right - half,
+                bottom_right: radii.bottom_right - half,
+                bottom_left: radii.bottom_left - half,
             }
+            .clamp_to(&inset);
+
+            unsafe {
+                let pen = gdi_cache::pen(border_color, styles.border_width);
+                let old_pen = SelectObject(hdc, pen);
+                Self::rounded_rect_path(hdc, &inset, &inset_radii);
+                StrokePath(hdc);
+                SelectObject(hdc, old_pen);
+            }
+        } else {
+            let brush = gdi_cache::brush(border_color);
+            styles
+                .border_sides
+                .side_rects(rect, styles.border_width, &radii)
+                .iter()
+                .for_each(|side| unsafe {
+                    let side: RECT = (*side).into();
+                    FillRect(hdc, &side, brush);
+                });
         }
     }
+
+    /// Begins and ends a path in the shape of a rect with the given corner radii. The path is
+    /// available to be filled or stroked afterwards.
+    fn rounded_rect_path(hdc: HDC, rect: &Rect, radii: &CornerRadii) {
+        let (x, y, x2, y2) = (rect.x, rect.y, rect.x2(), rect.y2());
+        let CornerRadii {
+            top_left: tl,
+            top_right: tr,
+            bottom_right: br,
+            bottom_left: bl,
+        } = *radii;
+
+        unsafe {
+            SetArcDirection(hdc, AD_CLOCKWISE);
+            BeginPath(hdc);
+            MoveToEx(hdc, x + tl, y, None);
+            LineTo(hdc, x2 - tr, y);
+            if tr > 0 {
+                ArcTo(hdc, x2 - 2 * tr, y, x2, y + 2 * tr, x2 - tr, y, x2, y + tr);
+            }
+            LineTo(hdc, x2, y2 - br);
+            if br > 0 {
+                ArcTo(
+                    hdc,
+                    x2 - 2 * br,
+                    y2 - 2 * br,
+                    x2,
+                    y2,
+                    x2,
+                    y2 - br,
+                    x2 - br,
+                    y2,
+                );
+            }
+            LineTo(hdc, x + bl, y2);
+            if bl > 0 {
+                ArcTo(hdc, x, y2 - 2 * bl, x + 2 * bl, y2, x + bl, y2, x, y2 - bl);
+            }
+            LineTo(hdc, x, y + tl);
+            if tl > 0 {
+                ArcTo(hdc, x, y, x + 2 * tl, y + 2 * tl, x, y + tl, x + tl, y);
+            }
+            EndPath(hdc);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn rules() -> Vec<StyleRule> {
+        let styles = |padding_x| {
+            Arc::new(StyleOptions {
+                padding_x,
+                ..Default::default()
+            })
+        };
+
+        vec![
+            StyleRule {
+                condition: StyleCondition::State("critical".to_string()),
+                styles: styles(1),
+            },
+            StyleRule {
+                condition: StyleCondition::Above(90.0),
+                styles: styles(2),
+            },
+            StyleRule {
+                condition: StyleCondition::Matches(Regex::new("^success").unwrap()),
+                styles: styles(3),
+            },
+        ]
+    }
+
+    #[test]
+    fn component_styles_resolve_base_when_no_rule_matches() {
+        let styles = ComponentStyles::new(StyleOptions::default(), rules());
+        assert_eq!(styles.resolve("CPU 42%").padding_x, 0);
+    }
+
+    #[test]
+    fn component_styles_resolve_number_above_threshold() {
+        let styles = ComponentStyles::new(StyleOptions::default(), rules());
+        assert_eq!(styles.resolve("CPU 93.5%").padding_x, 2);
+        assert_eq!(styles.resolve("CPU 90%").padding_x, 0);
+    }
+
+    #[test]
+    fn component_styles_resolve_regex() {
+        let styles = ComponentStyles::new(StyleOptions::default(), rules());
+        assert_eq!(styles.resolve("success: build 12").padding_x, 3);
+    }
+
+    #[test]
+    fn component_styles_resolve_first_matching_rule() {
+        let styles = ComponentStyles::new(StyleOptions::default(), rules());
+        styles.set_state(Some("critical".to_string()));
+        assert_eq!(styles.resolve("CPU 93.5%").padding_x, 1);
+
+        styles.set_state(None);
+        assert_eq!(styles.resolve("CPU 93.5%").padding_x, 2);
+    }
+
+    #[test]
+    fn component_styles_explicit_state_takes_precedence() {
+        let styles = ComponentStyles::new(StyleOptions::default(), rules());
+        assert_eq!(
+            styles
+                .resolve_with_state("CPU 42%", Some("critical"))
+                .padding_x,
+            1
+        );
+
+        styles.set_state(Some("ok".to_string()));
+        assert_eq!(
+            styles
+                .resolve_with_state("CPU 42%", Some("critical"))
+                .padding_x,
+            0
+        );
+    }
+
+    #[test]
+    fn constrain_width_fixed_takes_precedence() {
+        let styles = StyleOptions {
+            min_width: Some(50),
+            max_width: Some(100),
+            fixed_width: Some(80),
+            ..Default::default()
+        };
+
+        assert_eq!(styles.constrain_width(10), 80);
+        assert_eq!(styles.constrain_width(200), 80);
+    }
+
+    #[test]
+    fn constrain_width_min_max() {
+        let styles = StyleOptions {
+            min_width: Some(50),
+            max_width: Some(100),
+            ..Default::default()
+        };
+
+        assert_eq!(styles.constrain_width(10), 50);
+        assert_eq!(styles.constrain_width(75), 75);
+        assert_eq!(styles.constrain_width(200), 100);
+    }
+
+    #[test]
+    fn rounded_radius_is_diameter_of_corners() {
+        assert_eq!(
+            BorderStyle::Rounded { radius: 8 }.radii(),
+            CornerRadii::uniform(4)
+        );
+    }
+
+    #[test]
+    fn corner_radii_clamp_to_half_of_smallest_side() {
+        let rect = Rect {
+            x: 0,
+            y: 0,
+            width: 100,
+            height: 20,
+        };
+        let radii = CornerRadii {
+            top_left: 4,
+            top_right: 50,
+            bottom_right: -3,
+            bottom_left: 10,
+        };
+
+        assert_eq!(
+            radii.clamp_to(&rect),
+            CornerRadii {
+                top_left: 4,
+                top_right: 10,
+                bottom_right: 0,
+                bottom_left: 10,
+            }
+        );
+    }
+
+    #[test]
+    fn side_rects_underline_only() {
+        let rect = Rect {
+            x: 10,
+            y: 0,
+            width: 50,
+            height: 20,
+        };
+        let sides = BorderSides {
+            bottom: true,
+            ..BorderSides::none()
+        };
+
+        let rects = sides.side_rects(&rect, 2, &CornerRadii::default());
+        assert_eq!(
+            rects,
+            vec![Rect {
+                x: 10,
+                y: 18,
+                width: 50,
+                height: 2,
+            }]
+        );
+    }
+
+    #[test]
+    fn side_rects_are_shortened_by_corner_radii() {
+        let rect = Rect {
+            x: 0,
+            y: 0,
+            width: 50,
+            height: 20,
+        };
+        let sides = BorderSides {
+            top: true,
+            ..BorderSides::none()
+        };
+        let radii = CornerRadii {
+            top_left: 5,
+            top_right: 3,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            sides.side_rects(&rect, 1, &radii),
+            vec![Rect {
+                x: 5,
+                y: 0,
+                width: 42,
+                height: 1,
+            }]
+        );
+    }
 }
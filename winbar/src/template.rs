@@ -0,0 +1,623 @@
+//! Format strings shared by components.
+//!
+//! A template is text with placeholders replaced by the named fields a component exposes, e.g.,
+//! `"{cpu.usage:>3}% {mem.used|bytes}"`. A placeholder is written as `{name|filter|...:spec}`:
+//!
+//! - Filters transform the value of the field. See [`Filter`].
+//! - The spec is `[[fill]align][width][.precision]`, where the alignment is one of `<`, `>` and
+//!   `^`. The precision is the number of decimals of numbers, or the maximum number of characters
+//!   of text.
+//!
+//! Sections are only rendered if a field is truthy (`{?name}...{/}`) or not (`{!name}...{/}`).
+//! Braces are escaped by doubling them (`{{` and `}}`).
+use std::{
+    collections::{BTreeMap, HashMap},
+    hash::BuildHasher,
+};
+
+use anyhow::{anyhow, bail, Result};
+
+/// The value of a field.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Text(String),
+    Number(f64),
+    Bool(bool),
+}
+
+impl Value {
+    /// Whether the value enables sections: non-empty text, non-zero numbers and `true`.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Self::Text(text) => !text.is_empty(),
+            Self::Number(number) => *number != 0.0,
+            Self::Bool(value) => *value,
+        }
+    }
+
+    /// The value as a number, parsing text if needed.
+    pub fn as_number(&self) -> Option<f64> {
+        match self {
+            Self::Text(text) => text.trim().parse().ok(),
+            Self::Number(number) => Some(*number),
+            Self::Bool(value) => Some(if *value { 1.0 } else { 0.0 }),
+        }
+    }
+
+    fn format(&self, precision: Option<usize>) -> String {
+        match (self, precision) {
+            (Self::Text(text), Some(precision)) => text.chars().take(precision).collect(),
+            (Self::Text(text), None) => text.clone(),
+            (Self::Number(number), Some(precision)) => format!("{:.*}", precision, number),
+            (Self::Number(number), None) => number.to_string(),
+            (Self::Bool(value), _) => value.to_string(),
+        }
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Self::Text(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Self::Text(value.to_string())
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Self::Bool(value)
+    }
+}
+
+macro_rules! number_from {
+    ($($ty:ty),*) => {
+        $(impl From<$ty> for Value {
+            fn from(value: $ty) -> Self {
+                Self::Number(value as f64)
+            }
+        })*
+    };
+}
+
+number_from!(f32, f64, i32, i64, u32, u64, usize);
+
+/// The named fields exposed to templates.
+pub trait Fields {
+    fn field(&self, name: &str) -> Option<Value>;
+}
+
+impl<S: BuildHasher> Fields for HashMap<String, Value, S> {
+    fn field(&self, name: &str) -> Option<Value> {
+        self.get(name).cloned()
+    }
+}
+
+impl Fields for BTreeMap<String, Value> {
+    fn field(&self, name: &str) -> Option<Value> {
+        self.get(name).cloned()
+    }
+}
+
+/// A transformation of the value of a field.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Filter {
+    /// Formats a number of bytes with binary units, e.g., `1.5 GiB`. The precision defaults to 1.
+    Bytes,
+    /// Formats a number of seconds with its two largest units, e.g., `1h 5m`.
+    Duration,
+    /// Formats a percentage from 0 to 100, like the usage fields of the metric components, e.g.,
+    /// `42%`. The precision defaults to 0.
+    Percent,
+    Upper,
+    Lower,
+    /// Looks up an icon, written as `icon(a,b,c)` or `icon(key=a,_=b)`. Without keys, the value
+    /// is a percentage selecting one of the icons, from the first at 0 to the last at 100. With
+    /// keys, the icon of the text of the value is selected, falling back to the `_` key.
+    Icon(Vec<String>),
+}
+
+impl Filter {
+    fn parse(filter: &str) -> Result<Self> {
+        let (name, args) = match filter.split_once('(') {
+            Some((name, args)) => {
+                let args = args
+                    .strip_suffix(')')
+                    .ok_or_else(|| anyhow!("Unclosed arguments of filter: {}", filter))?;
+                (name.trim(), Some(args))
+            }
+            None => (filter.trim(), None),
+        };
+
+        Ok(match (name, args) {
+            ("bytes", None) => Self::Bytes,
+            ("duration", None) => Self::Duration,
+            ("percent", None) => Self::Percent,
+            ("upper", None) => Self::Upper,
+            ("lower", None) => Self::Lower,
+            ("icon", Some(args)) if !args.is_empty() => {
+                Self::Icon(args.split(',').map(str::to_string).collect())
+            }
+            ("icon", _) => bail!("The icon filter needs at least one icon"),
+            _ => bail!("Unknown filter: {}", filter),
+        })
+    }
+
+    /// Applies the filter. Filters formatting numbers use up the precision.
+    fn apply(&self, value: Value, precision: &mut Option<usize>) -> Value {
+        let number = value.as_number();
+        match (self, number) {
+            (Self::Bytes, Some(bytes)) => Value::Text(bytes_text(bytes, precision.take())),
+            (Self::Duration, Some(seconds)) => Value::Text(duration_text(seconds)),
+            (Self::Percent, Some(percent)) => {
+                Value::Text(format!("{:.*}%", precision.take().unwrap_or(0), percent))
+            }
+            (Self::Upper, _) => Value::Text(value.format(None).to_uppercase()),
+            (Self::Lower, _) => Value::Text(value.format(None).to_lowercase()),
+            (Self::Icon(icons), _) => icon(icons, &value, number)
+                .map(|icon| Value::Text(icon.to_string()))
+                .unwrap_or(value),
+            // filters of numbers leave other values as is
+            _ => value,
+        }
+    }
+}
+
+const BYTE_UNITS: [&str; 6] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB"];
+
+fn bytes_text(bytes: f64, precision: Option<usize>) -> String {
+    let mut value = bytes;
+    let mut unit = 0;
+    while value.abs() >= 1024.0 && unit < BYTE_UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+
+    if unit == 0 {
+        format!("{} {}", value.round(), BYTE_UNITS[0])
+    } else {
+        format!("{:.*} {}", precision.unwrap_or(1), value, BYTE_UNITS[unit])
+    }
+}
+
+fn duration_text(seconds: f64) -> String {
+    let seconds = seconds.max(0.0) as u64;
+    let units = [
+        (seconds / 86400, "d"),
+        (seconds / 3600 % 24, "h"),
+        (seconds / 60 % 60, "m"),
+        (seconds % 60, "s"),
+    ];
+
+    let first = units
+        .iter()
+        .position(|(amount, _)| *amount > 0)
+        .unwrap_or(units.len() - 1);
+    units[first..]
+        .iter()
+        .take(2)
+        .filter(|(amount, _)| *amount > 0 || seconds == 0)
+        .map(|(amount, unit)| format!("{}{}", amount, unit))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn icon<'a>(icons: &'a [String], value: &Value, number: Option<f64>) -> Option<&'a str> {
+    if icons.iter().any(|icon| icon.contains('=')) {
+        let text = value.format(None);
+        let lookup = |key: &str| {
+            icons.iter().find_map(|icon| {
+                icon.split_once('=')
+                    .filter(|(k, _)| *k == key)
+                    .map(|(_, icon)| icon)
+            })
+        };
+        return lookup(&text).or_else(|| lookup("_"));
+    }
+
+    let percent = number?.clamp(0.0, 100.0);
+    let index = (percent / 100.0 * (icons.len() - 1) as f64).round() as usize;
+    icons.get(index).map(String::as_str)
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Align {
+    #[default]
+    Left,
+    Right,
+    Center,
+}
+
+/// How a value is padded and how precise it is.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Spec {
+    pub fill: char,
+    pub align: Align,
+    pub width: usize,
+    pub precision: Option<usize>,
+}
+
+impl Default for Spec {
+    fn default() -> Self {
+        Self {
+            fill: ' ',
+            align: Align::Left,
+            width: 0,
+            precision: None,
+        }
+    }
+}
+
+impl Spec {
+    fn parse(spec: &str) -> Result<Self> {
+        let align = |c: char| match c {
+            '<' => Some(Align::Left),
+            '>' => Some(Align::Right),
+            '^' => Some(Align::Center),
+            _ => None,
+        };
+
+        let mut result = Self::default();
+        let mut chars = spec.chars();
+        let mut rest = spec;
+        let first = chars.next();
+        let second = chars.next();
+        if let (Some(fill), Some(a)) = (first, second.and_then(align)) {
+            result.fill = fill;
+            result.align = a;
+            rest = &spec[fill.len_utf8() + 1..];
+        } else if let Some(a) = first.and_then(align) {
+            result.align = a;
+            rest = &spec[1..];
+        }
+
+        let (width, precision) = match rest.split_once('.') {
+            Some((width, precision)) => (width, Some(precision)),
+            None => (rest, None),
+        };
+        if !width.is_empty() {
+            result.width = width
+                .parse()
+                .map_err(|_| anyhow!("Invalid width in format spec: {}", spec))?;
+        }
+        if let Some(precision) = precision {
+            result.precision = Some(
+                precision
+                    .parse()
+                    .map_err(|_| anyhow!("Invalid precision in format spec: {}", spec))?,
+            );
+        }
+
+        Ok(result)
+    }
+
+    fn pad(&self, text: String) -> String {
+        let len = text.chars().count();
+        if len >= self.width {
+            return text;
+        }
+
+        let padding = self.width - len;
+        let (left, right) = match self.align {
+            Align::Left => (0, padding),
+            Align::Right => (padding, 0),
+            Align::Center => (padding / 2, padding - padding / 2),
+        };
+        let fill = |n| std::iter::repeat_n(self.fill, n).collect::<String>();
+        format!("{}{}{}", fill(left), text, fill(right))
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Placeholder {
+    field: String,
+    filters: Vec<Filter>,
+    spec: Spec,
+}
+
+impl Placeholder {
+    fn parse(placeholder: &str) -> Result<Self> {
+        // the spec starts at the first colon outside of filter arguments
+        let mut depth = 0;
+        let mut spec_start = None;
+        for (i, c) in placeholder.char_indices() {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                ':' if depth == 0 => {
+                    spec_start = Some(i);
+                    break;
+                }
+                _ => {}
+            }
+        }
+        let (head, spec) = match spec_start {
+            Some(i) => (&placeholder[..i], Spec::parse(&placeholder[i + 1..])?),
+            None => (placeholder, Spec::default()),
+        };
+
+        let mut parts = split_outside_parens(head, '|').into_iter();
+        let field = parts.next().unwrap_or_default().trim().to_string();
+        if field.is_empty() {
+            bail!("Placeholder without a field: {{{}}}", placeholder);
+        }
+
+        Ok(Self {
+            field,
+            filters: parts.map(Filter::parse).collect::<Result<_>>()?,
+            spec,
+        })
+    }
+
+    fn render(&self, fields: &dyn Fields) -> String {
+        let text = match fields.field(&self.field) {
+            Some(value) => {
+                let mut precision = self.spec.precision;
+                let value = self
+                    .filters
+                    .iter()
+                    .fold(value, |value, filter| filter.apply(value, &mut precision));
+                value.format(precision)
+            }
+            None => String::new(),
+        };
+
+        self.spec.pad(text)
+    }
+}
+
+fn split_outside_parens(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            c if c == separator && depth == 0 => {
+                parts.push(&text[start..i]);
+                start = i + c.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(&text[start..]);
+    parts
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Node {
+    Text(String),
+    Placeholder(Placeholder),
+    Section {
+        field: String,
+        inverted: bool,
+        nodes: Vec<Node>,
+    },
+}
+
+/// A section being parsed, i.e., its field and whether it's inverted, and its nodes so far. The top
+/// level has no field.
+type Frame = (Option<(String, bool)>, Vec<Node>);
+
+/// A parsed template.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Template {
+    nodes: Vec<Node>,
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self> {
+        // the sections being parsed, with the top level at the bottom
+        let mut stack: Vec<Frame> = vec![(None, Vec::new())];
+        let mut text = String::new();
+        let mut chars = template.char_indices().peekable();
+
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '{' if chars.peek().map(|(_, c)| *c) == Some('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek().map(|(_, c)| *c) == Some('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => bail!("Unmatched '}}' at {} in template: {}", i, template),
+                '{' => {
+                    let end = template[i..].find('}').map(|end| i + end).ok_or_else(|| {
+                        anyhow!("Unclosed '{{' at {} in template: {}", i, template)
+                    })?;
+                    let content = &template[i + 1..end];
+                    while chars.peek().is_some_and(|(j, _)| *j <= end) {
+                        chars.next();
+                    }
+
+                    let nodes = &mut stack.last_mut().unwrap().1;
+                    if !text.is_empty() {
+                        nodes.push(Node::Text(std::mem::take(&mut text)));
+                    }
+
+                    if let Some(field) = content.strip_prefix('?') {
+                        stack.push((Some((field.trim().to_string(), false)), Vec::new()));
+                    } else if let Some(field) = content.strip_prefix('!') {
+                        stack.push((Some((field.trim().to_string(), true)), Vec::new()));
+                    } else if let Some(closed) = content.strip_prefix('/') {
+                        let (section, nodes) = stack.pop().unwrap();
+                        let Some((field, inverted)) = section else {
+                            bail!("Unopened section closed in template: {}", template);
+                        };
+                        if !closed.is_empty() && closed.trim() != field {
+                            bail!(
+                                "Section {} closed as {} in template: {}",
+                                field,
+                                closed,
+                                template
+                            );
+                        }
+
+                        stack.last_mut().unwrap().1.push(Node::Section {
+                            field,
+                            inverted,
+                            nodes,
+                        });
+                    } else {
+                        nodes.push(Node::Placeholder(Placeholder::parse(content)?));
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+
+        if stack.len() > 1 {
+            bail!("Unclosed section in template: {}", template);
+        }
+        let mut nodes = stack.pop().unwrap().1;
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+
+        Ok(Self { nodes })
+    }
+
+    /// Renders the template with the fields. Missing fields are rendered as empty text.
+    pub fn render(&self, fields: &dyn Fields) -> String {
+        let mut output = String::new();
+        Self::render_nodes(&self.nodes, fields, &mut output);
+        output
+    }
+
+    /// The names of the fields used by the template.
+    pub fn fields(&self) -> Vec<&str> {
+        fn collect<'a>(nodes: &'a [Node], fields: &mut Vec<&'a str>) {
+            for node in nodes {
+                match node {
+                    Node::Text(_) => {}
+                    Node::Placeholder(placeholder) => fields.push(&placeholder.field),
+                    Node::Section { field, nodes, .. } => {
+                        fields.push(field);
+                        collect(nodes, fields);
+                    }
+                }
+            }
+        }
+
+        let mut fields = Vec::new();
+        collect(&self.nodes, &mut fields);
+        fields
+    }
+
+    fn render_nodes(nodes: &[Node], fields: &dyn Fields, output: &mut String) {
+        for node in nodes {
+            match node {
+                Node::Text(text) => output.push_str(text),
+                Node::Placeholder(placeholder) => output.push_str(&placeholder.render(fields)),
+                Node::Section {
+                    field,
+                    inverted,
+                    nodes,
+                } => {
+                    let truthy = fields.field(field).is_some_and(|value| value.is_truthy());
+                    if truthy != *inverted {
+                        Self::render_nodes(nodes, fields, output);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fields() -> HashMap<String, Value> {
+        HashMap::from([
+            ("cpu.usage".to_string(), Value::from(7)),
+            ("mem.used".to_string(), Value::from(1610612736u64)),
+            ("load".to_string(), Value::from(0.4234)),
+            ("usage".to_string(), Value::from(42.34)),
+            ("uptime".to_string(), Value::from(3900)),
+            ("title".to_string(), Value::from("winbar")),
+            ("state".to_string(), Value::from("charging")),
+            ("charging".to_string(), Value::from(true)),
+            ("empty".to_string(), Value::from("")),
+        ])
+    }
+
+    fn render(template: &str) -> String {
+        Template::parse(template).unwrap().render(&fields())
+    }
+
+    #[test]
+    fn placeholders_are_replaced() {
+        assert_eq!(render("{cpu.usage:>3}% {mem.used|bytes}"), "  7% 1.5 GiB");
+        assert_eq!(render("[{title}] {missing}!"), "[winbar] !");
+        assert_eq!(render("{{{title}}}"), "{winbar}");
+    }
+
+    #[test]
+    fn specs_pad_and_round() {
+        assert_eq!(render("{cpu.usage:0>3}"), "007");
+        assert_eq!(render("{title:*^10}"), "**winbar**");
+        assert_eq!(render("{title:<8}|"), "winbar  |");
+        assert_eq!(render("{load:.2}"), "0.42");
+        assert_eq!(render("{title:.3}"), "win");
+    }
+
+    #[test]
+    fn filters_format_values() {
+        assert_eq!(render("{usage|percent}"), "42%");
+        assert_eq!(render("{usage|percent:.1}"), "42.3%");
+        assert_eq!(render("{cpu.usage|percent}"), "7%");
+        assert_eq!(render("{mem.used|bytes:.2}"), "1.50 GiB");
+        assert_eq!(render("{uptime|duration}"), "1h 5m");
+        assert_eq!(render("{title|upper}"), "WINBAR");
+        assert_eq!(render("{cpu.usage|icon(a,b,c)}"), "a");
+        assert_eq!(render("{state|icon(charging=+,_=-)}"), "+");
+        assert_eq!(render("{title|icon(charging=+,_=-)}"), "-");
+        // number filters leave text as is
+        assert_eq!(render("{title|bytes}"), "winbar");
+    }
+
+    #[test]
+    fn sections_are_conditional() {
+        assert_eq!(render("{?charging}+{/}{!charging}-{/charging}"), "+");
+        assert_eq!(render("{?empty}x{/}{?missing}y{/}{!missing}z{/}"), "z");
+        assert_eq!(render("{?title}a{?charging}b{/}c{/}"), "abc");
+    }
+
+    #[test]
+    fn invalid_templates_fail() {
+        for template in [
+            "{title",
+            "title}",
+            "{title|unknown}",
+            "{title:x}",
+            "{?title}",
+            "{/}",
+            "{?a}{/b}",
+            "{}",
+            "{title|icon()}",
+        ] {
+            assert!(Template::parse(template).is_err(), "{}", template);
+        }
+    }
+
+    #[test]
+    fn duration_uses_two_largest_units() {
+        assert_eq!(duration_text(0.0), "0s");
+        assert_eq!(duration_text(59.0), "59s");
+        assert_eq!(duration_text(3600.0), "1h");
+        assert_eq!(duration_text(90061.0), "1d 1h");
+    }
+
+    #[test]
+    fn fields_are_listed() {
+        let template = Template::parse("{a} {?b}{c|bytes}{/}").unwrap();
+        assert_eq!(template.fields(), vec!["a", "b", "c"]);
+    }
+}
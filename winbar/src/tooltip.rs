@@ -0,0 +1,139 @@
+use windows::{
+    core::PWSTR,
+    Win32::{
+        Foundation::{HWND, LPARAM, POINT, WPARAM},
+        System::LibraryLoader::GetModuleHandleW,
+        UI::{
+            Controls::{
+                InitCommonControlsEx, ICC_WIN95_CLASSES, INITCOMMONCONTROLSEX, TOOLTIPS_CLASSW,
+                TTF_ABSOLUTE, TTF_TRACK, TTM_ADDTOOLW, TTM_SETMAXTIPWIDTH, TTM_TRACKACTIVATE,
+                TTM_TRACKPOSITION, TTM_UPDATETIPTEXTW, TTS_ALWAYSTIP, TTS_NOPREFIX, TTTOOLINFOW,
+            },
+            WindowsAndMessaging::{
+                CreateWindowExW, DestroyWindow, GetCursorPos, SendMessageW, CW_USEDEFAULT,
+                WINDOW_STYLE, WS_EX_TOPMOST, WS_POPUP,
+            },
+        },
+    },
+};
+
+use crate::windows_api::WindowsApi;
+
+/// The maximum width of a tooltip, in pixels, after which its text wraps.
+const MAX_TOOLTIP_WIDTH: isize = 500;
+
+/// The vertical offset of a tooltip from the mouse, such that it's not hidden by the cursor.
+const CURSOR_OFFSET: i32 = 20;
+
+/// A tracking tooltip window, shown at the mouse when requested.
+pub struct TooltipWindow {
+    hwnd: HWND,
+    parent: HWND,
+    /// The null-terminated text of the tooltip, which has to outlive its use by the tooltip control.
+    text: Vec<u16>,
+}
+
+impl TooltipWindow {
+    pub fn create(parent: HWND) -> Self {
+        unsafe {
+            let controls = INITCOMMONCONTROLSEX {
+                dwSize: std::mem::size_of::<INITCOMMONCONTROLSEX>() as u32,
+                dwICC: ICC_WIN95_CLASSES,
+            };
+            InitCommonControlsEx(&controls);
+
+            let hwnd = CreateWindowExW(
+                WS_EX_TOPMOST,
+                TOOLTIPS_CLASSW,
+                None,
+                WS_POPUP | WINDOW_STYLE(TTS_NOPREFIX | TTS_ALWAYSTIP),
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                CW_USEDEFAULT,
+                parent,
+                None,
+                GetModuleHandleW(None).unwrap_or_default(),
+                None,
+            );
+
+            let mut tooltip = Self {
+                hwnd,
+                parent,
+                text: vec![0],
+            };
+            let info = tooltip.tool_info();
+            SendMessageW(
+                hwnd,
+                TTM_ADDTOOLW,
+                WPARAM(0),
+                LPARAM(&info as *const TTTOOLINFOW as isize),
+            );
+            SendMessageW(
+                hwnd,
+                TTM_SETMAXTIPWIDTH,
+                WPARAM(0),
+                LPARAM(MAX_TOOLTIP_WIDTH),
+            );
+
+            tooltip
+        }
+    }
+
+    /// Shows the tooltip with the given text below the mouse.
+    pub fn show(&mut self, text: &str) {
+        self.text = WindowsApi::str_to_u16_slice(text);
+        self.text.push(0);
+
+        let mut cursor = POINT::default();
+        unsafe {
+            if let Err(e) = GetCursorPos(&mut cursor) {
+                tracing::error!("Could not get cursor position for tooltip: {}", e);
+                return;
+            }
+
+            let info = self.tool_info();
+            let info = LPARAM(&info as *const TTTOOLINFOW as isize);
+            // the low and high words hold the x and y coordinates
+            let position =
+                (cursor.x as u16 as isize) | ((cursor.y + CURSOR_OFFSET) as u16 as isize) << 16;
+
+            SendMessageW(self.hwnd, TTM_UPDATETIPTEXTW, WPARAM(0), info);
+            SendMessageW(self.hwnd, TTM_TRACKPOSITION, WPARAM(0), LPARAM(position));
+            SendMessageW(self.hwnd, TTM_TRACKACTIVATE, WPARAM(1), info);
+        }
+    }
+
+    pub fn hide(&mut self) {
+        let info = self.tool_info();
+        unsafe {
+            SendMessageW(
+                self.hwnd,
+                TTM_TRACKACTIVATE,
+                WPARAM(0),
+                LPARAM(&info as *const TTTOOLINFOW as isize),
+            );
+        }
+    }
+
+    fn tool_info(&mut self) -> TTTOOLINFOW {
+        TTTOOLINFOW {
+            cbSize: std::mem::size_of::<TTTOOLINFOW>() as u32,
+            uFlags: TTF_TRACK | TTF_ABSOLUTE,
+            hwnd: self.parent,
+            uId: 0,
+            lpszText: PWSTR(self.text.as_mut_ptr()),
+            ..Default::default()
+        }
+    }
+}
+
+impl Drop for TooltipWindow {
+    fn drop(&mut self) {
+        unsafe {
+            if let Err(e) = DestroyWindow(self.hwnd) {
+                tracing::error!("Could not destroy tooltip window: {}", e);
+            }
+        }
+    }
+}
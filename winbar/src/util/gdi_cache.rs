@@ -0,0 +1,222 @@
+//! A cache of the fonts, pens and brushes used to draw components, such that they're created once
+//! rather than on every paint.
+//!
+//! Cached objects are owned by the cache: they must not be deleted by the caller, and are deleted
+//! by [`collect`] once the cache is invalidated (e.g., when the config changes) or grows too large.
+use std::{collections::HashMap, sync::Mutex};
+
+use lazy_static::lazy_static;
+use windows::Win32::Graphics::Gdi::{DeleteObject, HBRUSH, HFONT, HGDIOBJ, HPEN};
+
+use crate::styles::{StyleOptions, Styles};
+
+/// The number of objects above which the cache is cleared on the next collection. Styles that
+/// change with the value of a component (e.g., a color per CPU load) otherwise add objects
+/// indefinitely.
+pub const MAX_OBJECTS: usize = 256;
+
+lazy_static! {
+    static ref CACHE: Mutex<GdiCache> = Mutex::new(GdiCache::default());
+}
+
+/// Identifies a GDI object by the values it was created with.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum GdiKey {
+    Font {
+        name: String,
+        size: i32,
+        weight: Option<i32>,
+        italic: bool,
+        underline: bool,
+        strikethrough: bool,
+    },
+    /// A geometric pen (see [`Styles::geometric_pen`]).
+    Pen {
+        color: u32,
+        width: i32,
+    },
+    Brush {
+        color: u32,
+    },
+}
+
+/// The number of cache lookups that found an existing object, and those that created one.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl CacheStats {
+    /// The fraction of lookups that found an existing object, or 1 if there were no lookups.
+    pub fn hit_rate(&self) -> f64 {
+        match self.hits + self.misses {
+            0 => 1.0,
+            total => self.hits as f64 / total as f64,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct GdiCache {
+    objects: HashMap<GdiKey, HGDIOBJ>,
+    invalidated: bool,
+    stats: CacheStats,
+}
+
+impl GdiCache {
+    /// Returns the cached object for the key, creating it if there is none.
+    pub fn get_or_insert_with<F>(&mut self, key: GdiKey, create: F) -> HGDIOBJ
+    where
+        F: FnOnce() -> HGDIOBJ,
+    {
+        if let Some(object) = self.objects.get(&key) {
+            self.stats.hits += 1;
+            return *object;
+        }
+
+        self.stats.misses += 1;
+        *self.objects.entry(key).or_insert_with(create)
+    }
+
+    /// Marks all objects to be removed on the next eviction.
+    pub fn invalidate(&mut self) {
+        self.invalidated = true;
+    }
+
+    /// Removes all objects if the cache was invalidated or holds more than [`MAX_OBJECTS`],
+    /// returning them to be deleted.
+    pub fn evict(&mut self) -> Vec<HGDIOBJ> {
+        if !self.invalidated && self.objects.len() <= MAX_OBJECTS {
+            return Vec::new();
+        }
+
+        self.invalidated = false;
+        self.objects.drain().map(|(_, object)| object).collect()
+    }
+
+    /// Returns the stats since they were last taken.
+    pub fn take_stats(&mut self) -> CacheStats {
+        std::mem::take(&mut self.stats)
+    }
+
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+}
+
+/// A cached font with the given size and name, and the weight, italic, underline and strikethrough
+/// options of the styles.
+pub fn font(size: i32, name: &str, styles: &StyleOptions) -> HFONT {
+    let key = GdiKey::Font {
+        name: name.to_string(),
+        size,
+        weight: styles.font_weight,
+        italic: styles.italic,
+        underline: styles.underline,
+        strikethrough: styles.strikethrough,
+    };
+    let object = CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(key, || Styles::font(size, name, styles).into());
+    HFONT(object.0)
+}
+
+/// A cached geometric pen with the given color and width.
+pub fn pen(color: u32, width: i32) -> HPEN {
+    let object = CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(GdiKey::Pen { color, width }, || {
+            Styles::geometric_pen(color, width).into()
+        });
+    HPEN(object.0)
+}
+
+/// A cached solid brush with the given color.
+pub fn brush(color: u32) -> HBRUSH {
+    let object = CACHE
+        .lock()
+        .unwrap()
+        .get_or_insert_with(GdiKey::Brush { color }, || {
+            Styles::solid_brush(color).into()
+        });
+    HBRUSH(object.0)
+}
+
+/// Marks the cached objects to be deleted on the next collection, e.g., because the config changed.
+pub fn invalidate() {
+    CACHE.lock().unwrap().invalidate();
+}
+
+/// Deletes the cached objects if the cache was invalidated or grew too large.
+///
+/// This must only be called while none of the cached objects are selected into a device context,
+/// e.g., before painting.
+pub fn collect() {
+    let evicted = CACHE.lock().unwrap().evict();
+    for object in evicted {
+        unsafe {
+            DeleteObject(object);
+        }
+    }
+}
+
+/// Returns the cache stats since they were last taken.
+pub fn take_stats() -> CacheStats {
+    CACHE.lock().unwrap().take_stats()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn brush_key(color: u32) -> GdiKey {
+        GdiKey::Brush { color }
+    }
+
+    #[test]
+    fn cache_reuses_objects() {
+        let mut cache = GdiCache::default();
+        let mut created = 0;
+        let mut get = |cache: &mut GdiCache, color| {
+            cache.get_or_insert_with(brush_key(color), || {
+                created += 1;
+                HGDIOBJ(color as isize)
+            })
+        };
+
+        assert_eq!(get(&mut cache, 1), HGDIOBJ(1));
+        assert_eq!(get(&mut cache, 1), HGDIOBJ(1));
+        assert_eq!(get(&mut cache, 2), HGDIOBJ(2));
+        assert_eq!(created, 2);
+
+        let stats = cache.take_stats();
+        assert_eq!(stats, CacheStats { hits: 1, misses: 2 });
+        assert!((stats.hit_rate() - 1.0 / 3.0).abs() < f64::EPSILON);
+        assert_eq!(cache.take_stats(), CacheStats::default());
+    }
+
+    #[test]
+    fn cache_evicts_when_invalidated_or_full() {
+        let mut cache = GdiCache::default();
+        cache.get_or_insert_with(brush_key(1), || HGDIOBJ(1));
+        assert!(cache.evict().is_empty());
+
+        cache.invalidate();
+        assert_eq!(cache.evict(), vec![HGDIOBJ(1)]);
+        assert!(cache.is_empty());
+        assert!(cache.evict().is_empty());
+
+        for color in 0..=MAX_OBJECTS as u32 {
+            cache.get_or_insert_with(brush_key(color), || HGDIOBJ(color as isize));
+        }
+        assert_eq!(cache.evict().len(), MAX_OBJECTS + 1);
+        assert!(cache.is_empty());
+    }
+}
@@ -1,2 +1,4 @@
+pub mod gdi_cache;
 pub mod hex_parser;
 pub mod rect;
+pub mod truncate;
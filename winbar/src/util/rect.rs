@@ -1,6 +1,8 @@
 use windows::Win32::Foundation::RECT;
 
-#[derive(Clone, Copy, Debug, Default)]
+use crate::input::Point;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 pub struct Rect {
     /// The x value of the top left corner of the rect.
     pub x: i32,
@@ -22,6 +24,24 @@ impl Rect {
     pub fn y2(&self) -> i32 {
         self.y + self.height
     }
+
+    /// Returns whether the point is within the rect. The right and bottom edges are exclusive.
+    pub fn contains(&self, point: Point) -> bool {
+        (self.x..self.x2()).contains(&point.x) && (self.y..self.y2()).contains(&point.y)
+    }
+
+    /// Returns whether the rects overlap. Rects that only touch at an edge don't overlap.
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.x < other.x2() && other.x < self.x2() && self.y < other.y2() && other.y < self.y2()
+    }
+
+    /// Returns the point relative to the top left corner of the rect.
+    pub fn relative(&self, point: Point) -> Point {
+        Point {
+            x: point.x - self.x,
+            y: point.y - self.y,
+        }
+    }
 }
 
 impl From<Rect> for RECT {
@@ -34,3 +54,58 @@ impl From<Rect> for RECT {
         }
     }
 }
+
+impl From<RECT> for Rect {
+    fn from(value: RECT) -> Self {
+        Self {
+            x: value.left,
+            y: value.top,
+            width: value.right - value.left,
+            height: value.bottom - value.top,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn contains_excludes_right_and_bottom_edges() {
+        let rect = Rect {
+            x: 10,
+            y: 0,
+            width: 20,
+            height: 10,
+        };
+        assert!(rect.contains(Point { x: 10, y: 0 }));
+        assert!(rect.contains(Point { x: 29, y: 9 }));
+        assert!(!rect.contains(Point { x: 30, y: 5 }));
+        assert!(!rect.contains(Point { x: 15, y: 10 }));
+        assert!(!rect.contains(Point { x: 9, y: 5 }));
+    }
+
+    #[test]
+    fn intersects_excludes_touching_rects() {
+        let rect = Rect {
+            x: 10,
+            y: 0,
+            width: 20,
+            height: 10,
+        };
+        let left = Rect {
+            x: 0,
+            width: 10,
+            ..rect
+        };
+        let overlapping = Rect {
+            x: 29,
+            width: 10,
+            ..rect
+        };
+        assert!(rect.intersects(&rect));
+        assert!(!rect.intersects(&left));
+        assert!(rect.intersects(&overlapping));
+        assert!(overlapping.intersects(&rect));
+    }
+}
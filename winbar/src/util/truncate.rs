@@ -0,0 +1,110 @@
+/// The ellipsis inserted into truncated text.
+pub const ELLIPSIS: &str = "\u{2026}";
+
+/// Where text is cut, and the ellipsis inserted, when it's too wide.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Truncation {
+    /// Cut the start of the text (e.g., "…world").
+    Start,
+    /// Cut the middle of the text (e.g., "he…ld").
+    Middle,
+    /// Cut the end of the text (e.g., "hello…").
+    #[default]
+    End,
+}
+
+/// Truncates the text such that it fits within the max width, as measured by the given function.
+/// If the text already fits, it is returned unchanged. Otherwise, as many characters as possible are
+/// kept, with an ellipsis inserted where the text was cut.
+///
+/// If not even the ellipsis fits, an empty string is returned.
+pub fn truncate<F>(text: &str, max_width: i32, truncation: Truncation, measure: F) -> String
+where
+    F: Fn(&str) -> i32,
+{
+    if measure(text) <= max_width {
+        return text.to_string();
+    }
+
+    let chars = text.chars().collect::<Vec<char>>();
+    let build = |keep: usize| -> String {
+        match truncation {
+            Truncation::Start => {
+                let start = chars.len() - keep;
+                format!("{}{}", ELLIPSIS, chars[start..].iter().collect::<String>())
+            }
+            Truncation::Middle => {
+                let front = keep.div_ceil(2);
+                let back = keep / 2;
+                format!(
+                    "{}{}{}",
+                    chars[..front].iter().collect::<String>(),
+                    ELLIPSIS,
+                    chars[chars.len() - back..].iter().collect::<String>()
+                )
+            }
+            Truncation::End => format!("{}{}", chars[..keep].iter().collect::<String>(), ELLIPSIS),
+        }
+    };
+
+    // binary search for the largest number of characters to keep that still fits
+    let (mut low, mut high) = (0, chars.len());
+    let mut best = None;
+    while low <= high {
+        let mid = (low + high) / 2;
+        let candidate = build(mid);
+        if measure(&candidate) <= max_width {
+            best = Some(candidate);
+            low = mid + 1;
+        } else if mid == 0 {
+            break;
+        } else {
+            high = mid - 1;
+        }
+    }
+
+    best.unwrap_or_default()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn char_count(s: &str) -> i32 {
+        s.chars().count() as i32
+    }
+
+    #[test]
+    fn truncate_fitting_text_is_unchanged() {
+        assert_eq!(truncate("hello", 5, Truncation::End, char_count), "hello");
+    }
+
+    #[test]
+    fn truncate_end_works() {
+        assert_eq!(
+            truncate("hello world", 6, Truncation::End, char_count),
+            "hello…"
+        );
+    }
+
+    #[test]
+    fn truncate_start_works() {
+        assert_eq!(
+            truncate("hello world", 6, Truncation::Start, char_count),
+            "…world"
+        );
+    }
+
+    #[test]
+    fn truncate_middle_works() {
+        assert_eq!(
+            truncate("hello world", 6, Truncation::Middle, char_count),
+            "hel…ld"
+        );
+    }
+
+    #[test]
+    fn truncate_nothing_fits() {
+        assert_eq!(truncate("hello", 0, Truncation::End, char_count), "");
+    }
+}
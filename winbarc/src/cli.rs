@@ -35,4 +35,23 @@ pub enum WinbarSubcommand {
     Show,
     /// Sends a message to hide the status bar
     Hide,
+    /// Sends a message to set the state of a component, used to select conditional styles
+    SetState {
+        /// The id of the component
+        id: String,
+        /// The state to set. The state is cleared if not given.
+        state: Option<String>,
+    },
+    /// Sends a message to restart a component
+    Restart {
+        /// The id of the component
+        id: String,
+    },
+    /// Prints the status of every component
+    Status,
+    /// Prints the tooltip of a component
+    Tooltip {
+        /// The id of the component
+        id: String,
+    },
 }
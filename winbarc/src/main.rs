@@ -36,6 +36,22 @@ fn response_handler(payload: WinbarClientPayload) {
         ClientMessage::Error(msg) => {
             log!("Message was sent, but an error occurred: {}", msg);
         }
+        ClientMessage::ComponentStatus(reports) => {
+            for report in reports {
+                let id = report.id.as_deref().unwrap_or("<no id>");
+                match report.last_error {
+                    Some(error) => println!(
+                        "{}: {:?} ({} failures, last error: {})",
+                        id, report.status, report.failures, error
+                    ),
+                    None => println!("{}: {:?}", id, report.status),
+                }
+            }
+        }
+        ClientMessage::Tooltip(tooltip) => match tooltip {
+            Some(tooltip) => println!("{}", tooltip),
+            None => log!("The component has no tooltip"),
+        },
     }
     std::process::exit(0);
 }
@@ -114,6 +130,42 @@ async fn main() {
             .await
             .unwrap();
         }
+        WinbarSubcommand::SetState { id, state } => {
+            log!("Sending set component state payload...");
+            send.send(WinbarServerPayload {
+                id: 0,
+                message: ServerMessage::SetComponentState { id, state },
+            })
+            .await
+            .unwrap();
+        }
+        WinbarSubcommand::Restart { id } => {
+            log!("Sending restart component payload...");
+            send.send(WinbarServerPayload {
+                id: 0,
+                message: ServerMessage::RestartComponent { id },
+            })
+            .await
+            .unwrap();
+        }
+        WinbarSubcommand::Status => {
+            log!("Sending component status payload...");
+            send.send(WinbarServerPayload {
+                id: 0,
+                message: ServerMessage::GetComponentStatus,
+            })
+            .await
+            .unwrap();
+        }
+        WinbarSubcommand::Tooltip { id } => {
+            log!("Sending tooltip payload...");
+            send.send(WinbarServerPayload {
+                id: 0,
+                message: ServerMessage::GetTooltip { id },
+            })
+            .await
+            .unwrap();
+        }
     }
 
     tokio::select! {